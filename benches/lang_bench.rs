@@ -0,0 +1,126 @@
+use std::fs;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use regex::Regex;
+use toml::Value;
+use grackle::get;
+
+const FRAMES: u32 = 500;
+
+/// Roughly the lookups the editor makes in a frame with the menus, tools, status bar and outliner showing.
+fn frame(count: usize, axis: &str) -> usize {
+    let mut length = 0;
+    for _ in 0..4 {
+        length += get!("viewport.free").len();
+        length += get!("viewport.render_modes.wireframe").len();
+        length += get!("viewport.layouts.grid").len();
+    }
+    for tool in ["select", "move", "room", "measure"] {
+        length += get!(format!("tools.{}", tool)).len();
+        length += get!("tools.tooltip", "tool", tool, "key", "1").len();
+    }
+    length += get!("snap.grid").len();
+    length += get!("snap.granularity").len();
+    length += get!("snap.targets.title").len();
+    length += get!("status_bar.cursor", "view", axis, "coordinates", "X    1.000  Z    3.250").len();
+    length += get!("status_bar.grid_view", "size", 0.1, "spacing", 1.0).len();
+    length += get!("status_bar.selection", "x", "1.000", "y", "2.000", "z", "3.000").len();
+    for _ in 0..count {
+        length += get!("outliner.type.room").len();
+        length += get!("outliner.visible").len();
+        length += get!("outliner.locked").len();
+    }
+    length += get!("inspector.multiple", "count", count).len();
+    length
+}
+
+/// How lookups worked before templates were parsed ahead of time: walk the tables for every key,
+/// then build a regex for every placeholder.
+struct Baseline {
+    strings: Value,
+}
+
+impl Baseline {
+    fn get(&self, key: &str) -> String {
+        let mut current = &self.strings;
+        for key in key.split('.') {
+            match current.get(key) {
+                Some(next) => current = next,
+                None => return format!("<{}>", key),
+            }
+        }
+        current.as_str().map_or(format!("<{}>", key), str::to_owned)
+    }
+
+    fn get_template(&self, key: &str, pairs: &[(&str, &str)]) -> String {
+        let mut product = self.get(key);
+        for (name, value) in pairs {
+            let re = Regex::new(&format!(r"\{{\s*{}\s*\}}", name)).unwrap();
+            product = re.replace_all(&product, *value).into_owned();
+        }
+        product
+    }
+
+    fn frame(&self, count: usize, axis: &str) -> usize {
+        let mut length = 0;
+        for _ in 0..4 {
+            length += self.get("viewport.free").len();
+            length += self.get("viewport.render_modes.wireframe").len();
+            length += self.get("viewport.layouts.grid").len();
+        }
+        for tool in ["select", "move", "room", "measure"] {
+            length += self.get(&format!("tools.{}", tool)).len();
+            length += self.get_template("tools.tooltip", &[("tool", tool), ("key", "1")]).len();
+        }
+        length += self.get("snap.grid").len();
+        length += self.get("snap.granularity").len();
+        length += self.get("snap.targets.title").len();
+        length += self.get_template("status_bar.cursor", &[("view", axis), ("coordinates", "X    1.000  Z    3.250")]).len();
+        length += self.get_template("status_bar.grid_view", &[("size", &format!("{}", 0.1)), ("spacing", &format!("{}", 1.0))]).len();
+        length += self.get_template("status_bar.selection", &[("x", "1.000"), ("y", "2.000"), ("z", "3.000")]).len();
+        for _ in 0..count {
+            length += self.get("outliner.type.room").len();
+            length += self.get("outliner.visible").len();
+            length += self.get("outliner.locked").len();
+        }
+        length += self.get_template("inspector.multiple", &[("count", &format!("{}", count))]).len();
+        length
+    }
+}
+
+/// The number of lookups `frame` makes.
+fn lookups(count: usize) -> usize {
+    4 * 3 + 4 * 2 + 3 + 3 + count * 3 + 1
+}
+
+fn measure(name: &str, count: usize, mut frame: impl FnMut() -> usize) -> Duration {
+    // The first frames intern keys and warm caches.
+    for _ in 0..10 {
+        black_box(frame());
+    }
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        black_box(frame());
+    }
+    let per_frame = start.elapsed() / FRAMES;
+    println!(
+        "{:<10} {:>10.2?} per frame, {:>8.0?} per lookup",
+        name,
+        per_frame,
+        per_frame / lookups(count) as u32,
+    );
+    per_frame
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let strings: Value = fs::read_to_string(grackle::common::lang::lang_dir().join("en-US.toml"))?.parse()?;
+    let baseline = Baseline { strings };
+
+    for count in [10, 100] {
+        println!("{} objects in the outliner, {} lookups a frame:", count, lookups(count));
+        let parsed = measure("parsed", count, || frame(count, "Top"));
+        let old = measure("baseline", count, || baseline.frame(count, "Top"));
+        println!("{:.1}× faster\n", old.as_secs_f64() / parsed.as_secs_f64());
+    }
+    Ok(())
+}
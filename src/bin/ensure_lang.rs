@@ -1,9 +1,12 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 use toml::Value;
 
+/// What older versions of this tool filled missing keys in with.
+const MISSING_PREFIX: &str = "MISSING ";
+
 #[derive(Debug, Deserialize, Serialize)]
 struct LangFile {
     #[serde(flatten)]
@@ -11,7 +14,7 @@ struct LangFile {
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let lang_dir = Path::new("assets/default/lang");
+    let lang_dir = grackle::common::lang::lang_dir();
     
     // Read the reference en-US.toml file
     let en_us_path = lang_dir.join("en-US.toml");
@@ -89,71 +92,64 @@ fn process_lang_file(path: &PathBuf, reference_keys: &[String]) -> Result<(), Bo
     // Read the existing file
     let content = fs::read_to_string(path)?;
     let mut value: Value = toml::from_str(&content)?;
+    let filename = path.file_name().unwrap().to_string_lossy();
     
-    let mut missing_keys = Vec::new();
-    let mut added_count = 0;
-    
-    // Check each reference key
+    // Missing keys fall back to other languages at runtime (see pack.toml), so they're only reported.
+    // Placeholders left by older versions of this tool would hide the fallback, so they're removed.
+    let mut removed_count = 0;
     for key in reference_keys {
-        if !key_exists(&value, key) {
-            missing_keys.push(key.clone());
+        if value_at(&value, key).and_then(Value::as_str).is_some_and(|text| text.starts_with(MISSING_PREFIX)) {
+            remove_nested_key(&mut value, key);
+            removed_count += 1;
+            println!("  Removed placeholder: {}", key);
         }
     }
     
-    // Add missing keys
-    for key in missing_keys {
-        let missing_value = format!("MISSING {}", key);
-        set_nested_key(&mut value, &key, Value::String(missing_value));
-        added_count += 1;
-        println!("  Added missing key: {}", key);
-    }
+    let missing_count = reference_keys
+        .iter()
+        .filter(|key| value_at(&value, key).is_none())
+        .inspect(|key| println!("  Missing key: {}", key))
+        .count();
     
-    if added_count > 0 {
+    if removed_count > 0 {
         // Write the updated file back
         let updated_content = toml::to_string_pretty(&value)?;
         fs::write(path, updated_content)?;
-        println!("  Updated {} with {} missing keys", path.file_name().unwrap().to_string_lossy(), added_count);
+        println!("  Removed {} placeholders from {}", removed_count, filename);
+    }
+    if missing_count > 0 {
+        println!("  {} keys missing from {}; they fall back to another language", missing_count, filename);
     } else {
-        println!("  No missing keys found in {}", path.file_name().unwrap().to_string_lossy());
+        println!("  No missing keys found in {}", filename);
     }
     
     Ok(())
 }
 
-fn key_exists(value: &Value, key: &str) -> bool {
-    let parts: Vec<&str> = key.split('.').collect();
+fn value_at<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
     let mut current = value;
     
-    for part in parts {
-        match current {
-            Value::Table(table) => {
-                if let Some(next_value) = table.get(part) {
-                    current = next_value;
-                } else {
-                    return false;
-                }
-            }
-            _ => return false,
-        }
+    for part in key.split('.') {
+        current = current.as_table()?.get(part)?;
     }
     
-    true
+    Some(current)
 }
 
-fn set_nested_key(value: &mut Value, key: &str, new_value: Value) {
+fn remove_nested_key(value: &mut Value, key: &str) {
     let parts: Vec<&str> = key.split('.').collect();
     let mut current = value;
     
-    // Navigate to the parent of the target key, creating tables as needed
+    // Navigate to the parent of the target key
     for part in &parts[..parts.len() - 1] {
-        if let Value::Table(table) = current {
-            let entry = table.entry(part.to_string()).or_insert_with(|| Value::Table(toml::map::Map::new()));
-            current = entry;
+        match current.as_table_mut().and_then(|table| table.get_mut(*part)) {
+            Some(next_value) => current = next_value,
+            None => return,
         }
     }
     
-    // Set the final key
+    // Remove the final key
     if let Value::Table(table) = current {
-        table.insert(parts[parts.len() - 1].to_string(), new_value);
+        table.remove(parts[parts.len() - 1]);
     }
 }
@@ -0,0 +1,39 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+const APP_DIR: &str = "grackle";
+
+/// Where per-user editor settings live.
+/// `%APPDATA%\grackle` on Windows, `~/Library/Application Support/grackle` on macOS,
+/// and `$XDG_CONFIG_HOME/grackle` (falling back to `~/.config/grackle`) everywhere else.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join(APP_DIR))
+}
+
+/// Reads a file from the config directory. A file that doesn't exist yet is `Ok(None)`.
+pub fn read_config(file_name: &str) -> Result<Option<String>, String> {
+    let path = config_dir().ok_or("Could not work out where the config directory is.")?.join(file_name);
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Could not read \"{}\": {}", path.display(), e)),
+    }
+}
+
+pub fn write_config(file_name: &str, contents: &str) -> Result<(), String> {
+    let dir = config_dir().ok_or("Could not work out where the config directory is.")?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Could not create \"{}\": {}", dir.display(), e))?;
+    let path = dir.join(file_name);
+    fs::write(&path, contents).map_err(|e| format!("Could not write \"{}\": {}", path.display(), e))
+}
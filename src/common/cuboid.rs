@@ -1,10 +1,13 @@
 use bevy::prelude::*;
 use bevy_egui::egui;
-use bevy_egui::egui::{Context, Ui};
+use bevy_egui::egui::Ui;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 use crate::common::PointResolutionError;
 use crate::editor::editable::EditorObject;
+use crate::editor::inspector::drag_vec3;
 use crate::get;
 
 lazy_static! {
@@ -20,7 +23,7 @@ lazy_static! {
     };
 }
 
-#[derive(Clone, Copy)]
+#[derive(EnumIter, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum CuboidPoint {
     Centroid,
     TopPlaneCenter,
@@ -84,6 +87,14 @@ impl CuboidPoint {
         }
     }
 
+    pub fn is_corner(&self) -> bool {
+        self.value().iter().all(|v| *v != 0.5)
+    }
+
+    pub fn is_edge_center(&self) -> bool {
+        self.value().iter().filter(|v| **v == 0.5).count() == 1
+    }
+
     pub fn resolve_in_bounds(&self, min: Vec3, max: Vec3) -> Vec3 {
         let [x, y, z] = self.value();
         let (low_x, high_x) = if min.x < max.x { (min.x, max.x) } else { (max.x, min.x) };
@@ -103,7 +114,7 @@ impl CuboidPoint {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct GrackleCuboid {
     min: Vec3,
     max: Vec3,
@@ -126,9 +137,15 @@ impl EditorObject for GrackleCuboid {
         //Ok(point.resolve_in_bounds(self.min, self.max))
     }
 
-    fn editor_ui(&mut self, ctx: &mut Context) {
-        egui::Window::new(self.type_name()).show(ctx, |ui| {
+    fn inspector_ui(&mut self, ui: &mut Ui) {
+        egui::Grid::new("cuboid").num_columns(4).show(ui, |ui| {
+            ui.label(get!("inspector.min"));
+            drag_vec3(ui, &mut self.min, 0.01);
+            ui.end_row();
 
+            ui.label(get!("inspector.max"));
+            drag_vec3(ui, &mut self.max, 0.01);
+            ui.end_row();
         });
     }
 
@@ -136,7 +153,19 @@ impl EditorObject for GrackleCuboid {
         get!("editor.actions.cuboid.title")
     }
     
+    fn snap_points(&self) -> Vec<Vec3> {
+        CuboidPoint::iter().map(|point| point.resolve_in_bounds(self.min, self.max)).collect()
+    }
+
     fn debug_gizmos(&self, _gizmos: &mut Gizmos) {
         todo!()
     }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.min.min(self.max), self.min.max(self.max)))
+    }
+
+    fn boxed_clone(&self) -> Box<dyn EditorObject> {
+        Box::new(self.clone())
+    }
 }
@@ -1,124 +1,435 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::sync::{RwLock};
-use bevy::prelude::info;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock, RwLock};
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::{info, warn};
 use lazy_static::lazy_static;
+use serde::Deserialize;
 use toml::Value;
-use regex::Regex;
-
-// Someday, I'd really love to actually parse the language file in and split it by each {}
-// then sort the {names} so that they're just in the order they go in the string.
-// Finally, when we want a string we "ask" the caller for each key in order
-// (by this I mean look up the key in the dict first to last)
-// This way I hope we spend less time making and hashing strings
-// but I'm not actually sure if this would speed up the hashmap lookup.
-// Perhaps some kind of tokenization optimization is in our future?
+
+// Lang files are flattened into dotted keys and each string is split at its { placeholders } when the
+// language loads, so filling a template in is just gluing pieces together. Keys are interned: every key
+// gets a `LangId` that stays the same across languages, and `get!` with a literal key remembers its id,
+// so the UI asking for the same strings every frame doesn't hash or allocate keys.
 //
-// Right now we just expand the cache forever. Leaky!
+// Strings a language doesn't have come from the next language in its fallback chain (see `PackLang`).
 lazy_static! {
-    static ref LANG: RwLock<Value> = RwLock::new(load_lang("en-US"));
+    static ref LANG: RwLock<Lang> = RwLock::new(Lang::load("en-US").unwrap_or_else(|e| panic!("{}", e)));
+}
 
-    static ref NAME_PATTERN: String = r"[A-Za-z_]+".into();
-    static ref TEMPLATE_REGEX: Regex = Regex::new(r"\$\{\s*[A-Za-z_]+\s*\}").unwrap();
-    static ref NAME_REGEX: Regex = Regex::new(&NAME_PATTERN).unwrap();
+/// Marks strings that came from a fallback language with that language's code.
+static HIGHLIGHT_FALLBACKS: AtomicBool = AtomicBool::new(false);
 
-    static ref CACHE: HashMap<String, String> = HashMap::new();
-}
+const PACK: &str = "default";
 
 pub fn change_lang(lang: &str) -> Result<(), String> {
+    let strings = read_chain(lang)?;
     let mut lang_lock = LANG.write().map_err(|_| "Could not change language; could not acquire lock.")?;
-    *lang_lock = load_lang(lang);
+    lang_lock.set_strings(strings);
     Ok(())
 }
 
-fn load_lang(lang: &str) -> Value {
-    let pack = "default";
-    
-    info!("Loading pack {} in lang {}", pack, lang);
-    
-    let lang_path = format!("assets/{}/lang/{}.toml", pack, lang);
+/// The loaded pack's folder, found the same way Bevy finds the `assets` folder,
+/// so it doesn't matter which directory the editor is started from.
+fn pack_dir() -> PathBuf {
+    FileAssetReader::get_base_path().join("assets").join(PACK)
+}
+
+/// Where the loaded pack keeps its lang files, one per language.
+pub fn lang_dir() -> PathBuf {
+    pack_dir().join("lang")
+}
+
+pub fn highlight_fallbacks() -> bool {
+    HIGHLIGHT_FALLBACKS.load(Ordering::Relaxed)
+}
+
+pub fn set_highlight_fallbacks(highlight: bool) {
+    HIGHLIGHT_FALLBACKS.store(highlight, Ordering::Relaxed);
+}
+
+/// The `[lang]` table of a pack's `pack.toml`.
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct PackLang {
+    /// Every fallback chain ends here.
+    pub default: String,
+    /// Where to look, in order, for strings a language doesn't have.
+    /// Fallbacks have fallbacks of their own, so `de-AT = ["de-DE"]` also gets whatever `de-DE` falls back to.
+    pub fallbacks: HashMap<String, Vec<String>>,
+}
+
+impl Default for PackLang {
+    fn default() -> Self {
+        Self {
+            default: "en-US".to_owned(),
+            fallbacks: HashMap::new(),
+        }
+    }
+}
+
+impl PackLang {
+    fn load() -> Result<Self, String> {
+        #[derive(Deserialize, Default)]
+        #[serde(default)]
+        struct Pack {
+            lang: PackLang,
+        }
+
+        let pack_path = pack_dir().join("pack.toml");
+        let Ok(toml_str) = fs::read_to_string(&pack_path) else {
+            return Ok(Self::default());
+        };
+        toml::from_str::<Pack>(&toml_str)
+            .map(|pack| pack.lang)
+            .map_err(|e| format!("Could not parse pack file \"{}\": {}", pack_path.display(), e))
+    }
+
+    /// `lang`, then its fallbacks, then theirs, then the default language.
+    pub fn chain(&self, lang: &str) -> Vec<String> {
+        let mut chain = vec![lang.to_owned()];
+        let mut next = 0;
+        while next < chain.len() {
+            for fallback in self.fallbacks.get(&chain[next]).into_iter().flatten() {
+                if !chain.contains(fallback) {
+                    chain.push(fallback.clone());
+                }
+            }
+            next += 1;
+        }
+        if !chain.contains(&self.default) {
+            chain.push(self.default.clone());
+        }
+        chain
+    }
+}
+
+/// Reads every language in `lang`'s fallback chain. Fallbacks without a file are skipped; `lang` itself is needed.
+fn read_chain(lang: &str) -> Result<Vec<(String, Value)>, String> {
+    let chain = PackLang::load()?.chain(lang);
+    info!("Loading pack {} in lang {} (falling back to {})", PACK, lang, chain[1..].join(", "));
+
+    let mut strings = Vec::new();
+    for code in chain {
+        match read_lang(&code) {
+            Ok(value) => strings.push((code, value)),
+            Err(e) if strings.is_empty() => return Err(e),
+            Err(e) => warn!("{}", e),
+        }
+    }
+    Ok(strings)
+}
+
+fn read_lang(lang: &str) -> Result<Value, String> {
+    let lang_path = lang_dir().join(format!("{}.toml", lang));
     let toml_str = fs::read_to_string(&lang_path)
-        .expect(format!("Lang file \"{}\" not found!", lang_path).as_str());
+        .map_err(|_| format!("Lang file \"{}\" not found!", lang_path.display()))?;
 
-    let parsed = toml_str
+    toml_str
         .parse()
-        .expect(format!("Could not parse lang file \"{}\"!", lang_path).as_str());
+        .map_err(|e| format!("Could not parse lang file \"{}\": {}", lang_path.display(), e))
+}
 
-    parsed
+/// An interned lang key.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct LangId(u32);
+
+/// A piece of a lang string.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Segment {
+    Literal(String),
+    /// `raw` is how it was written, for when there's nothing to fill it in with.
+    Placeholder { name: String, raw: String },
 }
 
-pub fn get_maybe(keys: &[&str]) -> Option<String> {
-    let mut current: &Value = &*(LANG.read().unwrap());
-    for key in keys {
-        let next_current = current.get(*key);
-        match next_current {
-            Some(next_current) => current = next_current,
-            None => return None
+/// A lang string split at its placeholders.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Splits `text` at every `{ name }`. Braces around anything that isn't a name are left as they are.
+    pub fn parse(text: &str) -> Self {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = text;
+        while let Some(open) = rest.find('{') {
+            let Some(close) = rest[open..].find('}').map(|close| open + close) else {
+                break;
+            };
+            let name = rest[open + 1..close].trim();
+            let is_name = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            literal.push_str(&rest[..open]);
+            if is_name {
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Placeholder { name: name.to_owned(), raw: rest[open..=close].to_owned() });
+            } else {
+                literal.push_str(&rest[open..=close]);
+            }
+            rest = &rest[close + 1..];
         }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Self { segments }
     }
-    match current.as_str() {
-        None => None,
-        Some(s) => Some(s.to_string())
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Fills in placeholders from `pairs`. Ones without a pair are left as they were written.
+    pub fn fill(&self, pairs: &[(&str, &str)]) -> String {
+        match self.segments.as_slice() {
+            [] => String::new(),
+            [Segment::Literal(text)] => text.clone(),
+            segments => {
+                let mut product = String::with_capacity(segments.iter().map(|segment| match segment {
+                    Segment::Literal(text) => text.len(),
+                    Segment::Placeholder { raw, .. } => raw.len(),
+                }).sum());
+                for segment in segments {
+                    match segment {
+                        Segment::Literal(text) => product.push_str(text),
+                        Segment::Placeholder { name, raw } => {
+                            let value = pairs.iter().find(|(key, _)| key == name).map_or(raw.as_str(), |(_, value)| *value);
+                            product.push_str(value);
+                        }
+                    }
+                }
+                product
+            }
+        }
     }
 }
 
-pub fn get_infallible(keys: &[&str]) -> String {
-    get_maybe(keys).unwrap_or(format!("<{}>", full_path(keys)))
+/// A string of the loaded language, and which language in the chain it came from.
+#[derive(Clone, PartialEq, Debug)]
+struct Entry {
+    template: Template,
+    /// Index into `Lang::chain`. Anything but 0 is a fallback.
+    source: usize,
 }
 
-pub fn get_parsed(keys: &str) -> String {
-    let keys = keys.split('.').collect::<Vec<&str>>();
-    get_infallible(&keys)
+/// The strings of the loaded language and its fallbacks, by interned key.
+#[derive(Default)]
+pub struct Lang {
+    ids: HashMap<String, LangId>,
+    keys: Vec<String>,
+    /// Indexed by id. `None` for keys no language in the chain has.
+    entries: Vec<Option<Entry>>,
+    /// The loaded language first, then the ones it falls back to.
+    chain: Vec<String>,
+    /// Keys already logged as missing or fallen back, so the UI asking every frame only logs them once.
+    logged: Mutex<HashSet<LangId>>,
+}
+
+impl Lang {
+    fn load(lang: &str) -> Result<Self, String> {
+        let mut loaded = Self::default();
+        loaded.set_strings(read_chain(lang)?);
+        Ok(loaded)
+    }
+
+    /// A language from `(code, toml)` pairs, fallbacks after the language itself.
+    pub fn from_toml(chain: &[(&str, &str)]) -> Result<Self, String> {
+        let strings = chain
+            .iter()
+            .map(|(code, toml)| toml.parse().map(|value| (code.to_string(), value)).map_err(|e: toml::de::Error| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut lang = Self::default();
+        lang.set_strings(strings);
+        Ok(lang)
+    }
+
+    /// Swaps in another language's strings. Ids handed out before keep pointing at the same keys.
+    fn set_strings(&mut self, chain: Vec<(String, Value)>) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.logged.get_mut().unwrap().clear();
+        self.chain = chain.iter().map(|(code, _)| code.clone()).collect();
+        for (source, (_, value)) in chain.iter().enumerate() {
+            let mut strings = Vec::new();
+            flatten(value, String::new(), &mut strings);
+            for (key, text) in strings {
+                let id = self.intern(&key);
+                let entry = &mut self.entries[id.0 as usize];
+                if entry.is_none() {
+                    *entry = Some(Entry { template: Template::parse(text), source });
+                }
+            }
+        }
+    }
+
+    pub fn intern(&mut self, key: &str) -> LangId {
+        if let Some(id) = self.ids.get(key) {
+            return *id;
+        }
+        let id = LangId(self.keys.len() as u32);
+        self.ids.insert(key.to_owned(), id);
+        self.keys.push(key.to_owned());
+        self.entries.push(None);
+        id
+    }
+
+    pub fn id(&self, key: &str) -> Option<LangId> {
+        self.ids.get(key).copied()
+    }
+
+    pub fn key(&self, id: LangId) -> &str {
+        &self.keys[id.0 as usize]
+    }
+
+    pub fn template(&self, id: LangId) -> Option<&Template> {
+        self.entry(id).map(|entry| &entry.template)
+    }
+
+    /// The language a string came from, if it isn't the loaded one.
+    pub fn fallback(&self, id: LangId) -> Option<&str> {
+        self.entry(id).filter(|entry| entry.source > 0).map(|entry| self.chain[entry.source].as_str())
+    }
+
+    fn entry(&self, id: LangId) -> Option<&Entry> {
+        self.entries.get(id.0 as usize).and_then(Option::as_ref)
+    }
+
+    /// Fills in a string for showing, logging the first time it's missing or falls back.
+    /// Missing strings come out as their key and pairs; `highlight` marks fallbacks with their language.
+    pub fn render(&self, id: LangId, pairs: &[(&str, &str)], highlight: bool) -> String {
+        let Some(entry) = self.entry(id) else {
+            if self.log_once(id) {
+                warn!("No language in {:?} has the string {}.", self.chain, self.key(id));
+            }
+            return match pairs {
+                [] => format!("<{}>", self.key(id)),
+                pairs => format!("<{}; {:?}>", self.key(id), pairs),
+            };
+        };
+        let text = entry.template.fill(pairs);
+        if entry.source == 0 {
+            return text;
+        }
+        let fallback = &self.chain[entry.source];
+        if self.log_once(id) {
+            info!("{} has no string {}, using the one from {}.", self.chain[0], self.key(id), fallback);
+        }
+        if highlight {
+            format!("[{}] {}", fallback, text)
+        } else {
+            text
+        }
+    }
+
+    /// Whether this is the first time `id` needed logging since the language loaded.
+    fn log_once(&self, id: LangId) -> bool {
+        self.logged.lock().unwrap().insert(id)
+    }
+
+    pub fn get(&self, id: LangId) -> String {
+        self.render(id, &[], highlight_fallbacks())
+    }
+
+    pub fn get_template(&self, id: LangId, pairs: &[(&str, &str)]) -> String {
+        self.render(id, pairs, highlight_fallbacks())
+    }
 }
 
-fn full_path(keys: &[&str]) -> String {
-    let mut debug_string = "".into();
-    for (idx, key) in keys.iter().enumerate() {
-        debug_string += *key;
-        if idx < keys.len() - 1 {
-            debug_string += "."
+/// Every string in a lang file, by dotted key.
+fn flatten<'a>(value: &'a Value, prefix: String, strings: &mut Vec<(String, &'a str)>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(value, key, strings);
+            }
         }
+        Value::String(text) => strings.push((prefix, text)),
+        _ => {}
     }
-    debug_string
 }
 
-fn pair_path(pairs: &[(&str, &str)]) -> String {
-    pairs.iter().map(|(k, v)| format!("{}:{}", k, v)).collect::<Vec<_>>().join("::")
+/// A key written once in the code, interned the first time it's looked up.
+pub struct LangKey {
+    key: &'static str,
+    id: OnceLock<LangId>,
 }
 
-fn template_pair_path(keys: &[&str], pairs: &[(&str, &str)]) -> String {
-    format!("{}|{}", full_path(keys), pair_path(pairs))
+impl LangKey {
+    pub const fn new(key: &'static str) -> Self {
+        Self { key, id: OnceLock::new() }
+    }
+
+    pub fn id(&self) -> LangId {
+        *self.id.get_or_init(|| intern(self.key))
+    }
 }
 
-pub fn get_template_maybe(keys: &[&str], pairs: &[(&str, &str)]) -> Result<String, String> {
-    let cache_key = template_pair_path(keys, pairs);
-    match CACHE.get(&cache_key) {
-        Some(cached_value) => Ok(cached_value.clone()),
-        None => fill_template(keys, pairs, true),
+pub fn intern(key: &str) -> LangId {
+    if let Some(id) = LANG.read().unwrap().id(key) {
+        return id;
     }
+    LANG.write().unwrap().intern(key)
+}
+
+pub fn get_id(id: LangId) -> String {
+    LANG.read().unwrap().get(id)
+}
+
+pub fn get_template_id(id: LangId, pairs: &[(&str, &str)]) -> String {
+    LANG.read().unwrap().get_template(id, pairs)
+}
+
+pub fn get_maybe(keys: &[&str]) -> Option<String> {
+    let lang = LANG.read().unwrap();
+    lang.id(&keys.join(".")).and_then(|id| lang.template(id)).map(|template| template.fill(&[]))
+}
+
+pub fn get_infallible(keys: &[&str]) -> String {
+    get_maybe(keys).unwrap_or(format!("<{}>", keys.join(".")))
+}
+
+pub fn get_parsed(keys: &str) -> String {
+    get_id(intern(keys))
+}
+
+pub fn get_template_maybe(keys: &[&str], pairs: &[(&str, &str)]) -> Result<String, String> {
+    let lang = LANG.read().unwrap();
+    lang.id(&keys.join("."))
+        .and_then(|id| lang.template(id))
+        .map(|template| template.fill(pairs))
+        .ok_or(format!("Failed to find value for key {:?}", keys))
 }
 
 pub fn get_template(keys: &[&str], pairs: &[(&str, &str)]) -> String {
-    let cache_key = template_pair_path(keys, pairs);
-    match CACHE.get(&cache_key) {
-        Some(cached_value) => cached_value.clone(),
-        None => {
-            let pairs_string = format!("{:?}", pairs);
-            fill_template(keys, pairs, false).unwrap_or(format!("<{}; {}>", full_path(keys), pairs_string))
-        }
-    }
+    get_template_parsed(&keys.join("."), pairs)
 }
 
 pub fn get_template_parsed(key: &str, pairs: &[(&str, &str)]) -> String {
-    let keys = key.split('.').collect::<Vec<&str>>();
-    get_template(&keys, pairs)
+    get_template_id(intern(key), pairs)
 }
 
+/// Looks up a lang string, filling in `name, value` pairs if there are any.
+/// Literal keys are interned once per call site; anything else is interned on every call.
 #[macro_export]
 macro_rules! get {
+    ($key:literal) => {{
+        static KEY: $crate::common::lang::LangKey = $crate::common::lang::LangKey::new($key);
+        $crate::common::lang::get_id(KEY.id())
+    }};
     ($key:expr) => {
         $crate::common::lang::get_parsed(&$key)
     };
+    ($key:literal, $($var:expr, $val:expr),*) => {{
+        static KEY: $crate::common::lang::LangKey = $crate::common::lang::LangKey::new($key);
+        $crate::common::lang::get_template_id(KEY.id(), &[
+            $(($var, &format!("{}", $val))),*
+        ])
+    }};
     ($key:expr, $($var:expr, $val:expr),*) => {
         $crate::common::lang::get_template_parsed(&$key, &[
             $(($var, &format!("{}", $val))),*
@@ -138,26 +449,68 @@ macro_rules! get_with_debug {
     };
 }
 
-fn fill_template(keys: &[&str], pairs: &[(&str, &str)], fail_quickly: bool) -> Result<String, String> {
-    let template = get_maybe(keys).ok_or(format!("Failed to find value for key {:?}", keys))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let pairs: HashMap<String, String> = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    #[test]
+    fn test_templates_split_at_placeholders() {
+        let template = Template::parse("Saved to { path }, {count} times. {} { not a name }");
+        assert_eq!(template.segments(), &[
+            Segment::Literal("Saved to ".to_owned()),
+            Segment::Placeholder { name: "path".to_owned(), raw: "{ path }".to_owned() },
+            Segment::Literal(", ".to_owned()),
+            Segment::Placeholder { name: "count".to_owned(), raw: "{count}".to_owned() },
+            Segment::Literal(" times. {} { not a name }".to_owned()),
+        ]);
+        assert_eq!(template.fill(&[("count", "3"), ("path", "a.gmp")]), "Saved to a.gmp, 3 times. {} { not a name }");
+        assert_eq!(template.fill(&[]), "Saved to { path }, {count} times. {} { not a name }");
+    }
 
-    let mut product = template.clone();
+    #[test]
+    fn test_ids_survive_language_changes() {
+        let mut lang = Lang::from_toml(&[("en-US", "[tools]\nselect = \"Select\"\nmove = \"Move { what }\"")]).unwrap();
+        let select = lang.id("tools.select").unwrap();
+        let missing = lang.intern("tools.missing");
+        assert_eq!(lang.render(lang.id("tools.move").unwrap(), &[("what", "it")], false), "Move it");
+        assert_eq!(lang.render(missing, &[], false), "<tools.missing>");
 
-    for (key, replacement) in pairs {
-        let re = Regex::new(&format!(r"\{{\s*{}\s*\}}", key));
-        match re {
-            Ok(re) => {
-                product = re.replace_all(&product, replacement.as_str()).parse().unwrap();
-            }
-            Err(_) => {
-                if fail_quickly {
-                    return Err(format!("Replacement names must be valid regex: \"{}\"", key));
-                }
-            }
-        }
+        lang.set_strings(vec![("de-DE".to_owned(), "[tools]\nselect = \"Auswählen\"\nmissing = \"Da\"".parse().unwrap())]);
+        assert_eq!(lang.render(select, &[], false), "Auswählen");
+        assert_eq!(lang.render(missing, &[], false), "Da");
+        assert_eq!(lang.template(lang.id("tools.move").unwrap()), None);
     }
 
-    Ok(product)
+    #[test]
+    fn test_chains_follow_fallbacks_to_default() {
+        let pack = PackLang {
+            default: "en-US".to_owned(),
+            fallbacks: HashMap::from([
+                ("de-AT".to_owned(), vec!["de-DE".to_owned()]),
+                ("de-DE".to_owned(), vec!["de-AT".to_owned(), "de-CH".to_owned()]),
+            ]),
+        };
+        assert_eq!(pack.chain("de-AT"), ["de-AT", "de-DE", "de-CH", "en-US"]);
+        assert_eq!(pack.chain("en-US"), ["en-US"]);
+        assert_eq!(pack.chain("fr-FR"), ["fr-FR", "en-US"]);
+    }
+
+    #[test]
+    fn test_missing_strings_fall_back_and_log_once() {
+        let lang = Lang::from_toml(&[
+            ("de-AT", "[months]\njanuary = \"Jänner\""),
+            ("de-DE", "[months]\njanuary = \"Januar\"\nfebruary = \"Februar\""),
+            ("en-US", "[months]\nmarch = \"March\""),
+        ]).unwrap();
+        let [january, february, march] = ["months.january", "months.february", "months.march"].map(|key| lang.id(key).unwrap());
+        assert_eq!(lang.render(january, &[], true), "Jänner");
+        assert_eq!(lang.render(february, &[], false), "Februar");
+        assert_eq!(lang.render(march, &[], true), "[en-US] March");
+        assert_eq!(lang.fallback(february), Some("de-DE"));
+        assert_eq!(lang.fallback(january), None);
+
+        assert!(!lang.log_once(february));
+        assert!(!lang.log_once(march));
+        assert!(lang.log_once(january));
+    }
 }
@@ -3,6 +3,7 @@ pub(crate) mod perf;
 pub(crate) mod systems;
 pub(crate) mod painter;
 pub(crate) mod ray;
+pub(crate) mod config;
 pub mod item;
 pub mod cuboid;
 
@@ -12,3 +13,15 @@ pub enum PointResolutionError {
     PropagatedError,
     Other,
 }
+
+impl std::fmt::Display for PointResolutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            PointResolutionError::NoSuchPoint => crate::get!("point_resolution.no_such_point"),
+            PointResolutionError::NoSuchReferent => crate::get!("point_resolution.no_such_referent"),
+            PointResolutionError::PropagatedError => crate::get!("point_resolution.propagated"),
+            PointResolutionError::Other => crate::get!("point_resolution.other"),
+        };
+        write!(f, "{}", message)
+    }
+}
@@ -1,5 +1,6 @@
-use bevy::prelude::Camera;
-use bevy::math::Vec2;
+use bevy::prelude::{Camera, GlobalTransform};
+use bevy::math::{Vec2, Vec3};
+use bevy_vector_shapes::prelude::*;
 
 pub fn window_to_painter(cam: &Camera, pos: Vec2) -> Vec2 {
     let cam_viewport = cam.physical_viewport_rect().unwrap();
@@ -17,4 +18,24 @@ pub fn window_to_painter_frac(cam: &Camera, frac: Vec2) -> Vec2 {
         (cam_viewport.max.x - cam_viewport.min.x) as f32 * (frac.x - 0.5),
         (cam_viewport.max.y - cam_viewport.min.y) as f32 * (1.0 - (frac.y + 0.5))
     )
-}
\ No newline at end of file
+}
+
+/// Like `Camera::world_to_viewport`, but doesn't reject points outside the near and far planes.
+/// Orthographic views should be able to work with things behind the camera.
+pub fn world_to_viewport_ignoring_depth(camera: &Camera, camera_tfm: &GlobalTransform, point: Vec3) -> Option<Vec2> {
+    let target_rect = camera.logical_viewport_rect()?;
+    let ndc = camera.world_to_ndc(camera_tfm, point)?;
+    let rect_relative = Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) / 2.0;
+    Some(target_rect.min + rect_relative * target_rect.size())
+}
+
+pub fn draw_rect(
+    painter: &mut ShapePainter,
+    min: Vec2,
+    max: Vec2,
+) {
+    painter.line(Vec3::new(min.x, min.y, 0.0), Vec3::new(max.x, min.y, 0.0)); // Bottom
+    painter.line(Vec3::new(min.x, max.y, 0.0), Vec3::new(max.x, max.y, 0.0)); // Top
+    painter.line(Vec3::new(min.x, min.y, 0.0), Vec3::new(min.x, max.y, 0.0)); // Left
+    painter.line(Vec3::new(max.x, min.y, 0.0), Vec3::new(max.x, max.y, 0.0)); // Right
+}
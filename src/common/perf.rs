@@ -1,9 +1,10 @@
 use bevy::app::{App, Plugin, Update};
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use iyes_perf_ui::PerfUiPlugin;
-use bevy::prelude::{AppExtStates, Commands, Component, KeyCode, NextState, OnEnter, Res, ResMut, State, States};
+use bevy::prelude::{AppExtStates, Commands, Component, NextState, OnEnter, Res, ResMut, State, States};
 use iyes_perf_ui::entries::{PerfUiFixedTimeEntries, PerfUiFramerateEntries, PerfUiWindowEntries};
-use bevy::input::ButtonInput;
+use crate::editor::keymap::{ActionState, InputAction};
+use crate::editor::palette::{Operation, OperationsExt};
 
 pub struct PerfPlugin;
 
@@ -19,6 +20,13 @@ impl Plugin for PerfPlugin {
             .add_systems(OnEnter(DebugState::Off), crate::common::systems::despawn_recursive_entities_with::<PerfUI>)
             .add_systems(OnEnter(DebugState::AllPerf), add_all_perf)
             .add_systems(Update, toggle_perf)
+            .add_operation(Operation::new("palette.operations.toggle_perf", |world| {
+                let next = match world.resource::<State<DebugState>>().get() {
+                    DebugState::Off => DebugState::AllPerf,
+                    DebugState::AllPerf => DebugState::Off,
+                };
+                world.resource_mut::<NextState<DebugState>>().set(next);
+            }).with_action(InputAction::TogglePerf))
             .init_state::<DebugState>()
         ;
     }
@@ -34,11 +42,11 @@ fn add_all_perf(mut commands: Commands) {
 }
 
 fn toggle_perf(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     state: Res<State<DebugState>>,
     mut next_state: ResMut<NextState<DebugState>>,
 ) {
-    if keyboard.just_pressed(KeyCode::F3) {
+    if actions.just_pressed(InputAction::TogglePerf) {
         match state.get() {
             DebugState::Off => {
                 next_state.set(DebugState::AllPerf);
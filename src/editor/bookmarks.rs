@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::editor::console::{ConsoleCommand, ConsoleCommands, ConsoleCommandsExt};
+use crate::editor::keymap::{ActionState, InputAction};
+use crate::editor::multicam::{CameraAxis, Multicam, MulticamPlugin};
+use crate::get;
+use crate::tool::playtest::Playtest;
+
+/// How long jumping to a bookmark takes.
+const TWEEN_SECONDS: f32 = 0.35;
+
+/// The actions that jump to bookmarks 1 to 9.
+const RECALL_ACTIONS: [InputAction; 9] = [
+    InputAction::Bookmark1, InputAction::Bookmark2, InputAction::Bookmark3,
+    InputAction::Bookmark4, InputAction::Bookmark5, InputAction::Bookmark6,
+    InputAction::Bookmark7, InputAction::Bookmark8, InputAction::Bookmark9,
+];
+
+/// The actions that set bookmarks 1 to 9 to where the cameras are now.
+const STORE_ACTIONS: [InputAction; 9] = [
+    InputAction::SetBookmark1, InputAction::SetBookmark2, InputAction::SetBookmark3,
+    InputAction::SetBookmark4, InputAction::SetBookmark5, InputAction::SetBookmark6,
+    InputAction::SetBookmark7, InputAction::SetBookmark8, InputAction::SetBookmark9,
+];
+
+pub struct CameraBookmarksPlugin;
+
+impl Plugin for CameraBookmarksPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CameraBookmarks>()
+            .add_event::<BookmarkEvent>()
+            .add_systems(Update, (
+                CameraBookmarks::hotkeys.run_if(Playtest::inactive),
+                CameraBookmarks::handle_events,
+                CameraTween::animate,
+            ).chain())
+            .add_console_command("bookmark", ConsoleCommand::new("console.commands.bookmark", CameraBookmarks::bookmark_command)
+                .with_completions(|_| vec!["save".to_owned(), "go".to_owned(), "list".to_owned()]))
+        ;
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub enum BookmarkEvent {
+    /// Saves where every camera is to a slot, with an optional name.
+    Store(u8, Option<String>),
+    Recall(u8),
+}
+
+/// Where one viewport's camera was.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct ViewBookmark {
+    /// The `Multicam::id` of the camera.
+    pub camera: u32,
+    pub axis: CameraAxis,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    /// The zoom of orthographic cameras.
+    pub scale: Option<f32>,
+}
+
+/// A named spot in the map, remembered for every viewport at once.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct CameraBookmark {
+    pub name: String,
+    pub views: Vec<ViewBookmark>,
+}
+
+/// Bookmarks by slot. They're part of the map, so they're saved in the `.gmp` file.
+#[derive(Resource, Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(transparent)]
+pub struct CameraBookmarks {
+    slots: BTreeMap<u8, CameraBookmark>,
+}
+
+impl CameraBookmarks {
+    pub fn get(&self, slot: u8) -> Option<&CameraBookmark> {
+        self.slots.get(&slot)
+    }
+
+    pub fn set(&mut self, slot: u8, bookmark: CameraBookmark) {
+        self.slots.insert(slot, bookmark);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &CameraBookmark)> {
+        self.slots.iter().map(|(slot, bookmark)| (*slot, bookmark))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    fn bookmark_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+        let usage = || world.resource::<ConsoleCommands>().get("bookmark").map(ConsoleCommand::usage).unwrap_or_default();
+        let parse_slot = |slot: &str| slot.parse::<u8>().ok().filter(|slot| (1..=9).contains(slot));
+        match args {
+            ["list"] => {
+                let bookmarks = world.resource::<Self>();
+                if bookmarks.is_empty() {
+                    return Ok(get!("console.no_bookmarks"));
+                }
+                Ok(bookmarks.iter().map(|(slot, bookmark)| format!("{}: {}", slot, bookmark.name)).collect::<Vec<_>>().join("\n"))
+            }
+            ["save", slot, name @ ..] => {
+                let slot = parse_slot(slot).ok_or_else(usage)?;
+                let name = (!name.is_empty()).then(|| name.join(" "));
+                world.send_event(BookmarkEvent::Store(slot, name));
+                Ok(get!("console.bookmark_saved", "slot", slot))
+            }
+            ["go", slot] => {
+                let slot = parse_slot(slot).ok_or_else(usage)?;
+                let bookmark = world.resource::<Self>().get(slot).ok_or_else(|| get!("console.no_such_bookmark", "slot", slot))?;
+                let message = get!("console.bookmark_recalled", "name", bookmark.name);
+                world.send_event(BookmarkEvent::Recall(slot));
+                Ok(message)
+            }
+            _ => Err(usage()),
+        }
+    }
+
+    fn hotkeys(actions: Res<ActionState>, mut events: EventWriter<BookmarkEvent>) {
+        for slot in 1..=9u8 {
+            let index = slot as usize - 1;
+            if actions.just_pressed(STORE_ACTIONS[index]) {
+                events.write(BookmarkEvent::Store(slot, None));
+            } else if actions.just_pressed(RECALL_ACTIONS[index]) {
+                events.write(BookmarkEvent::Recall(slot));
+            }
+        }
+    }
+
+    fn handle_events(
+        mut commands: Commands,
+        mut events: EventReader<BookmarkEvent>,
+        mut bookmarks: ResMut<Self>,
+        mut cameras: Query<(Entity, &mut Multicam, &mut Transform, &mut Projection)>,
+    ) {
+        for event in events.read() {
+            match event {
+                BookmarkEvent::Store(slot, name) => {
+                    let views = cameras
+                        .iter()
+                        .map(|(_, multicam, transform, projection)| ViewBookmark {
+                            camera: multicam.id,
+                            axis: multicam.axis,
+                            translation: transform.translation,
+                            rotation: transform.rotation,
+                            scale: match projection {
+                                Projection::Orthographic(ortho) => Some(ortho.scale),
+                                _ => None,
+                            },
+                        })
+                        .collect();
+                    // Saving over a bookmark without a new name keeps the old one.
+                    let name = name.clone()
+                        .or_else(|| bookmarks.get(*slot).map(|bookmark| bookmark.name.clone()))
+                        .unwrap_or_else(|| get!("bookmarks.default_name", "slot", slot));
+                    bookmarks.set(*slot, CameraBookmark { name, views });
+                }
+                BookmarkEvent::Recall(slot) => {
+                    let Some(bookmark) = bookmarks.get(*slot) else { continue; };
+                    for (entity, mut multicam, mut transform, mut projection) in &mut cameras {
+                        let Some(view) = bookmark.views.iter().find(|view| view.camera == multicam.id) else { continue; };
+                        // Changing axis snaps the camera round; only the move to the bookmarked spot is animated.
+                        MulticamPlugin::set_axis(view.axis, &mut multicam, &mut transform, &mut projection);
+                        let from_scale = match projection.as_ref() {
+                            Projection::Orthographic(ortho) => Some(ortho.scale),
+                            _ => None,
+                        };
+                        commands.entity(entity).insert(CameraTween {
+                            from: *transform,
+                            from_scale,
+                            to: transform.with_translation(view.translation).with_rotation(view.rotation),
+                            to_scale: view.scale,
+                            elapsed: 0.0,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Moves a camera smoothly from one spot to another, then removes itself.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CameraTween {
+    from: Transform,
+    from_scale: Option<f32>,
+    to: Transform,
+    to_scale: Option<f32>,
+    elapsed: f32,
+}
+
+impl CameraTween {
+    /// Where the camera is `t` of the way through, easing in and out.
+    fn sample(&self, t: f32) -> (Transform, Option<f32>) {
+        let t = t.clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+        let transform = Transform {
+            translation: self.from.translation.lerp(self.to.translation, t),
+            rotation: self.from.rotation.slerp(self.to.rotation, t),
+            scale: self.to.scale,
+        };
+        let scale = match (self.from_scale, self.to_scale) {
+            (Some(from), Some(to)) => Some(from + (to - from) * t),
+            (_, to) => to,
+        };
+        (transform, scale)
+    }
+
+    fn animate(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut cameras: Query<(Entity, &mut CameraTween, &mut Transform, &mut Projection)>,
+    ) {
+        for (entity, mut tween, mut transform, mut projection) in &mut cameras {
+            tween.elapsed += time.delta_secs();
+            let t = tween.elapsed / TWEEN_SECONDS;
+            let (sampled, scale) = tween.sample(t);
+            *transform = sampled;
+            if let (Projection::Orthographic(ortho), Some(scale)) = (projection.as_mut(), scale) {
+                ortho.scale = scale;
+            }
+            if t >= 1.0 {
+                commands.entity(entity).remove::<CameraTween>();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tween() -> CameraTween {
+        CameraTween {
+            from: Transform::from_xyz(0.0, 0.0, 0.0),
+            from_scale: Some(0.01),
+            to: Transform::from_xyz(10.0, 0.0, 0.0).with_rotation(Quat::from_rotation_y(1.0)),
+            to_scale: Some(0.03),
+            elapsed: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_tween_starts_and_ends_in_place() {
+        let tween = tween();
+        let (start, start_scale) = tween.sample(0.0);
+        assert_eq!(start.translation, Vec3::ZERO);
+        assert_eq!(start_scale, Some(0.01));
+
+        let (end, end_scale) = tween.sample(1.5);
+        assert!(end.translation.distance(Vec3::new(10.0, 0.0, 0.0)) < 1e-5);
+        assert!(end.rotation.angle_between(Quat::from_rotation_y(1.0)) < 1e-4);
+        assert!((end_scale.unwrap() - 0.03).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_tween_eases() {
+        let (quarter, _) = tween().sample(0.25);
+        // Smoothstep is slower than linear at the start.
+        assert!(quarter.translation.x < 2.5);
+        assert!(quarter.translation.x > 0.0);
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc;
+use bevy::log::{BoxedLayer, Level};
+use bevy::log::tracing::{Event, Subscriber};
+use bevy::log::tracing::field::{Field, Visit};
+use bevy::log::tracing_subscriber::Layer;
+use bevy::log::tracing_subscriber::layer::Context as LayerContext;
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::{Color32, Key, Modifiers, RichText, Ui};
+use crate::common::lang;
+use crate::editor::palette::{Operation, OperationsExt};
+use crate::get;
+
+const MAX_LINES: usize = 2000;
+const LEVELS: [Level; 5] = [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG, Level::TRACE];
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Console>()
+            .add_console_command("help", ConsoleCommand::new("console.commands.help", Self::help))
+            .add_console_command("clear", ConsoleCommand::new("console.commands.clear", Self::clear))
+            .add_console_command("lang", ConsoleCommand::new("console.commands.lang", Self::lang)
+                .with_completions(Self::lang_names))
+            .add_operation(Operation::command("palette.operations.clear_console", "clear"))
+            .add_systems(Update, (
+                Console::capture_logs,
+                Console::run_commands,
+            ))
+        ;
+    }
+}
+
+/// Hand this to `LogPlugin::custom_layer` so that log output shows up in the console.
+pub fn log_layer(app: &mut App) -> Option<BoxedLayer> {
+    let (sender, receiver) = mpsc::channel();
+    app.insert_non_send_resource(CapturedLogs(receiver));
+    Some(Box::new(ConsoleLayer { sender }))
+}
+
+struct CapturedLogs(mpsc::Receiver<ConsoleLine>);
+
+struct ConsoleLayer {
+    sender: mpsc::Sender<ConsoleLine>,
+}
+
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        // The receiver only goes away when the app does, at which point nobody is reading anyway.
+        let _ = self.sender.send(ConsoleLine {
+            kind: LineKind::Log(*metadata.level()),
+            text: format!("{}: {}", metadata.target(), visitor.message),
+        });
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message.insert_str(0, &format!("{:?}", value));
+        } else {
+            self.message.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LineKind {
+    Log(Level),
+    Input,
+    Output,
+    Error,
+}
+
+pub struct ConsoleLine {
+    pub kind: LineKind,
+    pub text: String,
+}
+
+pub type CommandHandler = fn(&mut World, &[&str]) -> Result<String, String>;
+pub type CommandCompleter = fn(&mut World) -> Vec<String>;
+
+pub struct ConsoleCommand {
+    /// Lang key for a one-line description, including usage.
+    pub help_key: &'static str,
+    pub run: CommandHandler,
+    /// Candidates for the command's arguments, for tab completion.
+    pub complete: Option<CommandCompleter>,
+}
+
+impl ConsoleCommand {
+    pub fn new(help_key: &'static str, run: CommandHandler) -> Self {
+        Self {
+            help_key,
+            run,
+            complete: None,
+        }
+    }
+
+    pub fn with_completions(mut self, complete: CommandCompleter) -> Self {
+        self.complete = Some(complete);
+        self
+    }
+
+    pub fn usage(&self) -> String {
+        get!("console.usage", "usage", get!(self.help_key))
+    }
+}
+
+/// Every command the console knows about. Plugins add to it with `App::add_console_command`.
+#[derive(Resource, Default)]
+pub struct ConsoleCommands {
+    commands: BTreeMap<String, ConsoleCommand>,
+}
+
+impl ConsoleCommands {
+    pub fn get(&self, name: &str) -> Option<&ConsoleCommand> {
+        self.commands.get(name)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.commands.keys().cloned().collect()
+    }
+}
+
+pub trait ConsoleCommandsExt {
+    fn add_console_command(&mut self, name: &str, command: ConsoleCommand) -> &mut Self;
+}
+
+impl ConsoleCommandsExt for App {
+    fn add_console_command(&mut self, name: &str, command: ConsoleCommand) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<ConsoleCommands>()
+            .commands
+            .insert(name.to_owned(), command);
+        self
+    }
+}
+
+#[derive(Resource)]
+pub struct Console {
+    lines: VecDeque<ConsoleLine>,
+    pub input: String,
+    pub search: String,
+    /// Whether each of `LEVELS` is shown.
+    shown_levels: [bool; 5],
+    history: Vec<String>,
+    history_cursor: Option<usize>,
+    pending: Vec<String>,
+    complete_requested: bool,
+    focus_input: bool,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self {
+            lines: VecDeque::new(),
+            input: String::new(),
+            search: String::new(),
+            shown_levels: [true; 5],
+            history: vec![],
+            history_cursor: None,
+            pending: vec![],
+            complete_requested: false,
+            focus_input: false,
+        }
+    }
+}
+
+impl Console {
+    pub fn push(&mut self, kind: LineKind, text: &str) {
+        for line in text.lines() {
+            if self.lines.len() >= MAX_LINES {
+                self.lines.pop_front();
+            }
+            self.lines.push_back(ConsoleLine { kind, text: line.to_owned() });
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Queues a command to run at the next `run_commands`.
+    pub fn submit(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        if self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_owned());
+        }
+        self.history_cursor = None;
+        self.pending.push(line.to_owned());
+    }
+
+    fn is_shown(&self, line: &ConsoleLine) -> bool {
+        if let LineKind::Log(level) = line.kind
+            && let Some(index) = LEVELS.iter().position(|l| *l == level)
+            && !self.shown_levels[index] {
+            return false;
+        }
+        let search = self.search.trim().to_lowercase();
+        search.is_empty() || line.text.to_lowercase().contains(&search)
+    }
+
+    fn step_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        self.history_cursor = match (self.history_cursor, older) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index < last => Some(index + 1),
+            (Some(_), false) => None,
+        };
+        self.input = match self.history_cursor {
+            Some(index) => self.history[index].clone(),
+            None => String::new(),
+        };
+    }
+
+    pub fn ui(ui: &mut Ui, console: &mut Self) {
+        ui.horizontal(|ui| {
+            for (index, level) in LEVELS.iter().enumerate() {
+                let shown = console.shown_levels[index];
+                if ui.selectable_label(shown, RichText::new(level.as_str()).color(level_color(ui, *level))).clicked() {
+                    console.shown_levels[index] = !shown;
+                }
+            }
+            ui.separator();
+            ui.add(egui::TextEdit::singleline(&mut console.search).hint_text(get!("console.search")));
+        });
+        ui.separator();
+
+        let input_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y * 2.0;
+        egui::ScrollArea::vertical()
+            .max_height((ui.available_height() - input_height).max(0.0))
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in console.lines.iter().filter(|line| console.is_shown(line)) {
+                    let text = match line.kind {
+                        LineKind::Log(level) => RichText::new(&line.text).color(level_color(ui, level)),
+                        LineKind::Input => RichText::new(format!("> {}", line.text)).strong(),
+                        LineKind::Output => RichText::new(&line.text),
+                        LineKind::Error => RichText::new(&line.text).color(level_color(ui, Level::ERROR)),
+                    };
+                    ui.label(text.monospace());
+                }
+            });
+
+        let input_id = ui.id().with("console_input");
+        if ui.memory(|memory| memory.has_focus(input_id)) {
+            if ui.input_mut(|input| input.consume_key(Modifiers::NONE, Key::Tab)) {
+                console.complete_requested = true;
+            }
+            if ui.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowUp)) {
+                console.step_history(true);
+            }
+            if ui.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowDown)) {
+                console.step_history(false);
+            }
+        }
+        let response = ui.add(egui::TextEdit::singleline(&mut console.input)
+            .id(input_id)
+            .lock_focus(true)
+            .font(egui::TextStyle::Monospace)
+            .desired_width(f32::INFINITY)
+            .hint_text(get!("console.input")));
+        if response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter)) {
+            let line = std::mem::take(&mut console.input);
+            console.submit(&line);
+            console.focus_input = true;
+        }
+        if console.focus_input {
+            response.request_focus();
+            console.focus_input = false;
+        }
+    }
+
+    fn capture_logs(captured: Option<NonSend<CapturedLogs>>, mut console: ResMut<Self>) {
+        let Some(captured) = captured else { return; };
+        for line in captured.0.try_iter() {
+            console.push(line.kind, &line.text);
+        }
+    }
+
+    /// Commands get the whole world, so they run in an exclusive system rather than from the UI.
+    fn run_commands(world: &mut World) {
+        let (pending, complete_requested) = {
+            let mut console = world.resource_mut::<Self>();
+            if console.pending.is_empty() && !console.complete_requested {
+                return;
+            }
+            (std::mem::take(&mut console.pending), std::mem::take(&mut console.complete_requested))
+        };
+
+        for line in pending {
+            world.resource_mut::<Self>().push(LineKind::Input, &line);
+            let (result, kind) = match Self::run_line(world, &line) {
+                Ok(output) => (output, LineKind::Output),
+                Err(e) => (e, LineKind::Error),
+            };
+            world.resource_mut::<Self>().push(kind, &result);
+        }
+
+        if complete_requested {
+            Self::complete(world);
+        }
+    }
+
+    fn run_line(world: &mut World, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else { return Ok(String::new()); };
+        let args: Vec<&str> = words.collect();
+        // The handler is copied out so that the command can look at the registry too, like `help` does.
+        let run = world.resource::<ConsoleCommands>()
+            .get(name)
+            .map(|command| command.run)
+            .ok_or_else(|| get!("console.unknown", "command", name))?;
+        run(world, &args)
+    }
+
+    fn complete(world: &mut World) {
+        let input = world.resource::<Self>().input.clone();
+        let (prefix, partial, candidates) = match input.split_once(' ') {
+            None => (String::new(), input.as_str(), world.resource::<ConsoleCommands>().names()),
+            Some((name, rest)) => {
+                let complete = world.resource::<ConsoleCommands>().get(name).and_then(|command| command.complete);
+                let Some(complete) = complete else { return; };
+                (format!("{} ", name), rest, complete(world))
+            }
+        };
+
+        let (completed, matches) = completion(partial, &candidates);
+        let mut console = world.resource_mut::<Self>();
+        if matches.len() > 1 {
+            console.push(LineKind::Output, &matches.join("  "));
+        }
+        if let Some(completed) = completed {
+            let space = if matches.len() == 1 && prefix.is_empty() { " " } else { "" };
+            console.input = format!("{}{}{}", prefix, completed, space);
+        }
+        console.focus_input = true;
+    }
+}
+
+impl ConsolePlugin {
+    fn help(world: &mut World, _args: &[&str]) -> Result<String, String> {
+        let commands = world.resource::<ConsoleCommands>();
+        Ok(commands.commands.values()
+            .map(|command| get!(command.help_key))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn clear(world: &mut World, _args: &[&str]) -> Result<String, String> {
+        world.resource_mut::<Console>().clear();
+        Ok(String::new())
+    }
+
+    fn lang(world: &mut World, args: &[&str]) -> Result<String, String> {
+        let [code] = args else {
+            return Err(world.resource::<ConsoleCommands>().get("lang").map(ConsoleCommand::usage).unwrap_or_default());
+        };
+        if !Self::lang_names(world).iter().any(|name| name == code) {
+            return Err(get!("console.no_such_lang", "lang", code));
+        }
+        lang::change_lang(code)?;
+        Ok(get!("console.lang_changed", "lang", code))
+    }
+
+    fn lang_names(_world: &mut World) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(lang::lang_dir()) else { return vec![]; };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "toml" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_string_lossy().into_owned())
+            })
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+fn level_color(ui: &Ui, level: Level) -> Color32 {
+    match level {
+        Level::ERROR => Color32::from_rgb(230, 80, 80),
+        Level::WARN => Color32::from_rgb(230, 200, 60),
+        Level::INFO => ui.visuals().text_color(),
+        _ => ui.visuals().weak_text_color(),
+    }
+}
+
+/// Returns what `partial` can be extended to, and every candidate it could become.
+fn completion(partial: &str, candidates: &[String]) -> (Option<String>, Vec<String>) {
+    let lower = partial.to_lowercase();
+    let matches: Vec<String> = candidates
+        .iter()
+        .filter(|candidate| candidate.to_lowercase().starts_with(&lower))
+        .cloned()
+        .collect();
+    let Some(first) = matches.first() else {
+        return (None, matches);
+    };
+    let mut common = first.clone();
+    for other in &matches[1..] {
+        let shared = common.chars()
+            .zip(other.chars())
+            .take_while(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+            .count();
+        common = common.chars().take(shared).collect();
+    }
+    if common.chars().count() < partial.chars().count() {
+        return (None, matches);
+    }
+    (Some(common), matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_unique_completion() {
+        let (completed, matches) = completion("se", &names(&["select", "bake", "tool"]));
+        assert_eq!(completed.as_deref(), Some("select"));
+        assert_eq!(matches.len(), 1);
+    }
+
+    #[test]
+    fn test_common_prefix_completion() {
+        let (completed, matches) = completion("c", &names(&["camera", "clear", "cube"]));
+        assert_eq!(completed.as_deref(), Some("c"));
+        assert_eq!(matches.len(), 3);
+
+        let (completed, _) = completion("cub", &names(&["Cube 1", "Cube 2"]));
+        assert_eq!(completed.as_deref(), Some("Cube "));
+    }
+
+    #[test]
+    fn test_no_completion() {
+        let (completed, matches) = completion("x", &names(&["select", "bake"]));
+        assert!(completed.is_none());
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut console = Console::default();
+        console.submit("tool room");
+        console.submit("bake");
+        console.submit("bake");
+        assert_eq!(console.history.len(), 2);
+
+        console.step_history(true);
+        assert_eq!(console.input, "bake");
+        console.step_history(true);
+        assert_eq!(console.input, "tool room");
+        console.step_history(true);
+        assert_eq!(console.input, "tool room");
+        console.step_history(false);
+        assert_eq!(console.input, "bake");
+        console.step_history(false);
+        assert_eq!(console.input, "");
+    }
+}
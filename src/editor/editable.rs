@@ -1,7 +1,7 @@
-use bevy::platform::collections::HashMap;
+use bevy::platform::collections::{HashMap, HashSet};
 use bevy::prelude::*;
-use bevy_egui::{egui, EguiContextPass, EguiContexts};
-use bevy_egui::egui::{Context, Widget};
+use bevy_egui::egui;
+use bevy_egui::egui::{Ui, Widget};
 use lazy_static::lazy_static;
 use serde::{Serialize, Deserialize};
 use crate::common::cuboid::{CuboidPoint, GrackleCuboid};
@@ -10,7 +10,7 @@ use crate::editor::global_point::GlobalPoint;
 use crate::get;
 
 lazy_static! {
-    static ref MAP_EXT: String = "gmp".to_owned(); // Grackle MaP
+    pub static ref MAP_EXT: String = "gmp".to_owned(); // Grackle MaP
     static ref MAP_ART: String = "gma".to_owned(); // Grackle Map Artifact
 }
 
@@ -20,7 +20,7 @@ impl Plugin for EditorStepsPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<EditorActions>()
-            .add_systems(EguiContextPass, EditorActions::floating_ui)
+            .add_systems(Update, EditorActions::draw_selected_gizmos)
         ;
     }
 }
@@ -28,9 +28,20 @@ impl Plugin for EditorStepsPlugin {
 #[typetag::serde]
 pub trait EditorObject: Send + Sync {
     fn get_point(&self, key: &str) -> Result<Vec3, PointResolutionError>;
-    fn editor_ui(&mut self, ctx: &mut Context);
+    /// Draws this object's fields into the Inspector.
+    fn inspector_ui(&mut self, ui: &mut Ui);
     fn type_name(&self) -> String;
     fn debug_gizmos(&self, gizmos: &mut Gizmos);
+    /// Points on this object that tools can snap to.
+    fn snap_points(&self) -> Vec<Vec3> {
+        Vec::new()
+    }
+    /// The box the object takes up, for framing it in the viewports. `None` if it has no place in the world.
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        None
+    }
+    /// Copies the object, for saving the map without taking its actions away from the editor.
+    fn boxed_clone(&self) -> Box<dyn EditorObject>;
 }
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy)]
@@ -85,6 +96,54 @@ impl EditorActions {
     pub fn get_action(&self, id: &EditorActionId) -> Option<&EditorAction> {
         self.actions.get(id)
     }
+
+    /// Checks that every action's parents exist and were taken before it.
+    /// Actions whose parents have problems are reported too, as propagated errors.
+    pub fn diagnose(&self) -> Vec<(EditorActionId, PointResolutionError)> {
+        let mut problems = Vec::new();
+        let mut seen = HashSet::new();
+        let mut failed = HashSet::new();
+        for id in &self.action_order {
+            if let Some(action) = self.actions.get(id) {
+                let error = action.parents.iter().find_map(|parent| {
+                    if !seen.contains(parent) {
+                        Some(PointResolutionError::NoSuchReferent)
+                    } else if failed.contains(parent) {
+                        Some(PointResolutionError::PropagatedError)
+                    } else {
+                        None
+                    }
+                });
+                if let Some(error) = error {
+                    failed.insert(*id);
+                    problems.push((*id, error));
+                }
+            }
+            seen.insert(*id);
+        }
+        problems
+    }
+
+    /// Every action, in the order they were taken.
+    pub fn iter(&self) -> impl Iterator<Item = &EditorAction> {
+        self.action_order.iter().filter_map(|id| self.actions.get(id))
+    }
+
+    /// Replaces every action with these, in this order, keeping their ids. Used when loading a map.
+    pub fn replace(&mut self, actions: Vec<EditorAction>) {
+        self.id_counter = actions.iter().map(|action| action.id._id + 1).max().unwrap_or(0);
+        self.action_order = actions.iter().map(|action| action.id).collect();
+        self.actions = actions.into_iter().map(|action| (action.id, action)).collect();
+        self.selected_action = None;
+    }
+
+    pub fn selected(&self) -> Option<EditorActionId> {
+        self.selected_action
+    }
+
+    pub fn select(&mut self, id: Option<EditorActionId>) {
+        self.selected_action = id;
+    }
     
     pub fn ui(
         ui: &mut egui::Ui,
@@ -115,17 +174,22 @@ impl EditorActions {
         }
     }
     
-    fn floating_ui(mut contexts: EguiContexts, mut actions: ResMut<Self>, mut gizmos: Gizmos,) {
-        let ctx = contexts.try_ctx_mut();
-        if ctx.is_none() {
-            return;
-        }
-        let ctx = ctx.unwrap();
-        
-        if let Some(selected_id) = actions.selected_action {
-            let action = actions.actions.get_mut(&selected_id).unwrap();
-            action.object.editor_ui(ctx);
+    /// Shows the selected action in the Inspector. Returns false if nothing is selected.
+    pub fn inspector_ui(ui: &mut Ui, actions: &mut Self) -> bool {
+        let Some(selected_id) = actions.selected_action else {
+            return false;
+        };
+        let Some(action) = actions.actions.get_mut(&selected_id) else {
+            return false;
+        };
+        ui.heading(action.type_name_with_id());
+        action.object.inspector_ui(ui);
+        true
+    }
 
+    fn draw_selected_gizmos(actions: Res<Self>, mut gizmos: Gizmos,) {
+        if let Some(selected_id) = actions.selected_action
+            && let Some(action) = actions.actions.get(&selected_id) {
             action.object.debug_gizmos(&mut gizmos);
         }
     }
@@ -154,11 +218,33 @@ pub struct EditorAction {
     parents: Vec<EditorActionId>,
 }
 
+impl Clone for EditorAction {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            object: self.object.boxed_clone(),
+            parents: self.parents.clone(),
+        }
+    }
+}
+
 impl EditorAction {
+    pub fn id(&self) -> EditorActionId {
+        self.id
+    }
+
     pub fn get_point(&self, key: &str) -> Result<Vec3, PointResolutionError> {
         self.object.get_point(key)
     }
     
+    pub fn snap_points(&self) -> Vec<Vec3> {
+        self.object.snap_points()
+    }
+
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.object.bounds()
+    }
+
     pub fn type_name(&self) -> String {
         self.object.type_name()
     }
@@ -190,3 +276,45 @@ impl Ref32 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose_finds_missing_and_propagated_parents() {
+        let mut actions = EditorActions::default();
+        let [first, second, third] = actions.action_order[..] else { panic!("expected three starting actions") };
+        // The second action depends on one taken after it, and the third depends on the second.
+        actions.actions.get_mut(&second).unwrap().parents = vec![third];
+        actions.actions.get_mut(&third).unwrap().parents = vec![second];
+
+        let problems = actions.diagnose();
+        assert_eq!(problems.len(), 2);
+        assert!(matches!(problems[0], (id, PointResolutionError::NoSuchReferent) if id == second));
+        assert!(matches!(problems[1], (id, PointResolutionError::PropagatedError) if id == third));
+        assert!(problems.iter().all(|(id, _)| *id != first));
+    }
+
+    #[test]
+    fn test_diagnose_accepts_earlier_parents() {
+        let mut actions = EditorActions::default();
+        let [first, second, _] = actions.action_order[..] else { panic!("expected three starting actions") };
+        actions.actions.get_mut(&second).unwrap().parents = vec![first];
+        assert!(actions.diagnose().is_empty());
+    }
+
+    #[test]
+    fn test_replaced_actions_keep_their_ids() {
+        let mut actions = EditorActions::default();
+        let saved: Vec<EditorAction> = actions.iter().skip(1).cloned().collect();
+        actions.select(Some(saved[0].id()));
+        actions.replace(saved);
+        assert_eq!(actions.iter().map(EditorAction::id).collect::<Vec<_>>(), [EditorActionId { _id: 1 }, EditorActionId { _id: 2 }]);
+        assert_eq!(actions.selected(), None);
+
+        actions.take_action(Box::new(GlobalPoint::new(0.0, 0.0, 0.0)));
+        assert_eq!(actions.iter().last().unwrap().id(), EditorActionId { _id: 3 });
+    }
+}
+
@@ -1,14 +1,14 @@
 use bevy::prelude::*;
 use bevy::reflect::erased_serde::{Error, Serializer};
 use bevy_egui::egui;
-use bevy_egui::egui::{Context, Slider, SliderClamping, Ui};
-use bevy_egui::egui::style::HandleShape;
+use bevy_egui::egui::Ui;
 use serde::{Deserialize, Serialize};
 use crate::common::PointResolutionError;
 use crate::editor::editable::EditorObject;
+use crate::editor::inspector::drag_vec3;
 use crate::get;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct GlobalPoint {
     location: Vec3,
 }
@@ -19,23 +19,11 @@ impl EditorObject for GlobalPoint {
         Ok(self.location)
     }
 
-    fn editor_ui(&mut self, ctx: &mut Context) {
-        egui::Window::new(self.type_name()).show(ctx, |ui| {
-            ui.add(Slider::new(&mut self.location.x, -10.0..=10.0)
-                .text("x")
-                .clamping(SliderClamping::Never)
-                .handle_shape(HandleShape::Rect { aspect_ratio: 1.0 })
-            );
-            ui.add(Slider::new(&mut self.location.y, -10.0..=10.0)
-                .text("y")
-                .clamping(SliderClamping::Never)
-                .handle_shape(HandleShape::Rect { aspect_ratio: 1.0 })
-            );
-            ui.add(Slider::new(&mut self.location.z, -10.0..=10.0)
-                .text("z")
-                .clamping(SliderClamping::Never)
-                .handle_shape(HandleShape::Rect { aspect_ratio: 1.0 })
-            );
+    fn inspector_ui(&mut self, ui: &mut Ui) {
+        egui::Grid::new("global_point").num_columns(4).show(ui, |ui| {
+            ui.label(get!("inspector.position"));
+            drag_vec3(ui, &mut self.location, 0.01);
+            ui.end_row();
         });
     }
 
@@ -46,6 +34,14 @@ impl EditorObject for GlobalPoint {
     fn debug_gizmos(&self, gizmos: &mut Gizmos) {
         gizmos.sphere(Isometry3d::from_translation(self.location), 0.2, Color::srgb_u8(0, 255, 0));
     }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.location, self.location))
+    }
+
+    fn boxed_clone(&self) -> Box<dyn EditorObject> {
+        Box::new(self.clone())
+    }
 }
 
 impl GlobalPoint {
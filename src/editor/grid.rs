@@ -0,0 +1,218 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::view::RenderLayers;
+use crate::editor::multicam::Multicam;
+use crate::tool::snap::SnapSettings;
+
+/// Grid lines closer together than this many pixels are skipped in favour of coarser ones.
+const MIN_LINE_PIXELS: f32 = 8.0;
+/// Every this many minor lines, there's a major one.
+const MAJOR_EVERY: i64 = 8;
+/// Grids never draw more than this many lines along one axis, however far out the view is zoomed.
+const MAX_LINES: i64 = 2000;
+
+const MINOR_COLOR: Color = Color::srgba(0.5, 0.5, 0.5, 0.18);
+const MAJOR_COLOR: Color = Color::srgba(0.6, 0.6, 0.6, 0.4);
+
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                ViewportGrid::spawn,
+                ViewportGrid::update,
+            ).chain())
+        ;
+    }
+}
+
+/// A grid drawn only in `camera`, on the plane it looks straight at.
+/// Each camera gets its own render layer for its grid, so the other views don't see it. Cameras are put
+/// on their grid's layer along with the ones for their render mode.
+#[derive(Component)]
+pub struct ViewportGrid {
+    pub camera: Entity,
+    /// What the mesh was last built from, so it's only rebuilt when something changes.
+    built: Option<GridKey>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct GridKey {
+    depth_index: usize,
+    depth: f32,
+    spacing: f32,
+    /// The range of lines drawn along each of the two plane axes, in multiples of `spacing`.
+    range: [(i64, i64); 2],
+}
+
+impl ViewportGrid {
+    pub fn layer(multicam: &Multicam) -> usize {
+        1 + multicam.id as usize
+    }
+
+    fn spawn(
+        mut commands: Commands,
+        cameras: Query<(Entity, &Multicam), Added<Multicam>>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+    ) {
+        for (camera, multicam) in &cameras {
+            let layer = Self::layer(multicam);
+            commands.spawn((
+                ViewportGrid { camera, built: None },
+                Mesh3d(meshes.add(Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default()))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: Color::WHITE,
+                    unlit: true,
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                })),
+                Transform::default(),
+                Visibility::Hidden,
+                RenderLayers::layer(layer),
+                NotShadowCaster,
+            ));
+        }
+    }
+
+    fn update(
+        mut grids: Query<(&mut ViewportGrid, &Mesh3d, &mut Visibility)>,
+        cameras: Query<(&Camera, &GlobalTransform, &Projection, &Multicam)>,
+        snap: Res<SnapSettings>,
+        mut meshes: ResMut<Assets<Mesh>>,
+    ) {
+        for (mut grid, mesh, mut visibility) in &mut grids {
+            let Ok((camera, camera_tfm, Projection::Orthographic(projection), multicam)) = cameras.get(grid.camera) else {
+                visibility.set_if_neq(Visibility::Hidden);
+                continue;
+            };
+            let (Some(depth_index), Some(direction), Some(size)) =
+                (multicam.axis.index(), multicam.axis.direction(), camera.logical_viewport_size()) else {
+                visibility.set_if_neq(Visibility::Hidden);
+                continue;
+            };
+            visibility.set_if_neq(Visibility::Inherited);
+
+            let spacing = grid_spacing(snap.granularity, projection.scale);
+            // Push the grid almost to the far plane so everything in the view draws over it.
+            let depth = (camera_tfm.translation() - direction * projection.far * 0.9)[depth_index];
+            let half_extent = size.max_element() * projection.scale / 2.0;
+            let center = camera_tfm.translation();
+            let plane_axes = plane_axes(depth_index);
+            let range = plane_axes.map(|axis| {
+                let min = ((center[axis] - half_extent) / spacing).floor() as i64;
+                let max = ((center[axis] + half_extent) / spacing).ceil() as i64;
+                (min.max(max - MAX_LINES), max)
+            });
+            let key = GridKey { depth_index, depth, spacing, range };
+            if grid.built == Some(key) {
+                continue;
+            }
+            grid.built = Some(key);
+
+            if let Some(mesh) = meshes.get_mut(&mesh.0) {
+                let (positions, colors) = grid_lines(&key);
+                mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+                mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+            }
+        }
+    }
+}
+
+/// The two world axes a view down `depth_index` shows, as indices.
+fn plane_axes(depth_index: usize) -> [usize; 2] {
+    match depth_index {
+        0 => [1, 2],
+        1 => [0, 2],
+        _ => [0, 1],
+    }
+}
+
+fn axis_color(axis: usize) -> Color {
+    match axis {
+        0 => Color::srgb_u8(230, 60, 60),
+        1 => Color::srgb_u8(60, 230, 60),
+        _ => Color::srgb_u8(60, 60, 230),
+    }
+}
+
+/// The snap size, doubled until neighbouring lines are at least `MIN_LINE_PIXELS` apart at this zoom.
+/// `scale` is world units per pixel.
+pub fn grid_spacing(granularity: f32, scale: f32) -> f32 {
+    let mut spacing = if granularity > 0.0 { granularity } else { 1.0 };
+    if scale <= 0.0 {
+        return spacing;
+    }
+    while spacing / scale < MIN_LINE_PIXELS {
+        spacing *= 2.0;
+    }
+    spacing
+}
+
+fn grid_lines(key: &GridKey) -> (Vec<[f32; 3]>, Vec<[f32; 4]>) {
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let axes = plane_axes(key.depth_index);
+    for (i, axis) in axes.iter().enumerate() {
+        // Lines at fixed values of `axis` run along the other plane axis.
+        let along = axes[1 - i];
+        let (along_min, along_max) = key.range[1 - i];
+        let (min, max) = key.range[i];
+        for step in min..=max {
+            let mut start = Vec3::ZERO;
+            start[key.depth_index] = key.depth;
+            start[*axis] = step as f32 * key.spacing;
+            let mut end = start;
+            start[along] = along_min as f32 * key.spacing;
+            end[along] = along_max as f32 * key.spacing;
+
+            let color = if step == 0 {
+                axis_color(along)
+            } else if step % MAJOR_EVERY == 0 {
+                MAJOR_COLOR
+            } else {
+                MINOR_COLOR
+            };
+            let color = color.to_linear().to_f32_array();
+            positions.push(start.to_array());
+            positions.push(end.to_array());
+            colors.push(color);
+            colors.push(color);
+        }
+    }
+    (positions, colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spacing_matches_snap_when_zoomed_in() {
+        assert_eq!(grid_spacing(0.1, 0.01), 0.1);
+        assert_eq!(grid_spacing(0.25, 0.001), 0.25);
+    }
+
+    #[test]
+    fn test_spacing_coarsens_when_zoomed_out() {
+        let spacing = grid_spacing(0.1, 0.05);
+        assert!(spacing / 0.05 >= MIN_LINE_PIXELS);
+        // Coarser grids still line up with the snap grid.
+        let multiple = spacing / 0.1;
+        assert!((multiple - multiple.round()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_origin_lines_are_axis_colored() {
+        let key = GridKey { depth_index: 1, depth: -10.0, spacing: 1.0, range: [(-2, 2), (-3, 3)] };
+        let (positions, colors) = grid_lines(&key);
+        assert_eq!(positions.len(), 2 * (5 + 7));
+        assert!(positions.iter().all(|position| position[1] == -10.0));
+
+        let origin_line = positions.chunks(2).position(|line| line[0][0] == 0.0 && line[1][0] == 0.0).unwrap();
+        assert_eq!(colors[origin_line * 2], axis_color(2).to_linear().to_f32_array());
+    }
+}
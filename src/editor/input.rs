@@ -6,8 +6,14 @@ use std::panic::Location;
 use bevy::input::mouse::MouseMotion;
 use bevy::picking::pointer::{PointerId, PointerLocation};
 use bevy::tasks::futures_lite::StreamExt;
+use crate::editor::keymap::{ActionState, InputAction, Modifiers};
 use crate::editor::multicam::Multicam;
 
+/// How far, in logical pixels, the cursor has to move with a button held before it counts as a drag rather than a click.
+pub const DRAG_THRESHOLD: f32 = 4.0;
+/// How soon, in seconds, a second click has to follow the first to make a double-click.
+const DOUBLE_CLICK_TIME: f32 = 0.4;
+
 pub struct EditorInputPlugin;
 
 impl Plugin for EditorInputPlugin {
@@ -17,14 +23,136 @@ impl Plugin for EditorInputPlugin {
             .init_resource::<CurrentKeyboardInput>()
             .add_systems(PreUpdate, (
                 Self::mouse_input,
-                Self::keyboard_input,
+                Self::pointer_gestures.after(Self::mouse_input),
+                Self::keyboard_input.after(ActionState::update),
             ))
         ;
     }
 }
 
+/// Every mouse button held at once, with the keyboard modifiers held alongside them.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct MouseChord {
+    pub left: bool,
+    pub right: bool,
+    pub middle: bool,
+    pub modifiers: Modifiers,
+}
+
+impl MouseChord {
+    fn held(mouse_buttons: &ButtonInput<MouseButton>, modifiers: Modifiers) -> Self {
+        Self {
+            left: mouse_buttons.pressed(MouseButton::Left),
+            right: mouse_buttons.pressed(MouseButton::Right),
+            middle: mouse_buttons.pressed(MouseButton::Middle),
+            modifiers,
+        }
+    }
+
+    pub fn contains(&self, button: MouseButton) -> bool {
+        match button {
+            MouseButton::Left => self.left,
+            MouseButton::Right => self.right,
+            MouseButton::Middle => self.middle,
+            _ => false,
+        }
+    }
+
+    /// Whether exactly these buttons are held, and no others.
+    pub fn is(&self, buttons: &[MouseButton]) -> bool {
+        [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+            .iter()
+            .all(|button| self.contains(*button) == buttons.contains(button))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.left && !self.right && !self.middle
+    }
+
+    fn with_buttons_of(self, other: &Self) -> Self {
+        Self {
+            left: self.left || other.left,
+            right: self.right || other.right,
+            middle: self.middle || other.middle,
+            modifiers: self.modifiers,
+        }
+    }
+}
+
+/// Something the pointer finished doing this frame. Each carries every button that was held at some point during it,
+/// and the modifiers held when it started.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PointerGesture {
+    Click { chord: MouseChord, double: bool },
+    DragStart(MouseChord),
+    Drag(MouseChord),
+    DragEnd(MouseChord),
+}
+
+struct ActiveGesture {
+    chord: MouseChord,
+    /// Where the cursor was when the gesture started, in logical pixels.
+    start: Option<Vec2>,
+    dragging: bool,
+}
+
+/// Follows the buttons and the cursor from frame to frame and sorts them into gestures.
+#[derive(Default)]
+struct GestureTracker {
+    held: MouseChord,
+    active: Option<ActiveGesture>,
+    last_click: Option<(MouseChord, Option<Vec2>, f32)>,
+}
+
+impl GestureTracker {
+    /// Takes the buttons held and the cursor position (in logical pixels) at `now` seconds, and returns what the
+    /// pointer did this frame. New gestures only start when `can_start` is set.
+    fn update(&mut self, chord: MouseChord, cursor: Option<Vec2>, now: f32, can_start: bool) -> Option<PointerGesture> {
+        let just_pressed = [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+            .iter()
+            .any(|button| chord.contains(*button) && !self.held.contains(*button));
+        self.held = chord;
+
+        let Some(mut active) = self.active.take() else {
+            if just_pressed && can_start {
+                self.active = Some(ActiveGesture { chord, start: cursor, dragging: false });
+            }
+            return None;
+        };
+
+        active.chord = active.chord.with_buttons_of(&chord);
+        let moved = matches!((active.start, cursor), (Some(start), Some(cursor)) if start.distance(cursor) > DRAG_THRESHOLD);
+        let mut gesture = None;
+        if !active.dragging && moved {
+            active.dragging = true;
+            gesture = Some(PointerGesture::DragStart(active.chord));
+        } else if active.dragging {
+            gesture = Some(PointerGesture::Drag(active.chord));
+        }
+
+        if !chord.is_empty() {
+            self.active = Some(active);
+            return gesture;
+        }
+        if active.dragging {
+            return Some(PointerGesture::DragEnd(active.chord));
+        }
+        let double = self.last_click.is_some_and(|(last_chord, last_cursor, time)| {
+            let close = match (last_cursor, cursor) {
+                (Some(last), Some(cursor)) => last.distance(cursor) <= DRAG_THRESHOLD,
+                _ => true,
+            };
+            last_chord == active.chord && now - time <= DOUBLE_CLICK_TIME && close
+        });
+        // A third click starts over rather than making another double-click.
+        self.last_click = if double { None } else { Some((active.chord, cursor, now)) };
+        Some(PointerGesture::Click { chord: active.chord, double })
+    }
+}
+
 #[derive(Resource)]
 pub struct CurrentMouseInput {
+    /// The button in charge. With several held, it's whichever was held first.
     pub pressed: Option<MouseButton>,
     pub just_pressed: bool,
     pub released: Option<MouseButton>,
@@ -35,6 +163,10 @@ pub struct CurrentMouseInput {
     pub normalized_pos: Option<Vec2>,
     pub global_pos: Option<Vec2>,
     pub world_pos: Option<Ray3d>,
+    /// Every button held right now.
+    pub chord: MouseChord,
+    pub gesture: Option<PointerGesture>,
+    gestures: GestureTracker,
 }
 
 impl Default for CurrentMouseInput {
@@ -50,6 +182,9 @@ impl Default for CurrentMouseInput {
             normalized_pos: None,
             global_pos: None,
             world_pos: None,
+            chord: MouseChord::default(),
+            gesture: None,
+            gestures: GestureTracker::default(),
         }
     }
 }
@@ -57,8 +192,12 @@ impl Default for CurrentMouseInput {
 #[derive(Resource, Default)]
 pub struct CurrentKeyboardInput {
     pub modify: bool,
+    pub toggle: bool,
     pub confirm: bool,
     pub cancel: bool,
+    pub undo: bool,
+    pub redo: bool,
+    pub jump: bool,
     forward: bool,
     left: bool,
     right: bool,
@@ -128,13 +267,14 @@ impl CurrentKeyboardInput {
         }
     }
 
-    pub fn update(&mut self, keys: &Res<ButtonInput<KeyCode>>) {
-        self.forward = keys.pressed(KeyCode::KeyW);
-        self.left = keys.pressed(KeyCode::KeyA);
-        self.right = keys.pressed(KeyCode::KeyD);
-        self.backward = keys.pressed(KeyCode::KeyS);
-        self.up = keys.pressed(KeyCode::KeyQ);
-        self.down = keys.pressed(KeyCode::KeyE);
+    pub fn update(&mut self, actions: &ActionState) {
+        self.forward = actions.pressed(InputAction::CameraForward);
+        self.left = actions.pressed(InputAction::CameraLeft);
+        self.right = actions.pressed(InputAction::CameraRight);
+        self.backward = actions.pressed(InputAction::CameraBackward);
+        self.up = actions.pressed(InputAction::CameraUp);
+        self.down = actions.pressed(InputAction::CameraDown);
+        self.jump = actions.pressed(InputAction::Jump);
     }
 }
 
@@ -162,7 +302,7 @@ impl EditorInputPlugin {
         }
 
         let window = primary_window.single().unwrap();
-        let (just_pressed, pressed, released) = mouse_precedence(mouse_buttons);
+        let (just_pressed, pressed, released) = mouse_precedence(mouse_buttons, current_input.pressed);
         current_input.just_pressed = just_pressed;
         current_input.pressed = pressed;
         current_input.released = released;
@@ -170,6 +310,10 @@ impl EditorInputPlugin {
         let mut locations = Vec::new();
         for (_, pointer) in pointers {
             for (camera_entity, camera, camera_transform, cam_multicam) in &cameras {
+                // Cameras hidden by the viewport layout keep their old viewports.
+                if !camera.is_active {
+                    continue;
+                }
                 if let Some(pointer_loc) = pointer.location() {
                     if pointer_loc.is_in_viewport(camera, &primary_window_entity) {
                         if pressed.is_some() {
@@ -217,14 +361,35 @@ impl EditorInputPlugin {
         }
     }
     
+    /// Sorts what the buttons are doing into clicks, double-clicks and drags. Gestures can only start outside egui,
+    /// but once started they're followed wherever the pointer goes.
+    fn pointer_gestures(
+        mut egui_contexts: EguiContexts,
+        mouse_buttons: Res<ButtonInput<MouseButton>>,
+        keys: Res<ButtonInput<KeyCode>>,
+        primary_window: Query<&Window, With<PrimaryWindow>>,
+        time: Res<Time>,
+        mut current_input: ResMut<CurrentMouseInput>,
+    ) {
+        let chord = MouseChord::held(&mouse_buttons, Modifiers::held(&keys));
+        let cursor = primary_window.single().ok().and_then(Window::cursor_position);
+        let over_egui = egui_contexts.try_ctx_mut().is_some_and(|ctx| ctx.is_pointer_over_area());
+        current_input.chord = chord;
+        let gesture = current_input.gestures.update(chord, cursor, time.elapsed_secs(), !over_egui);
+        current_input.gesture = gesture;
+    }
+
     fn keyboard_input(
         mut current_input: ResMut<CurrentKeyboardInput>,
-        keys: Res<ButtonInput<KeyCode>>,
+        actions: Res<ActionState>,
     ) {
-        current_input.modify = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
-        current_input.update(&keys);
-        current_input.confirm = keys.just_released(KeyCode::Enter) || keys.just_released(KeyCode::NumpadEnter);
-        current_input.cancel = keys.just_released(KeyCode::Escape);
+        current_input.modify = actions.pressed(InputAction::Modify);
+        current_input.toggle = actions.pressed(InputAction::Toggle);
+        current_input.update(&actions);
+        current_input.confirm = actions.just_released(InputAction::Confirm);
+        current_input.cancel = actions.just_released(InputAction::Cancel);
+        current_input.undo = actions.just_pressed(InputAction::Undo);
+        current_input.redo = actions.just_pressed(InputAction::Redo);
     }
 }
 
@@ -241,7 +406,7 @@ fn make_ray(
     camera.viewport_to_world(camera_tfm, pointer_loc.position).ok()
 }
 
-fn mouse_precedence(mouse_buttons: Res<ButtonInput<MouseButton>>) -> (bool, Option<MouseButton>, Option<MouseButton>) {
+fn mouse_precedence(mouse_buttons: Res<ButtonInput<MouseButton>>, previous: Option<MouseButton>) -> (bool, Option<MouseButton>, Option<MouseButton>) {
     let left = mouse_buttons.pressed(MouseButton::Left);
     let right = mouse_buttons.pressed(MouseButton::Right);
     let middle = mouse_buttons.pressed(MouseButton::Middle);
@@ -272,5 +437,90 @@ fn mouse_precedence(mouse_buttons: Res<ButtonInput<MouseButton>>) -> (bool, Opti
         return (mouse_buttons.just_pressed(MouseButton::Middle), Some(MouseButton::Middle), None);
     }
 
-    (false, None, None)
+    // Pressing another button on top doesn't take over from the one already held.
+    (false, previous.filter(|button| mouse_buttons.pressed(*button)), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_MODIFIERS: Modifiers = Modifiers { ctrl: false, shift: false, alt: false };
+    const LEFT: MouseChord = MouseChord { left: true, right: false, middle: false, modifiers: NO_MODIFIERS };
+    const RIGHT: MouseChord = MouseChord { left: false, right: true, middle: false, modifiers: NO_MODIFIERS };
+    const NONE: MouseChord = MouseChord { left: false, right: false, middle: false, modifiers: NO_MODIFIERS };
+
+    /// Presses and releases `chord` at `at`, a frame apart.
+    fn click(tracker: &mut GestureTracker, chord: MouseChord, at: Vec2, now: f32) -> Option<PointerGesture> {
+        tracker.update(chord, Some(at), now, true);
+        tracker.update(NONE, Some(at), now + 0.01, true)
+    }
+
+    #[test]
+    fn test_drags_start_past_the_threshold() {
+        let mut tracker = GestureTracker::default();
+        let start = Vec2::new(100.0, 100.0);
+        tracker.update(LEFT, Some(start), 0.0, true);
+        assert_eq!(tracker.update(LEFT, Some(start + Vec2::X * DRAG_THRESHOLD), 0.1, true), None);
+        assert_eq!(tracker.update(LEFT, Some(start + Vec2::X * (DRAG_THRESHOLD + 1.0)), 0.2, true), Some(PointerGesture::DragStart(LEFT)));
+        assert_eq!(tracker.update(LEFT, Some(start), 0.3, true), Some(PointerGesture::Drag(LEFT)));
+        assert_eq!(tracker.update(NONE, Some(start), 0.4, true), Some(PointerGesture::DragEnd(LEFT)));
+    }
+
+    #[test]
+    fn test_jitter_under_the_threshold_still_clicks() {
+        let mut tracker = GestureTracker::default();
+        let start = Vec2::new(100.0, 100.0);
+        tracker.update(LEFT, Some(start), 0.0, true);
+        assert_eq!(tracker.update(LEFT, Some(start + Vec2::new(2.0, 2.0)), 0.1, true), None);
+        assert_eq!(tracker.update(NONE, Some(start + Vec2::new(1.0, -2.0)), 0.2, true), Some(PointerGesture::Click { chord: LEFT, double: false }));
+    }
+
+    #[test]
+    fn test_double_clicks_time_out() {
+        let mut tracker = GestureTracker::default();
+        let at = Vec2::new(50.0, 50.0);
+        click(&mut tracker, LEFT, at, 0.0);
+        assert_eq!(click(&mut tracker, LEFT, at, DOUBLE_CLICK_TIME + 0.5), Some(PointerGesture::Click { chord: LEFT, double: false }));
+        assert_eq!(click(&mut tracker, LEFT, at, DOUBLE_CLICK_TIME + 0.7), Some(PointerGesture::Click { chord: LEFT, double: true }));
+    }
+
+    #[test]
+    fn test_third_click_starts_over() {
+        let mut tracker = GestureTracker::default();
+        let at = Vec2::new(50.0, 50.0);
+        assert_eq!(click(&mut tracker, LEFT, at, 0.0), Some(PointerGesture::Click { chord: LEFT, double: false }));
+        assert_eq!(click(&mut tracker, LEFT, at, 0.1), Some(PointerGesture::Click { chord: LEFT, double: true }));
+        assert_eq!(click(&mut tracker, LEFT, at, 0.2), Some(PointerGesture::Click { chord: LEFT, double: false }));
+        assert_eq!(click(&mut tracker, LEFT, at, 0.3), Some(PointerGesture::Click { chord: LEFT, double: true }));
+    }
+
+    #[test]
+    fn test_double_clicks_need_the_same_chord_in_the_same_place() {
+        let mut tracker = GestureTracker::default();
+        let at = Vec2::new(50.0, 50.0);
+        click(&mut tracker, LEFT, at, 0.0);
+        assert_eq!(click(&mut tracker, RIGHT, at, 0.1), Some(PointerGesture::Click { chord: RIGHT, double: false }));
+        assert_eq!(click(&mut tracker, RIGHT, at + Vec2::X * 20.0, 0.2), Some(PointerGesture::Click { chord: RIGHT, double: false }));
+    }
+
+    #[test]
+    fn test_buttons_pressed_during_a_gesture_join_its_chord() {
+        let mut tracker = GestureTracker::default();
+        let at = Vec2::new(50.0, 50.0);
+        let both = LEFT.with_buttons_of(&RIGHT);
+        tracker.update(LEFT, Some(at), 0.0, true);
+        tracker.update(both, Some(at), 0.1, true);
+        // Letting go of one button doesn't end the gesture, or drop the button from it.
+        assert_eq!(tracker.update(RIGHT, Some(at), 0.2, true), None);
+        assert_eq!(tracker.update(NONE, Some(at), 0.3, true), Some(PointerGesture::Click { chord: both, double: false }));
+    }
+
+    #[test]
+    fn test_gestures_only_start_when_allowed() {
+        let mut tracker = GestureTracker::default();
+        let at = Vec2::new(50.0, 50.0);
+        tracker.update(LEFT, Some(at), 0.0, false);
+        assert_eq!(tracker.update(NONE, Some(at), 0.1, true), None);
+    }
 }
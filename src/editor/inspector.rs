@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::{Response, Ui};
+use crate::editor::editable::EditorActions;
+use crate::get;
+use crate::tool::history::{Change, Edit, EditHistory};
+use crate::tool::room::Room;
+use crate::tool::selection::{EditorSelectable, Locked, SelectionState};
+
+/// Locked objects can't be edited here, just like they can't be moved by tools.
+pub type Inspectables<'w, 's> = Query<'w, 's, (&'static mut Transform, &'static EditorSelectable, Option<&'static mut Room>), Without<Locked>>;
+
+/// A copy of the inspected entity's editable state.
+/// The inspector tab edits this copy and `Inspector::apply` writes it back afterwards,
+/// so the tab viewer never has to hold on to a query.
+pub struct InspectedEntity {
+    pub entity: Entity,
+    pub name: String,
+    pub transform: Transform,
+    /// Euler angles in degrees. They're kept from frame to frame while the rotation stays the same,
+    /// so the fields don't jump to an equivalent set of angles while dragging.
+    pub rotation: Vec3,
+    pub room: Option<(Vec3, Vec3)>,
+}
+
+/// The state of an entity from before the inspector started changing it.
+struct PendingEdit {
+    entity: Entity,
+    transform: Transform,
+    room: Option<(Vec3, Vec3)>,
+}
+
+#[derive(Resource, Default)]
+pub struct Inspector {
+    pending: Option<PendingEdit>,
+    /// Set while one of the fields is being dragged or typed into.
+    interacting: bool,
+    /// The Euler angles last shown, and the rotation they were for.
+    rotation: Option<(Entity, Quat, Vec3)>,
+}
+
+impl Inspector {
+    pub fn gather(&self, selection: &SelectionState, inspectables: &Inspectables) -> Option<InspectedEntity> {
+        if selection.selected.len() != 1 {
+            return None;
+        }
+        let entity = *selection.selected.iter().next()?;
+        let (transform, select, room) = inspectables.get(entity).ok()?;
+        Some(InspectedEntity {
+            entity,
+            name: select.id.clone(),
+            transform: *transform,
+            rotation: self.euler_degrees(entity, transform.rotation),
+            room: room.map(|room| room.bounds()),
+        })
+    }
+
+    /// The angles shown last time if the rotation hasn't changed since, so they don't jump to an equivalent set.
+    fn euler_degrees(&self, entity: Entity, rotation: Quat) -> Vec3 {
+        match self.rotation {
+            Some((shown_entity, shown_rotation, degrees)) if shown_entity == entity && shown_rotation == rotation => degrees,
+            _ => {
+                let (x, y, z) = rotation.to_euler(EulerRot::XYZ);
+                Vec3::new(x.to_degrees(), y.to_degrees(), z.to_degrees())
+            }
+        }
+    }
+
+    pub fn ui(
+        ui: &mut Ui,
+        inspector: &mut Self,
+        inspected: &mut Option<InspectedEntity>,
+        selected_count: usize,
+        actions: &mut EditorActions,
+    ) {
+        let Some(inspected) = inspected else {
+            if selected_count > 1 {
+                ui.label(get!("inspector.multiple", "count", selected_count));
+            } else if !EditorActions::inspector_ui(ui, actions) {
+                ui.label(get!("inspector.nothing"));
+            }
+            return;
+        };
+
+        ui.heading(&inspected.name);
+        egui::Grid::new("inspector_transform").num_columns(4).show(ui, |ui| {
+            ui.label(get!("inspector.position"));
+            inspector.track(drag_vec3(ui, &mut inspected.transform.translation, 0.01));
+            ui.end_row();
+
+            ui.label(get!("inspector.rotation"));
+            if inspector.track(drag_vec3(ui, &mut inspected.rotation, 0.5)) {
+                let radians = inspected.rotation * std::f32::consts::PI / 180.0;
+                inspected.transform.rotation = Quat::from_euler(EulerRot::XYZ, radians.x, radians.y, radians.z);
+            }
+            ui.end_row();
+
+            ui.label(get!("inspector.scale"));
+            inspector.track(drag_vec3(ui, &mut inspected.transform.scale, 0.01));
+            ui.end_row();
+        });
+
+        if let Some((min, max)) = &mut inspected.room {
+            ui.separator();
+            ui.heading(get!("inspector.room"));
+            egui::Grid::new("inspector_room").num_columns(4).show(ui, |ui| {
+                ui.label(get!("inspector.min"));
+                inspector.track(drag_vec3(ui, min, 0.01));
+                ui.end_row();
+
+                ui.label(get!("inspector.max"));
+                inspector.track(drag_vec3(ui, max, 0.01));
+                ui.end_row();
+            });
+        }
+    }
+
+    /// Returns true if any of the fields changed.
+    fn track(&mut self, responses: [Response; 3]) -> bool {
+        let mut changed = false;
+        for response in responses {
+            self.interacting |= response.dragged() || response.has_focus();
+            changed |= response.changed();
+        }
+        changed
+    }
+
+    /// Writes the inspected copy back to the world.
+    /// Once the user lets go of a field, everything changed since they grabbed it becomes one undoable edit.
+    pub fn apply(
+        &mut self,
+        inspected: Option<InspectedEntity>,
+        inspectables: &mut Inspectables,
+        history: &mut EditHistory,
+    ) {
+        self.rotation = inspected.as_ref().map(|inspected| (inspected.entity, inspected.transform.rotation, inspected.rotation));
+        if let Some(inspected) = inspected
+            && let Ok((mut transform, _, mut room)) = inspectables.get_mut(inspected.entity) {
+            let room_bounds = room.as_ref().map(|room| room.bounds());
+            if *transform != inspected.transform || room_bounds != inspected.room {
+                if self.pending.is_none() {
+                    self.pending = Some(PendingEdit {
+                        entity: inspected.entity,
+                        transform: *transform,
+                        room: room_bounds,
+                    });
+                }
+                *transform = inspected.transform;
+                if let (Some(room), Some((min, max))) = (room.as_mut(), inspected.room) {
+                    room.set_bounds(min, max);
+                }
+            }
+        }
+
+        if !self.interacting && let Some(pending) = self.pending.take() {
+            if let Ok((transform, _, room)) = inspectables.get(pending.entity) {
+                let mut changes = Vec::new();
+                if *transform != pending.transform {
+                    changes.push(Change::Transform {
+                        entity: pending.entity,
+                        before: pending.transform,
+                        after: *transform,
+                    });
+                }
+                if let (Some(room), Some(before)) = (room, pending.room) && room.bounds() != before {
+                    changes.push(Change::Room {
+                        entity: pending.entity,
+                        before,
+                        after: room.bounds(),
+                    });
+                }
+                history.record(Edit {
+                    label: get!("inspector.title"),
+                    changes,
+                });
+            }
+        }
+        self.interacting = false;
+    }
+}
+
+/// Three drag fields in a row, one per axis.
+pub fn drag_vec3(ui: &mut Ui, value: &mut Vec3, speed: f64) -> [Response; 3] {
+    [
+        ui.add(egui::DragValue::new(&mut value.x).speed(speed).prefix("x: ")),
+        ui.add(egui::DragValue::new(&mut value.y).speed(speed).prefix("y: ")),
+        ui.add(egui::DragValue::new(&mut value.z).speed(speed).prefix("z: ")),
+    ]
+}
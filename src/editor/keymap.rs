@@ -0,0 +1,752 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Display;
+use std::str::FromStr;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContextPass, EguiContexts};
+use bevy_egui::egui::{Color32, RichText};
+use strum::IntoEnumIterator;
+use strum_macros::{EnumIter, EnumString};
+use crate::common::config;
+use crate::editor::palette::{Operation, OperationsExt};
+use crate::get;
+
+pub const KEYMAP_FILE: &str = "keymap.toml";
+/// How long to wait for the next step of a chord before giving up on it.
+const CHORD_TIMEOUT: f32 = 1.0;
+
+pub struct KeymapPlugin;
+
+impl Plugin for KeymapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Keymap>()
+            .init_resource::<ActionState>()
+            .init_resource::<KeymapScreen>()
+            .add_systems(Startup, Keymap::load)
+            .add_systems(PreUpdate, ActionState::update.after(InputSystem))
+            .add_systems(EguiContextPass, KeymapScreen::window)
+            .add_operation(Operation::new("palette.operations.keymap", |world| {
+                world.resource_mut::<KeymapScreen>().open = true;
+            }))
+        ;
+    }
+}
+
+/// Everything the editor lets you bind to the keyboard.
+#[derive(EnumIter, EnumString, strum_macros::Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[strum(serialize_all = "snake_case")]
+pub enum InputAction {
+    CameraForward,
+    CameraBackward,
+    CameraLeft,
+    CameraRight,
+    CameraUp,
+    CameraDown,
+    Modify,
+    Toggle,
+    Confirm,
+    Cancel,
+    Undo,
+    Redo,
+    ToolSelect,
+    ToolMove,
+    ToolRoom,
+    ToolMeasure,
+    TogglePerf,
+    CommandPalette,
+    MaximizeViewport,
+    FrameSelection,
+    Orbit,
+    Playtest,
+    Jump,
+    Bookmark1,
+    Bookmark2,
+    Bookmark3,
+    Bookmark4,
+    Bookmark5,
+    Bookmark6,
+    Bookmark7,
+    Bookmark8,
+    Bookmark9,
+    SetBookmark1,
+    SetBookmark2,
+    SetBookmark3,
+    SetBookmark4,
+    SetBookmark5,
+    SetBookmark6,
+    SetBookmark7,
+    SetBookmark8,
+    SetBookmark9,
+}
+
+impl InputAction {
+    pub fn name(&self) -> String {
+        get!(format!("keymap.actions.{}", self))
+    }
+
+    /// Held actions are active for as long as their keys are down, whatever other modifiers are held.
+    /// The rest only match their exact modifiers, so Ctrl+Z doesn't also fire on Ctrl+Shift+Z.
+    fn is_held(&self) -> bool {
+        matches!(self,
+            Self::CameraForward | Self::CameraBackward | Self::CameraLeft | Self::CameraRight
+            | Self::CameraUp | Self::CameraDown | Self::Modify | Self::Toggle | Self::Orbit | Self::Jump
+        )
+    }
+
+    /// Actions that still fire while a text field has the keyboard, so the palette can be closed from its own search box.
+    fn works_while_typing(&self) -> bool {
+        matches!(self, Self::CommandPalette)
+    }
+
+    fn default_bindings(&self) -> Vec<Binding> {
+        let bindings: &[&str] = match self {
+            Self::CameraForward => &["KeyW"],
+            Self::CameraBackward => &["KeyS"],
+            Self::CameraLeft => &["KeyA"],
+            Self::CameraRight => &["KeyD"],
+            Self::CameraUp => &["KeyQ"],
+            Self::CameraDown => &["KeyE"],
+            Self::Modify => &["Shift"],
+            Self::Toggle => &["Ctrl"],
+            Self::Confirm => &["Enter", "NumpadEnter"],
+            Self::Cancel => &["Escape"],
+            Self::Undo => &["Ctrl+KeyZ"],
+            Self::Redo => &["Ctrl+Shift+KeyZ", "Ctrl+KeyY"],
+            Self::ToolSelect => &["Digit1"],
+            Self::ToolMove => &["Digit2"],
+            Self::ToolRoom => &["Digit3"],
+            Self::ToolMeasure => &["Digit4"],
+            Self::TogglePerf => &["F3"],
+            Self::CommandPalette => &["Ctrl+Shift+KeyP"],
+            Self::MaximizeViewport => &["Ctrl+Space"],
+            Self::FrameSelection => &["KeyF"],
+            Self::Orbit => &["Alt"],
+            Self::Playtest => &["F5"],
+            Self::Jump => &["Space"],
+            Self::Bookmark1 => &["Alt+Digit1"],
+            Self::Bookmark2 => &["Alt+Digit2"],
+            Self::Bookmark3 => &["Alt+Digit3"],
+            Self::Bookmark4 => &["Alt+Digit4"],
+            Self::Bookmark5 => &["Alt+Digit5"],
+            Self::Bookmark6 => &["Alt+Digit6"],
+            Self::Bookmark7 => &["Alt+Digit7"],
+            Self::Bookmark8 => &["Alt+Digit8"],
+            Self::Bookmark9 => &["Alt+Digit9"],
+            Self::SetBookmark1 => &["Ctrl+Alt+Digit1"],
+            Self::SetBookmark2 => &["Ctrl+Alt+Digit2"],
+            Self::SetBookmark3 => &["Ctrl+Alt+Digit3"],
+            Self::SetBookmark4 => &["Ctrl+Alt+Digit4"],
+            Self::SetBookmark5 => &["Ctrl+Alt+Digit5"],
+            Self::SetBookmark6 => &["Ctrl+Alt+Digit6"],
+            Self::SetBookmark7 => &["Ctrl+Alt+Digit7"],
+            Self::SetBookmark8 => &["Ctrl+Alt+Digit8"],
+            Self::SetBookmark9 => &["Ctrl+Alt+Digit9"],
+        };
+        bindings.iter().map(|binding| binding.parse().unwrap()).collect()
+    }
+}
+
+/// Keys are bound by where they are, not what's printed on them, so they're named after their place on a US QWERTY
+/// keyboard. `KeyW` is the key above `KeyS` whatever the layout, which is Z on AZERTY.
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::KeyA, "KeyA"), (KeyCode::KeyB, "KeyB"), (KeyCode::KeyC, "KeyC"), (KeyCode::KeyD, "KeyD"),
+    (KeyCode::KeyE, "KeyE"), (KeyCode::KeyF, "KeyF"), (KeyCode::KeyG, "KeyG"), (KeyCode::KeyH, "KeyH"),
+    (KeyCode::KeyI, "KeyI"), (KeyCode::KeyJ, "KeyJ"), (KeyCode::KeyK, "KeyK"), (KeyCode::KeyL, "KeyL"),
+    (KeyCode::KeyM, "KeyM"), (KeyCode::KeyN, "KeyN"), (KeyCode::KeyO, "KeyO"), (KeyCode::KeyP, "KeyP"),
+    (KeyCode::KeyQ, "KeyQ"), (KeyCode::KeyR, "KeyR"), (KeyCode::KeyS, "KeyS"), (KeyCode::KeyT, "KeyT"),
+    (KeyCode::KeyU, "KeyU"), (KeyCode::KeyV, "KeyV"), (KeyCode::KeyW, "KeyW"), (KeyCode::KeyX, "KeyX"),
+    (KeyCode::KeyY, "KeyY"), (KeyCode::KeyZ, "KeyZ"),
+    (KeyCode::Digit0, "Digit0"), (KeyCode::Digit1, "Digit1"), (KeyCode::Digit2, "Digit2"), (KeyCode::Digit3, "Digit3"),
+    (KeyCode::Digit4, "Digit4"), (KeyCode::Digit5, "Digit5"), (KeyCode::Digit6, "Digit6"), (KeyCode::Digit7, "Digit7"),
+    (KeyCode::Digit8, "Digit8"), (KeyCode::Digit9, "Digit9"),
+    (KeyCode::F1, "F1"), (KeyCode::F2, "F2"), (KeyCode::F3, "F3"), (KeyCode::F4, "F4"),
+    (KeyCode::F5, "F5"), (KeyCode::F6, "F6"), (KeyCode::F7, "F7"), (KeyCode::F8, "F8"),
+    (KeyCode::F9, "F9"), (KeyCode::F10, "F10"), (KeyCode::F11, "F11"), (KeyCode::F12, "F12"),
+    (KeyCode::ArrowUp, "ArrowUp"), (KeyCode::ArrowDown, "ArrowDown"), (KeyCode::ArrowLeft, "ArrowLeft"), (KeyCode::ArrowRight, "ArrowRight"),
+    (KeyCode::Enter, "Enter"), (KeyCode::NumpadEnter, "NumpadEnter"), (KeyCode::Escape, "Escape"),
+    (KeyCode::Space, "Space"), (KeyCode::Tab, "Tab"), (KeyCode::Backspace, "Backspace"),
+    (KeyCode::Delete, "Delete"), (KeyCode::Insert, "Insert"), (KeyCode::Home, "Home"), (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"), (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Minus, "Minus"), (KeyCode::Equal, "Equal"), (KeyCode::BracketLeft, "BracketLeft"),
+    (KeyCode::BracketRight, "BracketRight"), (KeyCode::Semicolon, "Semicolon"), (KeyCode::Quote, "Quote"),
+    (KeyCode::Comma, "Comma"), (KeyCode::Period, "Period"), (KeyCode::Slash, "Slash"),
+    (KeyCode::Backslash, "Backslash"), (KeyCode::Backquote, "Backquote"),
+    (KeyCode::Numpad0, "Numpad0"), (KeyCode::Numpad1, "Numpad1"), (KeyCode::Numpad2, "Numpad2"),
+    (KeyCode::Numpad3, "Numpad3"), (KeyCode::Numpad4, "Numpad4"), (KeyCode::Numpad5, "Numpad5"),
+    (KeyCode::Numpad6, "Numpad6"), (KeyCode::Numpad7, "Numpad7"), (KeyCode::Numpad8, "Numpad8"),
+    (KeyCode::Numpad9, "Numpad9"), (KeyCode::NumpadAdd, "NumpadAdd"), (KeyCode::NumpadSubtract, "NumpadSubtract"),
+];
+
+const MODIFIER_KEYS: [KeyCode; 6] = [
+    KeyCode::ControlLeft, KeyCode::ControlRight,
+    KeyCode::ShiftLeft, KeyCode::ShiftRight,
+    KeyCode::AltLeft, KeyCode::AltRight,
+];
+
+fn key_name(key: KeyCode) -> Option<&'static str> {
+    KEY_NAMES.iter().find(|(code, _)| *code == key).map(|(_, name)| *name)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub fn held(keys: &ButtonInput<KeyCode>) -> Self {
+        Self {
+            ctrl: keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]),
+            shift: keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+            alt: keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.ctrl && !self.shift && !self.alt
+    }
+
+    /// Whether everything these modifiers need is in `held`.
+    fn within(&self, held: &Modifiers) -> bool {
+        (!self.ctrl || held.ctrl) && (!self.shift || held.shift) && (!self.alt || held.alt)
+    }
+}
+
+/// One step of a binding: a key with the modifiers that must be held alongside it.
+/// A combo without a key is just the modifiers, like "Shift".
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct KeyCombo {
+    pub modifiers: Modifiers,
+    pub key: Option<KeyCode>,
+}
+
+impl KeyCombo {
+    fn matches(&self, keys: &ButtonInput<KeyCode>, held: &Modifiers, exact: bool) -> bool {
+        let modifiers = if exact && self.key.is_some() {
+            self.modifiers == *held
+        } else {
+            self.modifiers.within(held)
+        };
+        modifiers && self.key.is_none_or(|key| keys.pressed(key))
+    }
+}
+
+impl Display for KeyCombo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if self.modifiers.ctrl {
+            parts.push("Ctrl");
+        }
+        if self.modifiers.shift {
+            parts.push("Shift");
+        }
+        if self.modifiers.alt {
+            parts.push("Alt");
+        }
+        if let Some(key) = self.key {
+            parts.push(key_name(key).unwrap_or("?"));
+        }
+        write!(f, "{}", parts.join("+"))
+    }
+}
+
+impl FromStr for KeyCombo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut combo = KeyCombo { modifiers: Modifiers::default(), key: None };
+        for part in s.split('+').map(str::trim) {
+            if combo.key.is_some() {
+                return Err(format!("\"{}\" has something after its key.", s));
+            }
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" => combo.modifiers.ctrl = true,
+                "shift" => combo.modifiers.shift = true,
+                "alt" => combo.modifiers.alt = true,
+                _ => {
+                    let key = KEY_NAMES.iter()
+                        .find(|(_, name)| name.eq_ignore_ascii_case(part))
+                        .ok_or_else(|| format!("\"{}\" is not a key. Keys are named by their place on a US keyboard, like KeyW or Digit1.", part))?;
+                    combo.key = Some(key.0);
+                }
+            }
+        }
+        if combo.key.is_none() && combo.modifiers.is_empty() {
+            return Err(format!("\"{}\" is not a key.", s));
+        }
+        Ok(combo)
+    }
+}
+
+/// A sequence of combos pressed one after another. Most bindings have one step; more than one makes a chord,
+/// written with spaces between the steps, like "Ctrl+K Ctrl+S".
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Binding(pub Vec<KeyCombo>);
+
+impl Binding {
+    fn is_chord(&self) -> bool {
+        self.0.len() > 1
+    }
+
+    fn starts_with(&self, other: &Binding) -> bool {
+        self.0.starts_with(&other.0)
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let steps: Vec<String> = self.0.iter().map(KeyCombo::to_string).collect();
+        write!(f, "{}", steps.join(" "))
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s.split_whitespace().map(str::parse).collect::<Result<Vec<KeyCombo>, _>>()?;
+        if steps.is_empty() {
+            return Err("Empty binding.".to_owned());
+        }
+        if steps.len() > 1 && steps.iter().any(|step| step.key.is_none()) {
+            return Err(format!("Every step of the chord \"{}\" needs a key.", s));
+        }
+        Ok(Self(steps))
+    }
+}
+
+/// Two actions that can't both be triggered by `binding`, because one of them is bound to it
+/// and the other to it or to a chord that starts with it.
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    pub first: InputAction,
+    pub second: InputAction,
+    pub binding: Binding,
+}
+
+/// Which keys trigger which actions. Defaults come from the code; a keymap file in the config directory overrides
+/// them one action at a time.
+#[derive(Resource, Clone, PartialEq, Debug)]
+pub struct Keymap {
+    bindings: BTreeMap<InputAction, Vec<Binding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: InputAction::iter().map(|action| (action, action.default_bindings())).collect(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn bindings(&self, action: InputAction) -> &[Binding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn bindings_mut(&mut self, action: InputAction) -> &mut Vec<Binding> {
+        self.bindings.entry(action).or_default()
+    }
+
+    /// The bindings for an action, for showing in tooltips.
+    pub fn describe(&self, action: InputAction) -> String {
+        let bindings = self.bindings(action);
+        if bindings.is_empty() {
+            get!("keymap.unbound")
+        } else {
+            bindings.iter().map(Binding::to_string).collect::<Vec<_>>().join(", ")
+        }
+    }
+
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let all: Vec<(InputAction, &Binding)> = self.bindings.iter()
+            .flat_map(|(action, bindings)| bindings.iter().map(move |binding| (*action, binding)))
+            .collect();
+        let mut conflicts = Vec::new();
+        for (i, (first, first_binding)) in all.iter().enumerate() {
+            for (second, second_binding) in all.iter().skip(i + 1) {
+                if first == second {
+                    continue;
+                }
+                if first_binding.starts_with(second_binding) {
+                    conflicts.push(Conflict { first: *first, second: *second, binding: (*second_binding).clone() });
+                } else if second_binding.starts_with(first_binding) {
+                    conflicts.push(Conflict { first: *first, second: *second, binding: (*first_binding).clone() });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Reads a keymap file. Actions the file doesn't mention keep their default bindings.
+    pub fn from_toml(contents: &str) -> Result<Self, String> {
+        #[derive(serde::Deserialize)]
+        struct KeymapFile {
+            #[serde(default)]
+            bindings: BTreeMap<String, Vec<String>>,
+        }
+
+        let file: KeymapFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+        let mut keymap = Self::default();
+        for (name, bindings) in file.bindings {
+            let action = InputAction::from_str(&name).map_err(|_| format!("\"{}\" is not an action.", name))?;
+            let bindings = bindings.iter()
+                .map(|binding| binding.parse())
+                .collect::<Result<Vec<Binding>, _>>()
+                .map_err(|e| format!("{}: {}", name, e))?;
+            keymap.bindings.insert(action, bindings);
+        }
+        Ok(keymap)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut contents = String::from("[bindings]\n");
+        for (action, bindings) in &self.bindings {
+            let bindings: Vec<String> = bindings.iter().map(|binding| format!("\"{}\"", binding)).collect();
+            contents.push_str(&format!("{} = [{}]\n", action, bindings.join(", ")));
+        }
+        contents
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        config::write_config(KEYMAP_FILE, &self.to_toml())
+    }
+
+    fn load(mut keymap: ResMut<Self>) {
+        let contents = match config::read_config(KEYMAP_FILE) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("{}", e);
+                return;
+            }
+        };
+        match Self::from_toml(&contents) {
+            Ok(loaded) => *keymap = loaded,
+            Err(e) => warn!("Ignoring keymap \"{}\": {}", KEYMAP_FILE, e),
+        }
+        for conflict in keymap.conflicts() {
+            warn!("{}", conflict.describe());
+        }
+    }
+}
+
+impl Conflict {
+    pub fn describe(&self) -> String {
+        get!("keymap.conflict", "binding", self.binding, "first", self.first.name(), "second", self.second.name())
+    }
+}
+
+/// Which actions are active this frame, worked out from the keymap. Read this instead of the raw keyboard.
+#[derive(Resource, Default)]
+pub struct ActionState {
+    pressed: HashSet<InputAction>,
+    just_pressed: HashSet<InputAction>,
+    just_released: HashSet<InputAction>,
+    chord: Vec<KeyCombo>,
+    chord_started: f32,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: InputAction) -> bool {
+        self.just_released.contains(&action)
+    }
+
+    pub fn update(
+        mut state: ResMut<Self>,
+        keymap: Res<Keymap>,
+        keys: Res<ButtonInput<KeyCode>>,
+        screen: Res<KeymapScreen>,
+        time: Res<Time>,
+        mut contexts: EguiContexts,
+    ) {
+        // Keys pressed while rebinding shouldn't do anything else.
+        if screen.capturing.is_some() {
+            *state = Self::default();
+            return;
+        }
+
+        // Nor should typing into a text field.
+        let typing = contexts.try_ctx_mut().is_some_and(|ctx| ctx.wants_keyboard_input());
+        let held = Modifiers::held(&keys);
+        let mut pressed = HashSet::new();
+        for (action, bindings) in keymap.bindings.iter().filter(|(action, _)| !typing || action.works_while_typing()) {
+            let exact = !action.is_held();
+            if bindings.iter().any(|binding| !binding.is_chord() && binding.0[0].matches(&keys, &held, exact)) {
+                pressed.insert(*action);
+            }
+        }
+        state.just_pressed = pressed.difference(&state.pressed).copied().collect();
+        state.just_released = state.pressed.difference(&pressed).copied().collect();
+        state.pressed = pressed;
+        if typing {
+            state.chord.clear();
+            return;
+        }
+
+        let now = time.elapsed_secs();
+        if now - state.chord_started > CHORD_TIMEOUT {
+            state.chord.clear();
+        }
+        for key in keys.get_just_pressed().filter(|key| !MODIFIER_KEYS.contains(key)) {
+            let combo = KeyCombo { modifiers: held, key: Some(*key) };
+            if state.chord.is_empty() {
+                state.chord_started = now;
+            }
+            state.chord.push(combo);
+            let progress = Binding(state.chord.clone());
+            let chords = keymap.bindings.iter()
+                .flat_map(|(action, bindings)| bindings.iter().map(move |binding| (*action, binding)))
+                .filter(|(_, binding)| binding.is_chord());
+            let mut waiting = false;
+            for (action, binding) in chords {
+                if *binding == progress {
+                    state.just_pressed.insert(action);
+                    state.just_released.insert(action);
+                } else if binding.starts_with(&progress) {
+                    waiting = true;
+                }
+            }
+            if !waiting {
+                state.chord.clear();
+            }
+        }
+    }
+}
+
+/// The rebinding window.
+#[derive(Resource, Default)]
+pub struct KeymapScreen {
+    pub open: bool,
+    /// The action being rebound, and which of its bindings is being replaced (`None` to add one).
+    capturing: Option<(InputAction, Option<usize>)>,
+    captured: Vec<KeyCombo>,
+    /// Modifiers held since the last key, so releasing them on their own can bind just the modifiers.
+    modifiers: Modifiers,
+    status: Option<String>,
+}
+
+impl KeymapScreen {
+    fn capture(&mut self, keys: &ButtonInput<KeyCode>) {
+        let held = Modifiers::held(keys);
+        for key in keys.get_just_pressed().filter(|key| !MODIFIER_KEYS.contains(key)) {
+            self.captured.push(KeyCombo { modifiers: held, key: Some(*key) });
+            self.modifiers = Modifiers::default();
+        }
+        if self.captured.is_empty() && !held.is_empty() {
+            self.modifiers = Modifiers {
+                ctrl: self.modifiers.ctrl || held.ctrl,
+                shift: self.modifiers.shift || held.shift,
+                alt: self.modifiers.alt || held.alt,
+            };
+        } else if self.captured.is_empty() && held.is_empty() && !self.modifiers.is_empty() {
+            self.captured.push(KeyCombo { modifiers: self.modifiers, key: None });
+        }
+    }
+
+    fn finish(&mut self, keymap: &mut Keymap) {
+        if let Some((action, index)) = self.capturing.take()
+            && !self.captured.is_empty() {
+            let binding = Binding(std::mem::take(&mut self.captured));
+            let bindings = keymap.bindings_mut(action);
+            match index {
+                Some(index) if index < bindings.len() => bindings[index] = binding,
+                _ => bindings.push(binding),
+            }
+        }
+        self.captured.clear();
+        self.modifiers = Modifiers::default();
+    }
+
+    fn window(
+        mut contexts: EguiContexts,
+        mut screen: ResMut<Self>,
+        mut keymap: ResMut<Keymap>,
+        keys: Res<ButtonInput<KeyCode>>,
+    ) {
+        if !screen.open {
+            return;
+        }
+        let Some(ctx) = contexts.try_ctx_mut() else {
+            return;
+        };
+        // A binding made of only modifiers is finished as soon as they're released; anything else waits for "Done".
+        let modifiers_only = screen.captured.first().is_some_and(|combo| combo.key.is_none());
+        if screen.capturing.is_some() {
+            screen.capture(&keys);
+        }
+        if modifiers_only {
+            screen.finish(&mut keymap);
+        }
+
+        let conflicts = keymap.conflicts();
+        let mut open = screen.open;
+        egui::Window::new(get!("keymap.title"))
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.small(get!("keymap.positions"));
+                for conflict in &conflicts {
+                    ui.colored_label(Color32::from_rgb(230, 80, 80), conflict.describe());
+                }
+                if !conflicts.is_empty() {
+                    ui.separator();
+                }
+
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("keymap_grid").striped(true).num_columns(3).show(ui, |ui| {
+                        for action in InputAction::iter() {
+                            ui.label(action.name());
+                            ui.horizontal(|ui| {
+                                let mut removed = None;
+                                for (index, binding) in keymap.bindings(action).iter().enumerate() {
+                                    let conflicted = conflicts.iter()
+                                        .any(|conflict| (conflict.first == action || conflict.second == action) && binding.starts_with(&conflict.binding));
+                                    let capturing = screen.capturing == Some((action, Some(index)));
+                                    let mut text = RichText::new(if capturing { get!("keymap.press") } else { binding.to_string() });
+                                    if conflicted {
+                                        text = text.color(Color32::from_rgb(230, 80, 80));
+                                    }
+                                    let button = ui.selectable_label(capturing, text)
+                                        .on_hover_text(get!("keymap.binding_tooltip"));
+                                    if button.clicked() {
+                                        screen.capturing = Some((action, Some(index)));
+                                        screen.captured.clear();
+                                    }
+                                    if button.secondary_clicked() {
+                                        removed = Some(index);
+                                    }
+                                }
+                                if let Some(index) = removed {
+                                    keymap.bindings_mut(action).remove(index);
+                                }
+                                if screen.capturing == Some((action, None)) {
+                                    ui.label(RichText::new(get!("keymap.press")).strong());
+                                } else if ui.small_button("+").on_hover_text(get!("keymap.add")).clicked() {
+                                    screen.capturing = Some((action, None));
+                                    screen.captured.clear();
+                                }
+                            });
+                            if ui.small_button("⟲").on_hover_text(get!("keymap.reset_action")).clicked() {
+                                *keymap.bindings_mut(action) = action.default_bindings();
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                ui.separator();
+                if screen.capturing.is_some() {
+                    let captured = Binding(screen.captured.clone());
+                    ui.horizontal(|ui| {
+                        ui.label(get!("keymap.capturing", "binding", captured));
+                        if ui.button(get!("keymap.done")).clicked() {
+                            screen.finish(&mut keymap);
+                        }
+                        if ui.button(get!("keymap.cancel")).clicked() {
+                            screen.capturing = None;
+                            screen.captured.clear();
+                        }
+                    });
+                } else {
+                    ui.horizontal(|ui| {
+                        if ui.button(get!("keymap.save")).clicked() {
+                            screen.status = Some(match keymap.save() {
+                                Ok(()) => get!("keymap.saved", "file", KEYMAP_FILE),
+                                Err(e) => e,
+                            });
+                        }
+                        if ui.button(get!("keymap.reset_all")).clicked() {
+                            *keymap = Keymap::default();
+                        }
+                    });
+                }
+                if let Some(status) = &screen.status {
+                    ui.weak(status);
+                }
+            });
+        if !open {
+            screen.open = false;
+            screen.capturing = None;
+            screen.captured.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binding_round_trip() {
+        for text in ["KeyW", "Ctrl+Shift+KeyZ", "Shift", "Ctrl+KeyK Ctrl+KeyS", "Alt+F4", "Alt+Digit1"] {
+            let binding: Binding = text.parse().unwrap();
+            assert_eq!(binding.to_string(), text);
+        }
+        assert_eq!("ctrl+keyz".parse::<Binding>().unwrap().to_string(), "Ctrl+KeyZ");
+    }
+
+    #[test]
+    fn test_bad_bindings() {
+        assert!("".parse::<Binding>().is_err());
+        assert!("Ctrl+Nope".parse::<Binding>().is_err());
+        assert!("KeyZ+Ctrl".parse::<Binding>().is_err());
+        assert!("Ctrl+KeyK Shift".parse::<Binding>().is_err());
+        // Printed letters would mean different keys on different layouts.
+        assert!("Ctrl+Z".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn test_default_keymap_has_no_conflicts() {
+        assert_eq!(Keymap::default().conflicts(), vec![]);
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let keymap = Keymap::from_toml("[bindings]\nundo = [\"Ctrl+KeyK\"]\nredo = [\"Ctrl+KeyK Ctrl+KeyR\"]\ntool_move = [\"Digit1\"]").unwrap();
+        let conflicts = keymap.conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts.iter().any(|conflict| conflict.binding.to_string() == "Ctrl+KeyK"));
+        assert!(conflicts.iter().any(|conflict| conflict.binding.to_string() == "Digit1"));
+    }
+
+    #[test]
+    fn test_keymap_file() {
+        let keymap = Keymap::from_toml("[bindings]\ncamera_forward = [\"KeyI\"]\ncamera_left = [\"KeyJ\"]\ncamera_up = [\"KeyU\"]").unwrap();
+        assert_eq!(keymap.describe(InputAction::CameraForward), "KeyI");
+        assert_eq!(keymap.describe(InputAction::CameraBackward), "KeyS");
+        assert_eq!(Keymap::from_toml(&keymap.to_toml()).unwrap(), keymap);
+        assert!(Keymap::from_toml("[bindings]\nfly = [\"KeyF\"]").is_err());
+    }
+
+    #[test]
+    fn test_azerty_flies_with_the_keys_under_wasd() {
+        // On AZERTY the keys under W, A, S and D are labelled Z, Q, S and D, and Bevy reports them by position.
+        let keymap = Keymap::default();
+        let forward = &keymap.bindings(InputAction::CameraForward)[0].0[0];
+        let left = &keymap.bindings(InputAction::CameraLeft)[0].0[0];
+        assert_eq!(keymap.describe(InputAction::CameraForward), "KeyW");
+
+        let mut keys = ButtonInput::<KeyCode>::default();
+        let held = Modifiers::default();
+        // Pressing the key labelled Z on AZERTY.
+        keys.press(KeyCode::KeyW);
+        assert!(forward.matches(&keys, &held, false));
+        assert!(!left.matches(&keys, &held, false));
+        keys.release(KeyCode::KeyW);
+        // Pressing the key labelled Q on AZERTY.
+        keys.press(KeyCode::KeyA);
+        assert!(left.matches(&keys, &held, false));
+        assert!(!forward.matches(&keys, &held, false));
+    }
+}
@@ -0,0 +1,291 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::get;
+use crate::tool::selection::{EditorSelectable, Locked, SelectionState};
+
+/// The layer that's always there. Objects whose layer is removed end up on it.
+pub const DEFAULT_LAYER: u32 = 0;
+/// A tint of white leaves colors as they are.
+const NO_TINT: [f32; 3] = [1.0, 1.0, 1.0];
+
+pub struct LayersPlugin;
+
+impl Plugin for LayersPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<EditorLayers>()
+            .add_systems(Update, (
+                EditorLayers::assign_new,
+                EditorLayers::sync,
+                LayerTint::apply,
+            ).chain())
+        ;
+    }
+}
+
+/// A named group of map objects that can be hidden, locked or tinted together.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct EditorLayer {
+    pub id: u32,
+    pub name: String,
+    pub visible: bool,
+    pub locked: bool,
+    /// Multiplies the colors of everything on the layer, in sRGB.
+    pub tint: [f32; 3],
+}
+
+impl EditorLayer {
+    pub fn new(id: u32, name: String) -> Self {
+        Self {
+            id,
+            name,
+            visible: true,
+            locked: false,
+            tint: NO_TINT,
+        }
+    }
+
+    pub fn tint_color(&self) -> Color {
+        let [red, green, blue] = self.tint;
+        Color::srgb(red, green, blue)
+    }
+}
+
+/// Every layer in the map. They're part of the map, so they're saved in the `.gmp` file.
+/// There's always a default layer, even when a file left it out.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(from = "SavedLayers")]
+pub struct EditorLayers {
+    layers: Vec<EditorLayer>,
+    /// The layer new objects go on.
+    pub active: u32,
+}
+
+/// [`EditorLayers`] as written in a file, before the default layer is put back.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SavedLayers {
+    layers: Vec<EditorLayer>,
+    active: u32,
+}
+
+impl From<SavedLayers> for EditorLayers {
+    fn from(saved: SavedLayers) -> Self {
+        let mut layers = Self { layers: saved.layers, active: saved.active };
+        layers.ensure_default();
+        layers
+    }
+}
+
+impl Default for EditorLayers {
+    fn default() -> Self {
+        Self {
+            layers: vec![EditorLayer::new(DEFAULT_LAYER, get!("layers.default"))],
+            active: DEFAULT_LAYER,
+        }
+    }
+}
+
+/// Which layer an object is on.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OnLayer(pub u32);
+
+/// Set on objects the layer hid, so showing the layer only shows those and not ones hidden by hand.
+#[derive(Component)]
+struct HiddenByLayer;
+
+/// Set on objects the layer locked, so unlocking the layer only unlocks those.
+#[derive(Component)]
+struct LockedByLayer;
+
+impl EditorLayers {
+    pub fn iter(&self) -> impl Iterator<Item = &EditorLayer> {
+        self.layers.iter()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&EditorLayer> {
+        self.layers.iter().find(|layer| layer.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut EditorLayer> {
+        self.layers.iter_mut().find(|layer| layer.id == id)
+    }
+
+    /// The layer an object with this id is really on: layers that don't exist any more count as the default one.
+    pub fn resolve(&self, id: u32) -> &EditorLayer {
+        self.get(id).or_else(|| self.get(DEFAULT_LAYER)).expect("the default layer can't be removed")
+    }
+
+    pub fn add(&mut self, name: String) -> u32 {
+        let id = self.layers.iter().map(|layer| layer.id).max().map_or(DEFAULT_LAYER, |id| id + 1);
+        self.layers.push(EditorLayer::new(id, name));
+        id
+    }
+
+    /// Removes a layer. The default layer can't be removed.
+    pub fn remove(&mut self, id: u32) -> bool {
+        if id == DEFAULT_LAYER {
+            return false;
+        }
+        let before = self.layers.len();
+        self.layers.retain(|layer| layer.id != id);
+        if self.active == id {
+            self.active = DEFAULT_LAYER;
+        }
+        self.layers.len() != before
+    }
+
+    /// Puts the default layer back if a file didn't have it.
+    fn ensure_default(&mut self) {
+        if self.get(DEFAULT_LAYER).is_none() {
+            self.layers.insert(0, EditorLayer::new(DEFAULT_LAYER, get!("layers.default")));
+        }
+        if self.get(self.active).is_none() {
+            self.active = DEFAULT_LAYER;
+        }
+    }
+
+    /// New map objects go on the active layer.
+    fn assign_new(
+        mut commands: Commands,
+        layers: Res<Self>,
+        objects: Query<Entity, (With<EditorSelectable>, Without<OnLayer>)>,
+    ) {
+        for entity in &objects {
+            commands.entity(entity).insert(OnLayer(layers.active));
+        }
+    }
+
+    /// Hides, locks and tints objects to match their layers. Hidden objects can't stay selected.
+    fn sync(
+        mut commands: Commands,
+        layers: Res<Self>,
+        mut selection: ResMut<SelectionState>,
+        mut objects: Query<(Entity, &OnLayer, Option<&mut Visibility>, Has<Locked>, Has<HiddenByLayer>, Has<LockedByLayer>, Option<&LayerTint>)>,
+    ) {
+        for (entity, on_layer, visibility, locked, hidden_by_layer, locked_by_layer, tint) in &mut objects {
+            let layer = layers.resolve(on_layer.0);
+            if layer.id != on_layer.0 {
+                commands.entity(entity).insert(OnLayer(layer.id));
+            }
+
+            if let Some(mut visibility) = visibility {
+                if !layer.visible {
+                    if *visibility != Visibility::Hidden {
+                        *visibility = Visibility::Hidden;
+                        commands.entity(entity).insert(HiddenByLayer);
+                    }
+                    if selection.is_selected(entity) {
+                        selection.selected.remove(&entity);
+                    }
+                } else if hidden_by_layer {
+                    *visibility = Visibility::Inherited;
+                    commands.entity(entity).remove::<HiddenByLayer>();
+                }
+            }
+
+            if layer.locked && !locked {
+                commands.entity(entity).insert((Locked, LockedByLayer));
+            } else if !layer.locked && locked_by_layer {
+                commands.entity(entity).remove::<(Locked, LockedByLayer)>();
+            }
+
+            let color = layer.tint_color();
+            if tint.map(|tint| tint.0) != Some(color) {
+                commands.entity(entity).insert(LayerTint(color));
+            }
+        }
+    }
+}
+
+/// The tint of an object's layer, kept on the object so drawing code doesn't need the layers.
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct LayerTint(pub Color);
+
+impl LayerTint {
+    /// Whether the layer changes colors at all.
+    pub fn is_tinted(&self) -> bool {
+        self.0 != Color::WHITE
+    }
+
+    fn tinted(&self, color: Color) -> Color {
+        let (color, tint) = (color.to_linear(), self.0.to_linear());
+        Color::linear_rgba(color.red * tint.red, color.green * tint.green, color.blue * tint.blue, color.alpha)
+    }
+
+    /// Tints the materials of objects and their children, such as the unlit copies for render modes.
+    fn apply(
+        mut commands: Commands,
+        objects: Query<(Entity, &LayerTint, Option<&Children>), Changed<LayerTint>>,
+        surfaces: Query<(&MeshMaterial3d<StandardMaterial>, Option<&Untinted>)>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+    ) {
+        for (entity, tint, children) in &objects {
+            let targets = std::iter::once(entity).chain(children.into_iter().flatten().copied());
+            for target in targets {
+                let Ok((material, untinted)) = surfaces.get(target) else {
+                    continue;
+                };
+                let Some(current) = materials.get(&material.0).map(|material| material.base_color) else {
+                    continue;
+                };
+                let original = match untinted {
+                    Some(untinted) => untinted.0,
+                    None => {
+                        commands.entity(target).insert(Untinted(current));
+                        current
+                    }
+                };
+                let color = tint.tinted(original);
+                if color != current && let Some(material) = materials.get_mut(&material.0) {
+                    material.base_color = color;
+                }
+            }
+        }
+    }
+}
+
+/// A material's color before any layer tinted it.
+#[derive(Component)]
+struct Untinted(Color);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers() -> EditorLayers {
+        EditorLayers {
+            layers: vec![EditorLayer::new(DEFAULT_LAYER, "Default".to_owned())],
+            active: DEFAULT_LAYER,
+        }
+    }
+
+    #[test]
+    fn test_removed_layers_fall_back_to_default() {
+        let mut layers = layers();
+        let roof = layers.add("Roof".to_owned());
+        layers.active = roof;
+        assert_eq!(layers.resolve(roof).name, "Roof");
+
+        assert!(layers.remove(roof));
+        assert_eq!(layers.resolve(roof).id, DEFAULT_LAYER);
+        assert_eq!(layers.active, DEFAULT_LAYER);
+        assert!(!layers.remove(DEFAULT_LAYER));
+    }
+
+    #[test]
+    fn test_files_without_default_layer_get_one() {
+        let mut layers = EditorLayers { layers: vec![EditorLayer::new(3, "Floor".to_owned())], active: 7 };
+        layers.ensure_default();
+        assert!(layers.get(DEFAULT_LAYER).is_some());
+        assert_eq!(layers.active, DEFAULT_LAYER);
+        assert_eq!(layers.add("Roof".to_owned()), 4);
+    }
+
+    #[test]
+    fn test_empty_layer_lists_load_with_default() {
+        let layers: EditorLayers = toml::from_str("layers = []\nactive = 5").unwrap();
+        assert_eq!(layers.resolve(5).id, DEFAULT_LAYER);
+        assert_eq!(layers.active, DEFAULT_LAYER);
+    }
+}
@@ -0,0 +1,250 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::editor::bookmarks::CameraBookmarks;
+use crate::editor::console::{ConsoleCommand, ConsoleCommands, ConsoleCommandsExt};
+use crate::editor::editable::{EditorAction, EditorActions, MAP_EXT};
+use crate::editor::layers::{EditorLayers, OnLayer, DEFAULT_LAYER};
+use crate::editor::palette::{Operation, OperationsExt};
+use crate::get;
+use crate::tool::history::EditHistory;
+use crate::tool::room::Room;
+use crate::tool::selection::{SelectionMode, SelectionState};
+
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<MapDocument>()
+            .add_console_command("map", ConsoleCommand::new("console.commands.map", Self::map_command)
+                .with_completions(|_| vec!["save".to_owned(), "load".to_owned()]))
+            .add_operation(Operation::command("palette.operations.save_map", "map save"))
+        ;
+    }
+}
+
+impl MapPlugin {
+    fn map_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+        let usage = || world.resource::<ConsoleCommands>().get("map").map(ConsoleCommand::usage).unwrap_or_default();
+        match args {
+            ["save"] => {
+                let path = world.resource::<MapDocument>().path.clone().ok_or_else(|| get!("console.map_no_path"))?;
+                Self::save(world, path)
+            }
+            ["save", path] => Self::save(world, with_extension(path)),
+            ["load", path] => Self::load(world, with_extension(path)),
+            _ => Err(usage()),
+        }
+    }
+
+    fn save(world: &mut World, path: PathBuf) -> Result<String, String> {
+        let contents = MapFile::gather(world).to_json()?;
+        fs::write(&path, contents).map_err(|e| get!("console.map_write_failed", "path", path.display(), "error", e))?;
+        let message = get!("console.map_saved", "path", path.display());
+        world.resource_mut::<MapDocument>().path = Some(path);
+        Ok(message)
+    }
+
+    fn load(world: &mut World, path: PathBuf) -> Result<String, String> {
+        let contents = fs::read_to_string(&path).map_err(|e| get!("console.map_read_failed", "path", path.display(), "error", e))?;
+        let map = MapFile::from_json(&contents)
+            .map_err(|e| get!("console.map_read_failed", "path", path.display(), "error", e))?;
+        map.apply(world);
+        let message = get!("console.map_loaded", "path", path.display());
+        world.resource_mut::<MapDocument>().path = Some(path);
+        Ok(message)
+    }
+}
+
+/// Adds the map extension to paths that don't have one.
+fn with_extension(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.extension().is_some() {
+        path.to_path_buf()
+    } else {
+        path.with_extension(&*MAP_EXT)
+    }
+}
+
+/// The map being edited: where it was last saved to or loaded from.
+#[derive(Resource, Default)]
+pub struct MapDocument {
+    pub path: Option<PathBuf>,
+}
+
+/// Everything in a `.gmp` file. Sections an older file doesn't have load empty.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct MapFile {
+    pub rooms: Vec<SavedRoom>,
+    pub bookmarks: CameraBookmarks,
+    pub layers: EditorLayers,
+    /// Every editor action, in the order they were taken.
+    pub actions: Vec<EditorAction>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SavedRoom {
+    pub min: Vec3,
+    pub max: Vec3,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+    #[serde(default)]
+    pub layer: u32,
+}
+
+impl MapFile {
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    pub fn from_json(contents: &str) -> Result<Self, String> {
+        serde_json::from_str(contents).map_err(|e| e.to_string())
+    }
+
+    /// Collects the map from the world.
+    pub fn gather(world: &mut World) -> Self {
+        let rooms = world
+            .query::<(&Room, &Transform, Option<&OnLayer>)>()
+            .iter(world)
+            .map(|(room, transform, on_layer)| {
+                let (min, max) = room.bounds();
+                SavedRoom {
+                    min,
+                    max,
+                    translation: transform.translation,
+                    rotation: transform.rotation,
+                    scale: transform.scale,
+                    layer: on_layer.map_or(DEFAULT_LAYER, |on_layer| on_layer.0),
+                }
+            })
+            .collect();
+        Self {
+            rooms,
+            bookmarks: world.resource::<CameraBookmarks>().clone(),
+            layers: world.resource::<EditorLayers>().clone(),
+            actions: world.resource::<EditorActions>().iter().cloned().collect(),
+        }
+    }
+
+    /// Replaces the map in the world with this one. Selection and undo history refer to the old map, so they go too.
+    pub fn apply(self, world: &mut World) {
+        let old: Vec<Entity> = world.query_filtered::<Entity, With<Room>>().iter(world).collect();
+        for entity in old {
+            world.despawn(entity);
+        }
+
+        let mut state: SystemState<(Commands, ResMut<Assets<Mesh>>, ResMut<Assets<StandardMaterial>>)> = SystemState::new(world);
+        for saved in self.rooms {
+            let (mut commands, meshes, materials) = state.get_mut(world);
+            let entity = Room::new(saved.min, saved.max).spawn(&mut commands, meshes, materials);
+            commands.entity(entity).insert(Transform {
+                translation: saved.translation,
+                rotation: saved.rotation,
+                scale: saved.scale,
+            }).insert(OnLayer(saved.layer));
+        }
+        state.apply(world);
+
+        world.resource_mut::<SelectionState>().apply(SelectionMode::Replace, []);
+        world.resource_mut::<EditHistory>().clear();
+        *world.resource_mut::<CameraBookmarks>() = self.bookmarks;
+        *world.resource_mut::<EditorLayers>() = self.layers;
+        world.resource_mut::<EditorActions>().replace(self.actions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::bookmarks::{CameraBookmark, ViewBookmark};
+    use crate::editor::editable::EditorActionId;
+    use crate::editor::global_point::GlobalPoint;
+    use crate::editor::multicam::CameraAxis;
+
+    fn map() -> MapFile {
+        let mut bookmarks = CameraBookmarks::default();
+        bookmarks.set(3, CameraBookmark {
+            name: "Atrium".to_owned(),
+            views: vec![ViewBookmark {
+                camera: 1,
+                axis: CameraAxis::Y,
+                translation: Vec3::new(0.0, 10.0, 0.0),
+                rotation: Quat::IDENTITY,
+                scale: Some(0.02),
+            }],
+        });
+        let mut layers = EditorLayers::default();
+        let detail = layers.add("Detail".to_owned());
+        layers.get_mut(detail).unwrap().locked = true;
+        let mut actions = EditorActions::default();
+        actions.take_action(Box::new(GlobalPoint::new(3.0, 4.0, 5.0)));
+        MapFile {
+            rooms: vec![SavedRoom {
+                min: Vec3::new(-1.0, 0.0, -1.0),
+                max: Vec3::new(1.0, 2.0, 1.0),
+                translation: Vec3::X,
+                rotation: Quat::from_rotation_y(0.5),
+                scale: Vec3::ONE,
+                layer: 2,
+            }],
+            bookmarks,
+            layers,
+            actions: actions.iter().skip(1).cloned().collect(),
+        }
+    }
+
+    fn points(actions: &[EditorAction]) -> Vec<(EditorActionId, Vec3)> {
+        actions.iter().map(|action| (action.id(), action.get_point("").ok().unwrap())).collect()
+    }
+
+    #[test]
+    fn test_map_round_trips() {
+        let map = map();
+        let loaded = MapFile::from_json(&map.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.rooms, map.rooms);
+        assert_eq!(loaded.bookmarks, map.bookmarks);
+        assert_eq!(loaded.layers, map.layers);
+        assert_eq!(points(&loaded.actions), points(&map.actions));
+    }
+
+    #[test]
+    fn test_loading_replaces_actions() {
+        let mut world = World::new();
+        world.init_resource::<Assets<Mesh>>();
+        world.init_resource::<Assets<StandardMaterial>>();
+        world.init_resource::<SelectionState>();
+        world.init_resource::<EditHistory>();
+        world.init_resource::<CameraBookmarks>();
+        world.init_resource::<EditorLayers>();
+        world.init_resource::<EditorActions>();
+
+        let map = map();
+        let saved = points(&map.actions);
+        map.apply(&mut world);
+        let actions: Vec<EditorAction> = world.resource::<EditorActions>().iter().cloned().collect();
+        assert_eq!(points(&actions), saved);
+
+        MapFile::from_json("{}").unwrap().apply(&mut world);
+        assert_eq!(world.resource::<EditorActions>().iter().count(), 0);
+    }
+
+    #[test]
+    fn test_missing_sections_load_empty() {
+        let loaded = MapFile::from_json("{}").unwrap();
+        assert!(loaded.rooms.is_empty());
+        assert!(loaded.bookmarks.is_empty());
+        assert_eq!(loaded.layers, EditorLayers::default());
+        assert!(loaded.actions.is_empty());
+    }
+
+    #[test]
+    fn test_paths_get_map_extension() {
+        assert_eq!(with_extension("maps/atrium"), PathBuf::from("maps/atrium.gmp"));
+        assert_eq!(with_extension("maps/atrium.json"), PathBuf::from("maps/atrium.json"));
+    }
+}
@@ -1,5 +1,18 @@
 pub mod multicam;
+pub mod grid;
 pub mod input;
+pub mod keymap;
 pub mod editable;
 pub mod global_point;
 pub mod panels;
+pub mod inspector;
+pub mod outliner;
+pub mod console;
+pub mod problems;
+pub mod palette;
+pub mod preferences;
+pub mod map;
+pub mod bookmarks;
+pub mod status_bar;
+pub mod render_mode;
+pub mod layers;
@@ -7,8 +7,17 @@ use bevy::render::camera::Viewport;
 use bevy::render::view::RenderLayers;
 use bevy::window::{PrimaryWindow, WindowResized};
 use bevy_egui::{egui, EguiContextPass, EguiContexts};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
 use bevy_vector_shapes::prelude::*;
 use crate::common::painter;
+use crate::common::painter::draw_rect;
+use crate::editor::input::CurrentMouseInput;
+use crate::editor::keymap::{ActionState, InputAction};
+use crate::editor::palette::{Operation, OperationsExt};
+use crate::editor::preferences::Preferences;
+use crate::editor::render_mode::RenderMode;
 use crate::tool::selection::EditorSelectable;
 use crate::get;
 
@@ -27,27 +36,183 @@ pub struct MulticamState {
     pub debug_mouse_circle: bool,
     pub draw_ortho_cameras: bool,
     pub draw_perspective_cameras: bool,
+    /// The `Multicam::id` of the camera filling the whole viewport area, if one is maximized.
+    pub maximized: Option<u32>,
 }
 
 #[derive(Component)]
 pub struct Multicam {
     pub name: String,
-    pub screen_pos: UVec2,
+    /// Cameras fill the layout's regions in id order.
     pub id: u32,
     pub axis: CameraAxis,
+    pub render_mode: RenderMode,
 }
 
-#[derive(PartialEq, Clone, Copy, Eq)]
+/// Which way a camera looks. `X` sits out along +X looking back at the origin, `NegX` along -X, and so on.
+/// `None` is the free perspective camera.
+#[derive(EnumIter, Serialize, Deserialize, PartialEq, Clone, Copy, Eq, Debug)]
 pub enum CameraAxis {
     None,
     X,
+    NegX,
     Y,
+    NegY,
     Z,
+    NegZ,
+}
+
+/// How far orthographic cameras sit from what they're looking at.
+const ORTHO_DISTANCE: f32 = 5.0;
+/// Where the free camera sits relative to what it's looking at.
+const FREE_OFFSET: Vec3 = Vec3::new(-2.5, 4.5, 9.0);
+
+impl CameraAxis {
+    pub fn name(&self) -> String {
+        match self {
+            Self::None => get!("viewport.free"),
+            Self::X => get!("viewport.front"),
+            Self::NegX => get!("viewport.back"),
+            Self::Y => get!("viewport.top"),
+            Self::NegY => get!("viewport.bottom"),
+            Self::Z => get!("viewport.right"),
+            Self::NegZ => get!("viewport.left"),
+        }
+    }
+
+    /// The world axis the camera looks along: 0 for X, 1 for Y, 2 for Z.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            Self::None => None,
+            Self::X | Self::NegX => Some(0),
+            Self::Y | Self::NegY => Some(1),
+            Self::Z | Self::NegZ => Some(2),
+        }
+    }
+
+    /// The direction from what the camera looks at to the camera.
+    pub fn direction(&self) -> Option<Vec3> {
+        match self {
+            Self::None => None,
+            Self::X => Some(Vec3::X),
+            Self::NegX => Some(Vec3::NEG_X),
+            Self::Y => Some(Vec3::Y),
+            Self::NegY => Some(Vec3::NEG_Y),
+            Self::Z => Some(Vec3::Z),
+            Self::NegZ => Some(Vec3::NEG_Z),
+        }
+    }
+
+    fn up(&self) -> Vec3 {
+        match self {
+            Self::Y | Self::NegY => Vec3::NEG_X,
+            _ => Vec3::Y,
+        }
+    }
+
+    pub fn transform(&self, focus: Vec3) -> Transform {
+        match self.direction() {
+            Some(direction) => Transform::from_translation(focus + direction * ORTHO_DISTANCE).looking_at(focus, self.up()),
+            None => Transform::from_translation(focus + FREE_OFFSET).looking_at(focus, Vec3::Y),
+        }
+    }
+
+    pub fn projection(&self) -> Projection {
+        match self {
+            // The field of view and clip planes come from the camera preferences.
+            Self::None => Projection::Perspective(PerspectiveProjection::default()),
+            _ => Projection::Orthographic(OrthographicProjection {
+                near: 0.05,
+                far: 1000.0,
+                scaling_mode: Default::default(),
+                scale: 0.01,
+                ..OrthographicProjection::default_2d()
+            }),
+        }
+    }
+}
+
+/// The text naming a viewport, drawn over `camera`.
+#[derive(Component)]
+pub struct MulticamLabel {
+    pub camera: Entity,
 }
 
 #[derive(Component)]
 pub struct MulticamTestScene;
 
+impl MulticamState {
+    /// Maximizes the camera with this id, or puts a maximized camera back in the layout.
+    pub fn toggle_maximized(&mut self, id: Option<u32>) {
+        if self.maximized.is_some() {
+            self.maximized = None;
+        } else {
+            self.maximized = id;
+        }
+    }
+}
+
+/// How the viewport area is split between cameras.
+#[derive(EnumIter, Display, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MulticamLayout {
+    Single,
+    TwoHorizontal,
+    TwoVertical,
+    OnePlusThree,
+    Grid,
+}
+
+impl MulticamLayout {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Single => get!("viewport.layouts.single"),
+            Self::TwoHorizontal => get!("viewport.layouts.two_horizontal"),
+            Self::TwoVertical => get!("viewport.layouts.two_vertical"),
+            Self::OnePlusThree => get!("viewport.layouts.one_plus_three"),
+            Self::Grid => get!("viewport.layouts.grid"),
+        }
+    }
+
+    /// The region each camera gets, in id order, as fractions of the viewport area.
+    /// `split` is where the draggable dividers are.
+    pub fn regions(&self, split: Vec2) -> Vec<Rect> {
+        let Vec2 { x, y } = split;
+        match self {
+            Self::Single => vec![Rect::new(0.0, 0.0, 1.0, 1.0)],
+            Self::TwoHorizontal => vec![Rect::new(0.0, 0.0, x, 1.0), Rect::new(x, 0.0, 1.0, 1.0)],
+            Self::TwoVertical => vec![Rect::new(0.0, 0.0, 1.0, y), Rect::new(0.0, y, 1.0, 1.0)],
+            Self::OnePlusThree => {
+                let third = 1.0 / 3.0;
+                vec![
+                    Rect::new(0.0, 0.0, x, 1.0),
+                    Rect::new(x, 0.0, 1.0, third),
+                    Rect::new(x, third, 1.0, 2.0 * third),
+                    Rect::new(x, 2.0 * third, 1.0, 1.0),
+                ]
+            }
+            Self::Grid => vec![
+                Rect::new(0.0, 0.0, x, y),
+                Rect::new(x, 0.0, 1.0, y),
+                Rect::new(0.0, y, x, 1.0),
+                Rect::new(x, y, 1.0, 1.0),
+            ],
+        }
+    }
+
+    /// Which dividers can be dragged: the vertical one at `split.x` and the horizontal one at `split.y`.
+    fn dividers(&self) -> (bool, bool) {
+        match self {
+            Self::Single => (false, false),
+            Self::TwoHorizontal | Self::OnePlusThree => (true, false),
+            Self::TwoVertical => (false, true),
+            Self::Grid => (true, true),
+        }
+    }
+}
+
+/// Dividers can't be dragged closer than this to the edge of the viewport area.
+pub(crate) const MIN_SPLIT: f32 = 0.1;
+
 impl Default for MulticamState {
     fn default() -> Self {
         Self {
@@ -60,6 +225,7 @@ impl Default for MulticamState {
             debug_window: false,
             draw_ortho_cameras: false,
             draw_perspective_cameras: true,
+            maximized: None,
         }
     }
 }
@@ -78,7 +244,18 @@ impl Plugin for MulticamPlugin {
             ))
             // Global transforms are propagated from transforms during PostUpdate, so we need to draw the camera after that.
             .add_systems(PostUpdate, Self::draw_camera_gizmos.after(TransformSystem::TransformPropagate))
-            .add_systems(EguiContextPass, Self::debug_window)
+            .add_systems(Update, Self::maximize)
+            .add_systems(Update, Self::update_labels)
+            .add_systems(EguiContextPass, (Self::debug_window, Self::dividers, Self::axis_menus))
+            .add_operation(Operation::new("palette.operations.maximize_viewport", |world| {
+                let camera = world.resource::<CurrentMouseInput>().in_camera;
+                let id = camera.and_then(|camera| world.get::<Multicam>(camera)).map(|multicam| multicam.id);
+                world.resource_mut::<MulticamState>().toggle_maximized(id);
+            }).with_action(InputAction::MaximizeViewport))
+            .add_operation(Operation::new("palette.operations.debug_multicam", |world| {
+                let mut state = world.resource_mut::<MulticamState>();
+                state.debug_window = !state.debug_window;
+            }))
         ;
     }
 }
@@ -90,26 +267,7 @@ impl MulticamPlugin {
         mut meshes: ResMut<Assets<Mesh>>,
         mut materials: ResMut<Assets<StandardMaterial>>,
     ) {
-        let perspective = Projection::Perspective(PerspectiveProjection {
-            fov: 120.0,
-            ..Default::default()
-        });
-        let orthographic = Projection::Orthographic(OrthographicProjection {
-            near: 0.05,
-            far: 1000.0,
-            scaling_mode: Default::default(),
-            scale: 0.01,
-            ..OrthographicProjection::default_2d()
-        });
-
-        let dist = 5.0;
-        let cameras = [
-            (get!("viewport.free"), Transform::from_xyz(-2.5, 4.5, 9.0).looking_at(Vec3::ZERO, Vec3::Y), &perspective, CameraAxis::None),
-            //(get!("viewport.free"), Transform::from_xyz(0.0, 1.5, 1.0).looking_at(Vec3::ZERO, Vec3::Y), &perspective),
-            (get!("viewport.front"), Transform::from_xyz(dist, 0.0, 0.0).looking_at(Vec3::ZERO, Vec3::Y), &orthographic, CameraAxis::X),
-            (get!("viewport.top"), Transform::from_xyz(0.0, dist, 0.0).looking_at(Vec3::ZERO, -Vec3::X), &orthographic, CameraAxis::Y),
-            (get!("viewport.right"), Transform::from_xyz(0.0, 0.0, dist).looking_at(Vec3::ZERO, Vec3::Y), &orthographic, CameraAxis::Z),
-        ];
+        let cameras = [CameraAxis::None, CameraAxis::X, CameraAxis::Y, CameraAxis::Z];
         let cameras_len = cameras.len();
 
         commands.spawn((
@@ -123,12 +281,12 @@ impl MulticamPlugin {
             RenderLayers::layer(31)
             /*Multicam {
                 name: get!("viewport.ui"),
-                screen_pos: UVec2::new(0u32, 0u32),
                 id: cameras_len as u32 + 1,
             },*/
         ));
 
-        for (idx, (camera_name, camera_pos, projection, axis)) in cameras.into_iter().enumerate() {
+        for (idx, axis) in cameras.into_iter().enumerate() {
+            let camera_name = axis.name();
             let camera = commands
                 .spawn((
                     Camera3d::default(),
@@ -137,16 +295,16 @@ impl MulticamPlugin {
                         order: (cameras_len - idx) as isize,
                         ..Default::default()
                     },
-                    camera_pos,
+                    axis.transform(Vec3::ZERO),
                     Bloom::NATURAL,
                     Tonemapping::TonyMcMapface,
                     Multicam {
                         name: camera_name.to_string(),
-                        screen_pos: UVec2::new((idx % 2) as u32, (idx / 2) as u32),
                         id: idx as u32,
                         axis,
+                        render_mode: RenderMode::default_for(axis),
                     },
-                    projection.clone(),
+                    axis.projection(),
                 ))
                 .id();
 
@@ -168,6 +326,7 @@ impl MulticamPlugin {
                                 ..Default::default()
                             },
                             Text::new(camera_name),
+                            MulticamLabel { camera },
                         ));
                     });
         }
@@ -185,10 +344,7 @@ impl MulticamPlugin {
             MeshMaterial3d(materials.add(Color::WHITE)),
             Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
             MulticamTestScene,
-            EditorSelectable {
-                id: "Base".to_owned(),
-                bounding_box: Cuboid::new(8.0, 8.0, 0.2),
-            },
+            EditorSelectable::new("Base"),
         ));
         // cube
         commands.spawn((
@@ -196,10 +352,7 @@ impl MulticamPlugin {
             MeshMaterial3d(materials.add(Color::srgb_u8(124, 144, 255))),
             Transform::from_xyz(0.0, 0.5, 0.0),
             MulticamTestScene,
-            EditorSelectable {
-                id: "Cube 1".to_owned(),
-                bounding_box: Cuboid::new(1.0, 1.0, 1.0),
-            },
+            EditorSelectable::new("Cube 1"),
         ));
         // cube
         commands.spawn((
@@ -207,10 +360,7 @@ impl MulticamPlugin {
             MeshMaterial3d(materials.add(Color::srgb_u8(255, 144, 124))),
             Transform::from_xyz(1.3, 0.5, 1.0).with_rotation(Quat::from_rotation_y(2.0 * std::f32::consts::FRAC_PI_2 / 3.0)),
             MulticamTestScene,
-            EditorSelectable {
-                id: "Cube 2".to_owned(),
-                bounding_box: Cuboid::new(1.0, 1.0, 1.0),
-            },
+            EditorSelectable::new("Cube 2"),
         ));
         
         // light
@@ -284,25 +434,26 @@ impl MulticamPlugin {
         mut resize_events: EventReader<WindowResized>,
         mut cameras: Query<(&mut Camera, &Multicam)>,
         state: Res<MulticamState>,
+        preferences: Res<Preferences>,
         frames: Res<FrameCount>,
     ) {
         for resize_event in resize_events.read() {
             if let Ok(window) = windows.get(resize_event.window) {
-                Self::calculate_resize(&mut cameras, &state, window);
+                Self::calculate_resize(&mut cameras, &state, &preferences, window);
             }
         }
-        if state.is_changed() {
+        if state.is_changed() || preferences.is_changed() {
             if let Ok(window) = windows.single() {
-                Self::calculate_resize(&mut cameras, &state, window);
+                Self::calculate_resize(&mut cameras, &state, &preferences, window);
             }
         }
         if frames.0 < 3 {
             let window = windows.single().unwrap();
-            Self::calculate_resize(&mut cameras, &state, window);
+            Self::calculate_resize(&mut cameras, &state, &preferences, window);
         }
     }
     
-    fn calculate_resize(cameras: &mut Query<(&mut Camera, &Multicam)>, state: &Res<MulticamState>, window: &Window) {
+    fn calculate_resize(cameras: &mut Query<(&mut Camera, &Multicam)>, state: &Res<MulticamState>, preferences: &Preferences, window: &Window) {
         let window_size = window.physical_size();
 
         // Calculate the viewport size based on start and end coordinates
@@ -317,27 +468,186 @@ impl MulticamPlugin {
             (state.start.y * window_size.y as f32) as u32,
         );
         
-        // Calculate the size of each camera's viewport (2x2 grid)
-        let camera_size = UVec2::new(
-            viewport_size.x / 2,
-            viewport_size.y / 2,
-        );
-
-        for (mut camera, multicam) in cameras {
-            // Calculate this camera's position within the viewport
-            let camera_pos = viewport_start + UVec2::new(
-                multicam.screen_pos.x * camera_size.x,
-                multicam.screen_pos.y * camera_size.y,
-            );
-
+        let viewports = &preferences.viewports;
+        let regions = viewports.layout.regions(Vec2::from(viewports.split));
+        let mut ordered: Vec<(Mut<Camera>, &Multicam)> = cameras.iter_mut().collect();
+        ordered.sort_by_key(|(_, multicam)| multicam.id);
+
+        let mut next_region = regions.iter();
+        for (mut camera, multicam) in ordered {
+            let region = match state.maximized {
+                Some(id) if id == multicam.id => Some(&Rect::new(0.0, 0.0, 1.0, 1.0)),
+                Some(_) => None,
+                None => next_region.next(),
+            };
+            // Cameras without a region stop rendering until the layout has room for them again.
+            let Some(region) = region else {
+                camera.is_active = false;
+                continue;
+            };
+            let min = (region.min * viewport_size.as_vec2()).as_uvec2();
+            let max = (region.max * viewport_size.as_vec2()).as_uvec2();
+            camera.is_active = true;
             camera.viewport = Some(Viewport {
-                physical_position: camera_pos,
-                physical_size: camera_size,
+                physical_position: viewport_start + min,
+                physical_size: (max - min).max(UVec2::ONE),
                 ..Default::default()
             });
         }
     }
 
+    /// Points a camera down another axis, or makes it the free camera. It keeps looking at roughly the same place.
+    pub fn set_axis(axis: CameraAxis, multicam: &mut Multicam, transform: &mut Transform, projection: &mut Projection) {
+        if multicam.axis == axis {
+            return;
+        }
+        let forward = transform.forward().as_vec3();
+        let focus = transform.translation - forward * transform.translation.dot(forward);
+        *transform = axis.transform(focus);
+        // Orthographic cameras switching between axes keep their zoom.
+        let keep_projection = matches!(projection, Projection::Orthographic(_)) && axis != CameraAxis::None;
+        if !keep_projection {
+            *projection = axis.projection();
+        }
+        multicam.axis = axis;
+        multicam.name = axis.name();
+    }
+
+    /// A dropdown in the corner of each viewport for picking which way it looks.
+    fn axis_menus(
+        mut contexts: EguiContexts,
+        windows: Query<&Window, With<PrimaryWindow>>,
+        mut cameras: Query<(&Camera, &mut Multicam, &mut Transform, &mut Projection)>,
+    ) {
+        let Some(ctx) = contexts.try_ctx_mut() else {
+            return;
+        };
+        let Ok(window) = windows.single() else {
+            return;
+        };
+        let screen = ctx.screen_rect();
+        let to_screen = screen.size() / egui::vec2(window.physical_width() as f32, window.physical_height() as f32);
+
+        for (camera, mut multicam, mut transform, mut projection) in &mut cameras {
+            let (true, Some(viewport)) = (camera.is_active, &camera.viewport) else {
+                continue;
+            };
+            let top_right = egui::pos2(
+                (viewport.physical_position.x + viewport.physical_size.x) as f32,
+                viewport.physical_position.y as f32,
+            );
+            let top_right = screen.min + top_right.to_vec2() * to_screen;
+
+            let mut chosen = multicam.axis;
+            let mut render_mode = multicam.render_mode;
+            egui::Area::new(egui::Id::new(("viewport_axis", multicam.id)))
+                .fixed_pos(top_right + egui::vec2(-8.0, 8.0))
+                .pivot(egui::Align2::RIGHT_TOP)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt(("viewport_render_mode_combo", multicam.id))
+                            .selected_text(render_mode.name())
+                            .width(130.0)
+                            .show_ui(ui, |ui| {
+                                for mode in RenderMode::iter() {
+                                    ui.selectable_value(&mut render_mode, mode, mode.name());
+                                }
+                            });
+                        egui::ComboBox::from_id_salt(("viewport_axis_combo", multicam.id))
+                            .selected_text(multicam.axis.name())
+                            .width(90.0)
+                            .show_ui(ui, |ui| {
+                                for axis in CameraAxis::iter() {
+                                    ui.selectable_value(&mut chosen, axis, axis.name());
+                                }
+                            });
+                    });
+                });
+            if chosen != multicam.axis {
+                Self::set_axis(chosen, &mut multicam, &mut transform, &mut projection);
+            }
+            if render_mode != multicam.render_mode {
+                multicam.render_mode = render_mode;
+            }
+        }
+    }
+
+    fn update_labels(
+        cameras: Query<&Multicam, Changed<Multicam>>,
+        mut labels: Query<(&MulticamLabel, &mut Text)>,
+    ) {
+        for (label, mut text) in &mut labels {
+            if let Ok(multicam) = cameras.get(label.camera)
+                && text.0 != multicam.name {
+                text.0 = multicam.name.clone();
+            }
+        }
+    }
+
+    /// Maximizes the camera under the pointer, or puts a maximized camera back in the layout.
+    fn maximize(
+        actions: Res<ActionState>,
+        mouse_input: Res<CurrentMouseInput>,
+        cameras: Query<&Multicam>,
+        mut state: ResMut<MulticamState>,
+    ) {
+        if actions.just_pressed(InputAction::MaximizeViewport) {
+            let id = mouse_input.in_camera.and_then(|camera| cameras.get(camera).ok()).map(|multicam| multicam.id);
+            state.toggle_maximized(id);
+        }
+    }
+
+    /// Invisible handles over the dividers between cameras, for dragging them around.
+    fn dividers(
+        mut contexts: EguiContexts,
+        state: Res<MulticamState>,
+        mut preferences: ResMut<Preferences>,
+    ) {
+        if state.maximized.is_some() {
+            return;
+        }
+        let Some(ctx) = contexts.try_ctx_mut() else {
+            return;
+        };
+        let (vertical, horizontal) = preferences.viewports.layout.dividers();
+        let screen = ctx.screen_rect();
+        let area_min = screen.min + egui::vec2(state.start.x, state.start.y) * screen.size();
+        let area_size = egui::vec2(state.end.x - state.start.x, state.end.y - state.start.y) * screen.size();
+        let split = preferences.viewports.split;
+        let grab = 6.0;
+
+        let mut handles = Vec::new();
+        if vertical {
+            let x = area_min.x + split[0] * area_size.x;
+            handles.push((0, egui::Rect::from_min_size(egui::pos2(x - grab / 2.0, area_min.y), egui::vec2(grab, area_size.y))));
+        }
+        if horizontal {
+            let y = area_min.y + split[1] * area_size.y;
+            handles.push((1, egui::Rect::from_min_size(egui::pos2(area_min.x, y - grab / 2.0), egui::vec2(area_size.x, grab))));
+        }
+
+        for (axis, rect) in handles {
+            egui::Area::new(egui::Id::new(("multicam_divider", axis)))
+                .fixed_pos(rect.min)
+                .order(egui::Order::Middle)
+                .show(ctx, |ui| {
+                    let response = ui.allocate_rect(egui::Rect::from_min_size(rect.min, rect.size()), egui::Sense::drag());
+                    if response.hovered() || response.dragged() {
+                        ui.ctx().set_cursor_icon(if axis == 0 {
+                            egui::CursorIcon::ResizeHorizontal
+                        } else {
+                            egui::CursorIcon::ResizeVertical
+                        });
+                    }
+                    if response.dragged() {
+                        let delta = response.drag_delta()[axis] / area_size[axis];
+                        let split = &mut preferences.viewports.split[axis];
+                        *split = (*split + delta).clamp(MIN_SPLIT, 1.0 - MIN_SPLIT);
+                    }
+                });
+        }
+    }
+
     fn debug_window(
         mut state: ResMut<MulticamState>,
         mut contexts: EguiContexts,
@@ -433,7 +743,7 @@ impl MulticamPlugin {
         Self::debug_ui_boxes(&state, &mut painter, window, &ui_cam);
 
         if let Some(cursor_pos_window) = window.cursor_position() {
-            for (camera_entity, camera, camera_tfm, camera_multicam) in &cameras_q {
+            for (camera_entity, camera, camera_tfm, camera_multicam) in cameras_q.iter().filter(|(_, camera, _, _)| camera.is_active) {
                 if let Ok((ui_cam, _)) = ui_cam {
                     if let Some(viewport) = &camera.viewport {
                         let vp_min = viewport.physical_position.as_vec2();
@@ -517,13 +827,49 @@ impl MulticamPlugin {
     }
 }
 
-fn draw_rect(
-    painter: &mut ShapePainter,
-    min: Vec2,
-    max: Vec2,
-) {
-    painter.line(Vec3::new(min.x, min.y, 0.0), Vec3::new(max.x, min.y, 0.0)); // Bottom
-    painter.line(Vec3::new(min.x, max.y, 0.0), Vec3::new(max.x, max.y, 0.0)); // Top
-    painter.line(Vec3::new(min.x, min.y, 0.0), Vec3::new(min.x, max.y, 0.0)); // Left
-    painter.line(Vec3::new(max.x, min.y, 0.0), Vec3::new(max.x, max.y, 0.0)); // Right
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions_cover_the_viewport_area() {
+        let split = Vec2::new(0.3, 0.6);
+        for layout in MulticamLayout::iter() {
+            let area: f32 = layout.regions(split).iter().map(|region| region.width() * region.height()).sum();
+            assert!((area - 1.0).abs() < 1e-5, "{:?} covers {}", layout, area);
+        }
+    }
+
+    #[test]
+    fn test_regions_follow_the_split() {
+        let regions = MulticamLayout::Grid.regions(Vec2::new(0.25, 0.75));
+        assert_eq!(regions[0], Rect::new(0.0, 0.0, 0.25, 0.75));
+        assert_eq!(regions[3], Rect::new(0.25, 0.75, 1.0, 1.0));
+        assert_eq!(MulticamLayout::OnePlusThree.regions(Vec2::splat(0.5)).len(), 4);
+        assert_eq!(MulticamLayout::Single.regions(Vec2::splat(0.5)).len(), 1);
+    }
+
+    #[test]
+    fn test_ortho_cameras_look_back_along_their_axis() {
+        for axis in CameraAxis::iter().filter(|axis| *axis != CameraAxis::None) {
+            let direction = axis.direction().unwrap();
+            let transform = axis.transform(Vec3::new(1.0, 2.0, 3.0));
+            assert!(transform.forward().as_vec3().abs_diff_eq(-direction, 1e-5), "{:?}", axis);
+            assert!((transform.translation - Vec3::new(1.0, 2.0, 3.0)).abs_diff_eq(direction * ORTHO_DISTANCE, 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_switching_axis_keeps_focus() {
+        let mut multicam = Multicam { name: String::new(), id: 1, axis: CameraAxis::X, render_mode: RenderMode::Wireframe };
+        let mut transform = CameraAxis::X.transform(Vec3::new(0.0, 2.0, -1.0));
+        let mut projection = CameraAxis::X.projection();
+        MulticamPlugin::set_axis(CameraAxis::NegZ, &mut multicam, &mut transform, &mut projection);
+        assert_eq!(multicam.axis, CameraAxis::NegZ);
+        assert!(transform.translation.abs_diff_eq(Vec3::new(0.0, 2.0, -1.0 - ORTHO_DISTANCE), 1e-5));
+        assert!(matches!(projection, Projection::Orthographic(_)));
+
+        MulticamPlugin::set_axis(CameraAxis::None, &mut multicam, &mut transform, &mut projection);
+        assert!(matches!(projection, Projection::Perspective(_)));
+    }
 }
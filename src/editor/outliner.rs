@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::Ui;
+use crate::editor::editable::EditorActions;
+use crate::editor::layers::{EditorLayers, OnLayer, DEFAULT_LAYER};
+use crate::get;
+use crate::tool::room::Room;
+use crate::tool::selection::{EditorSelectable, Locked, SelectionMode, SelectionState};
+
+/// A snapshot of one entity for the outliner to draw.
+pub struct OutlinerRow {
+    pub entity: Entity,
+    pub name: String,
+    pub kind: String,
+    /// None if the entity has no `Visibility` to toggle.
+    pub visible: Option<bool>,
+    pub locked: bool,
+    pub layer: String,
+}
+
+/// Changes the outliner asks for while drawing, applied once the panels are done.
+enum OutlinerRequest {
+    Select(Entity, SelectionMode),
+    SetVisible(Entity, bool),
+    SetLocked(Entity, bool),
+    AddLayer,
+    RemoveLayer(u32),
+    SetActiveLayer(u32),
+    SetLayerVisible(u32, bool),
+    SetLayerLocked(u32, bool),
+    SetLayerTint(u32, [f32; 3]),
+    RenameLayer(u32, String),
+    MoveSelectionToLayer(u32),
+}
+
+/// The outliner gathers its rows before the panels are drawn and applies what was clicked afterwards,
+/// so the panels system doesn't need the queries.
+#[derive(Resource, Default)]
+pub struct Outliner {
+    pub filter: String,
+    rows: Vec<OutlinerRow>,
+    layers: EditorLayers,
+    requests: Vec<OutlinerRequest>,
+}
+
+impl Outliner {
+    pub fn refresh(
+        mut outliner: ResMut<Self>,
+        layers: Res<EditorLayers>,
+        outlined: Query<(Entity, &EditorSelectable, Has<Room>, Option<&Visibility>, Has<Locked>, Option<&OnLayer>)>,
+    ) {
+        let mut rows: Vec<OutlinerRow> = outlined
+            .iter()
+            .map(|(entity, select, is_room, visibility, locked, on_layer)| OutlinerRow {
+                entity,
+                name: select.id.clone(),
+                kind: if is_room { get!("outliner.type.room") } else { get!("outliner.type.object") },
+                visible: visibility.map(|visibility| *visibility != Visibility::Hidden),
+                locked,
+                layer: layers.resolve(on_layer.map_or(layers.active, |on_layer| on_layer.0)).name.clone(),
+            })
+            .collect();
+        rows.sort_by(|a, b| a.name.cmp(&b.name).then(a.entity.cmp(&b.entity)));
+        outliner.rows = rows;
+        if outliner.layers != *layers {
+            outliner.layers = layers.clone();
+        }
+    }
+
+    pub fn ui(
+        ui: &mut Ui,
+        outliner: &mut Self,
+        selection: &SelectionState,
+        actions: &mut EditorActions,
+    ) {
+        let Self { filter, rows, layers, requests } = outliner;
+        ui.add(egui::TextEdit::singleline(filter).hint_text(get!("outliner.filter")));
+        let mode = selection_mode(ui.input(|input| input.modifiers));
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            Self::layers_ui(ui, layers, !selection.selected.is_empty(), requests);
+
+            ui.separator();
+            ui.heading(get!("outliner.objects"));
+            let shown: Vec<&OutlinerRow> = rows.iter().filter(|row| matches(filter, &row.name, &row.kind)).collect();
+            egui::Grid::new("outliner_objects").num_columns(5).striped(true).show(ui, |ui| {
+                for row in &shown {
+                    match row.visible {
+                        Some(visible) => {
+                            if ui.selectable_label(visible, "👁")
+                                .on_hover_text(get!("outliner.visible"))
+                                .clicked() {
+                                requests.push(OutlinerRequest::SetVisible(row.entity, !visible));
+                            }
+                        }
+                        None => { ui.label(""); }
+                    }
+                    if ui.selectable_label(row.locked, "🔒")
+                        .on_hover_text(get!("outliner.locked"))
+                        .clicked() {
+                        requests.push(OutlinerRequest::SetLocked(row.entity, !row.locked));
+                    }
+                    if ui.selectable_label(selection.is_selected(row.entity), &row.name).clicked() {
+                        requests.push(OutlinerRequest::Select(row.entity, mode));
+                    }
+                    ui.weak(&row.kind);
+                    ui.weak(&row.layer);
+                    ui.end_row();
+                }
+            });
+            if shown.is_empty() {
+                ui.weak(get!("outliner.empty"));
+            }
+
+            ui.separator();
+            ui.heading(get!("outliner.actions"));
+            let mut next_selected = None;
+            for action in actions.iter() {
+                let name = action.type_name_with_id();
+                if !matches(filter, &name, &action.type_name()) {
+                    continue;
+                }
+                let is_selected = actions.selected() == Some(action.id());
+                if ui.selectable_label(is_selected, name).clicked() {
+                    next_selected = Some(if is_selected { None } else { Some(action.id()) });
+                }
+            }
+            if let Some(next_selected) = next_selected {
+                actions.select(next_selected);
+            }
+        });
+    }
+
+    /// One row per layer: which one new objects go on, whether it's shown, locked and tinted, and its name.
+    fn layers_ui(ui: &mut Ui, layers: &mut EditorLayers, has_selection: bool, requests: &mut Vec<OutlinerRequest>) {
+        ui.horizontal(|ui| {
+            ui.heading(get!("outliner.layers.title"));
+            if ui.small_button("➕").on_hover_text(get!("outliner.layers.add")).clicked() {
+                requests.push(OutlinerRequest::AddLayer);
+            }
+        });
+        let active = layers.active;
+        let ids: Vec<u32> = layers.iter().map(|layer| layer.id).collect();
+        egui::Grid::new("outliner_layers").num_columns(7).show(ui, |ui| {
+            for id in ids {
+                let Some(layer) = layers.get_mut(id) else { continue; };
+                if ui.radio(active == id, "").on_hover_text(get!("outliner.layers.active")).clicked() && active != id {
+                    requests.push(OutlinerRequest::SetActiveLayer(id));
+                }
+                if ui.selectable_label(layer.visible, "👁").on_hover_text(get!("outliner.visible")).clicked() {
+                    requests.push(OutlinerRequest::SetLayerVisible(id, !layer.visible));
+                }
+                if ui.selectable_label(layer.locked, "🔒").on_hover_text(get!("outliner.locked")).clicked() {
+                    requests.push(OutlinerRequest::SetLayerLocked(id, !layer.locked));
+                }
+                if ui.color_edit_button_rgb(&mut layer.tint).on_hover_text(get!("outliner.layers.tint")).changed() {
+                    requests.push(OutlinerRequest::SetLayerTint(id, layer.tint));
+                }
+                if ui.add(egui::TextEdit::singleline(&mut layer.name).desired_width(100.0)).changed() {
+                    requests.push(OutlinerRequest::RenameLayer(id, layer.name.clone()));
+                }
+                if ui.add_enabled(has_selection, egui::Button::new("⤵").small())
+                    .on_hover_text(get!("outliner.layers.move_selection"))
+                    .clicked() {
+                    requests.push(OutlinerRequest::MoveSelectionToLayer(id));
+                }
+                if ui.add_enabled(id != DEFAULT_LAYER, egui::Button::new("🗑").small())
+                    .on_hover_text(get!("outliner.layers.remove"))
+                    .clicked() {
+                    requests.push(OutlinerRequest::RemoveLayer(id));
+                }
+                ui.end_row();
+            }
+        });
+    }
+
+    pub fn apply(
+        mut outliner: ResMut<Self>,
+        mut selection: ResMut<SelectionState>,
+        mut layers: ResMut<EditorLayers>,
+        mut visibilities: Query<&mut Visibility>,
+        mut commands: Commands,
+    ) {
+        for request in outliner.requests.drain(..) {
+            match request {
+                OutlinerRequest::Select(entity, mode) => {
+                    selection.apply(mode, [entity]);
+                }
+                OutlinerRequest::SetVisible(entity, visible) => {
+                    if let Ok(mut visibility) = visibilities.get_mut(entity) {
+                        *visibility = if visible { Visibility::Inherited } else { Visibility::Hidden };
+                    }
+                }
+                OutlinerRequest::SetLocked(entity, locked) => {
+                    if locked {
+                        commands.entity(entity).insert(Locked);
+                    } else {
+                        commands.entity(entity).remove::<Locked>();
+                    }
+                }
+                OutlinerRequest::AddLayer => {
+                    let count = layers.iter().count();
+                    layers.add(get!("layers.new", "number", count + 1));
+                }
+                OutlinerRequest::RemoveLayer(id) => {
+                    layers.remove(id);
+                }
+                OutlinerRequest::SetActiveLayer(id) => {
+                    layers.active = id;
+                }
+                OutlinerRequest::SetLayerVisible(id, visible) => {
+                    if let Some(layer) = layers.get_mut(id) {
+                        layer.visible = visible;
+                    }
+                }
+                OutlinerRequest::SetLayerLocked(id, locked) => {
+                    if let Some(layer) = layers.get_mut(id) {
+                        layer.locked = locked;
+                    }
+                }
+                OutlinerRequest::SetLayerTint(id, tint) => {
+                    if let Some(layer) = layers.get_mut(id) {
+                        layer.tint = tint;
+                    }
+                }
+                OutlinerRequest::RenameLayer(id, name) => {
+                    if let Some(layer) = layers.get_mut(id) {
+                        layer.name = name;
+                    }
+                }
+                OutlinerRequest::MoveSelectionToLayer(id) => {
+                    for entity in &selection.selected {
+                        commands.entity(*entity).insert(OnLayer(id));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn matches(filter: &str, name: &str, kind: &str) -> bool {
+    let filter = filter.trim().to_lowercase();
+    filter.is_empty() || name.to_lowercase().contains(&filter) || kind.to_lowercase().contains(&filter)
+}
+
+/// The same modifiers as clicking in the viewports: Ctrl toggles, Shift adds.
+fn selection_mode(modifiers: egui::Modifiers) -> SelectionMode {
+    if modifiers.command {
+        SelectionMode::Toggle
+    } else if modifiers.shift {
+        SelectionMode::Add
+    } else {
+        SelectionMode::Replace
+    }
+}
@@ -0,0 +1,267 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContextPass, EguiContexts};
+use bevy_egui::egui::{Align, Align2, Key, Layout};
+use crate::editor::console::Console;
+use crate::editor::keymap::{ActionState, InputAction, Keymap};
+use crate::get;
+
+pub struct CommandPalettePlugin;
+
+impl Plugin for CommandPalettePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Operations>()
+            .init_resource::<CommandPalette>()
+            .add_systems(Update, (
+                CommandPalette::toggle,
+                CommandPalette::run_chosen,
+            ))
+            .add_systems(EguiContextPass, CommandPalette::window)
+        ;
+    }
+}
+
+pub type OperationHandler = fn(&mut World);
+
+#[derive(Clone, Copy)]
+enum OperationRun {
+    /// A console command line, so the result shows up in the console.
+    Command(&'static str),
+    Handler(OperationHandler),
+}
+
+/// Something the command palette can do.
+#[derive(Clone, Copy)]
+pub struct Operation {
+    /// Lang key for the name shown in the palette.
+    pub name_key: &'static str,
+    run: OperationRun,
+    /// The keymap action that does the same thing, so the palette can show its binding.
+    pub action: Option<InputAction>,
+}
+
+impl Operation {
+    pub fn new(name_key: &'static str, run: OperationHandler) -> Self {
+        Self {
+            name_key,
+            run: OperationRun::Handler(run),
+            action: None,
+        }
+    }
+
+    pub fn command(name_key: &'static str, command: &'static str) -> Self {
+        Self {
+            name_key,
+            run: OperationRun::Command(command),
+            action: None,
+        }
+    }
+
+    pub fn with_action(mut self, action: InputAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+}
+
+/// Every operation in the palette. Plugins add to it with `App::add_operation`.
+#[derive(Resource, Default)]
+pub struct Operations {
+    operations: Vec<Operation>,
+}
+
+pub trait OperationsExt {
+    fn add_operation(&mut self, operation: Operation) -> &mut Self;
+}
+
+impl OperationsExt for App {
+    fn add_operation(&mut self, operation: Operation) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<Operations>()
+            .operations
+            .push(operation);
+        self
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    pub query: String,
+    /// Index into the filtered list.
+    selected: usize,
+    focus: bool,
+    /// Index into `Operations`, run at the next `run_chosen`.
+    chosen: Option<usize>,
+}
+
+impl CommandPalette {
+    fn toggle(actions: Res<ActionState>, mut palette: ResMut<Self>) {
+        if actions.just_pressed(InputAction::CommandPalette) {
+            palette.open = !palette.open;
+            palette.query.clear();
+            palette.selected = 0;
+            palette.focus = true;
+        }
+    }
+
+    fn window(
+        mut contexts: EguiContexts,
+        mut palette: ResMut<Self>,
+        operations: Res<Operations>,
+        keymap: Res<Keymap>,
+    ) {
+        if !palette.open {
+            return;
+        }
+        let Some(ctx) = contexts.try_ctx_mut() else {
+            return;
+        };
+
+        let mut matches: Vec<(usize, String, i32)> = operations.operations
+            .iter()
+            .enumerate()
+            .filter_map(|(index, operation)| {
+                let name = get!(operation.name_key);
+                fuzzy_score(&palette.query, &name).map(|score| (index, name, score))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.1.cmp(&b.1)));
+
+        let (up, down, enter, escape) = ctx.input(|input| (
+            input.key_pressed(Key::ArrowUp),
+            input.key_pressed(Key::ArrowDown),
+            input.key_pressed(Key::Enter),
+            input.key_pressed(Key::Escape),
+        ));
+        if down {
+            palette.selected += 1;
+        }
+        if up {
+            palette.selected = palette.selected.saturating_sub(1);
+        }
+        palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+
+        let mut chosen = None;
+        egui::Window::new(get!("palette.title"))
+            .title_bar(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_TOP, [0.0, 60.0])
+            .min_width(420.0)
+            .max_width(420.0)
+            .show(ctx, |ui| {
+                let input = ui.add(egui::TextEdit::singleline(&mut palette.query)
+                    .hint_text(get!("palette.hint"))
+                    .desired_width(f32::INFINITY));
+                if palette.focus {
+                    input.request_focus();
+                    palette.focus = false;
+                }
+                if input.changed() {
+                    palette.selected = 0;
+                }
+
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    if matches.is_empty() {
+                        ui.weak(get!("palette.none"));
+                    }
+                    for (row, (index, name, _)) in matches.iter().enumerate() {
+                        let selected = row == palette.selected;
+                        let binding = operations.operations[*index].action.map(|action| keymap.describe(action));
+                        ui.horizontal(|ui| {
+                            let label = ui.selectable_label(selected, name);
+                            if selected && (up || down) {
+                                label.scroll_to_me(None);
+                            }
+                            if label.clicked() {
+                                chosen = Some(*index);
+                            }
+                            if let Some(binding) = binding {
+                                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                    ui.weak(binding);
+                                });
+                            }
+                        });
+                    }
+                });
+            });
+
+        if enter && let Some((index, _, _)) = matches.get(palette.selected) {
+            chosen = Some(*index);
+        }
+        if chosen.is_some() || escape {
+            palette.chosen = chosen;
+            palette.open = false;
+        }
+    }
+
+    fn run_chosen(world: &mut World) {
+        let Some(index) = world.resource_mut::<Self>().chosen.take() else {
+            return;
+        };
+        let Some(operation) = world.resource::<Operations>().operations.get(index).copied() else {
+            return;
+        };
+        match operation.run {
+            OperationRun::Command(command) => world.resource_mut::<Console>().submit(command),
+            OperationRun::Handler(run) => run(world),
+        }
+    }
+}
+
+/// Scores how well `query` matches `candidate`, if every character of the query appears in it in order.
+/// Runs of consecutive characters and matches at the start of words score higher; gaps score lower.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let query: Vec<char> = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut matched = 0;
+    let mut last_match: Option<usize> = None;
+    for (index, c) in candidate.iter().enumerate() {
+        if matched == query.len() {
+            break;
+        }
+        if *c != query[matched] {
+            continue;
+        }
+        score += 1;
+        if index == 0 || !candidate[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        match last_match {
+            Some(last) if last + 1 == index => score += 5,
+            Some(last) => score -= (index - last - 1).min(3) as i32,
+            None => {}
+        }
+        last_match = Some(index);
+        matched += 1;
+    }
+    (matched == query.len()).then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_matches_in_order() {
+        assert!(fuzzy_score("bkrm", "Bake room geometry").is_some());
+        assert!(fuzzy_score("mrkb", "Bake room geometry").is_none());
+        assert_eq!(fuzzy_score("", "Anything"), Some(0));
+    }
+
+    #[test]
+    fn test_fuzzy_prefers_runs_and_word_starts() {
+        let run = fuzzy_score("room", "Room tool").unwrap();
+        let scattered = fuzzy_score("room", "Reset orthographic zoom").unwrap();
+        assert!(run > scattered);
+
+        let word_start = fuzzy_score("mt", "Move tool").unwrap();
+        let middle = fuzzy_score("mt", "Commit").unwrap();
+        assert!(word_start > middle);
+    }
+
+    #[test]
+    fn test_fuzzy_ignores_case_and_spaces() {
+        assert_eq!(fuzzy_score("MOVE T", "move tool"), fuzzy_score("movet", "Move Tool"));
+    }
+}
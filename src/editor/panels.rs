@@ -1,18 +1,57 @@
 use bevy_egui::egui::Context;
 use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
 use bevy::window::PrimaryWindow;
 use bevy_egui::{egui, EguiContext, EguiContextPass, EguiContexts};
 use bevy_egui::egui::{Ui, WidgetText};
 use egui_dock::{DockArea, DockState, TabViewer};
-use strum_macros::Display;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+use crate::common::{config, lang};
+use crate::editor::console::Console;
 use crate::editor::editable::EditorActions;
-use crate::editor::multicam::MulticamState;
-use crate::tool::Tools;
+use crate::editor::inspector::{InspectedEntity, Inspectables, Inspector};
+use crate::editor::multicam::{MulticamLayout, MulticamState};
+use crate::editor::keymap::{Keymap, KeymapScreen};
+use crate::editor::outliner::Outliner;
+use crate::editor::palette::{Operation, OperationsExt};
+use crate::editor::preferences::Preferences;
+use crate::editor::problems::Problems;
+use crate::editor::status_bar::StatusBarPlugin;
+use crate::get;
+use crate::tool::history::EditHistory;
+use crate::tool::movement::CameraSettingsScreen;
+use crate::tool::selection::SelectionState;
+use crate::tool::{ToolOptions, ToolPlugin, Tools};
 
+const LAYOUT_FILE: &str = "layout.json";
+
+#[derive(Serialize, Deserialize)]
 enum TabKinds {
     Empty(String),
     Tools,
     Timeline,
+    Inspector,
+    Outliner,
+    Console,
+    Problems,
+}
+
+/// Resources that belong to a single tab, bundled so the panels system stays under Bevy's parameter limit.
+#[derive(SystemParam)]
+struct TabResources<'w> {
+    outliner: ResMut<'w, Outliner>,
+    console: ResMut<'w, Console>,
+    problems: ResMut<'w, Problems>,
+}
+
+/// Resources the menu bar edits.
+#[derive(SystemParam)]
+struct MenuResources<'w> {
+    keymap_screen: ResMut<'w, KeymapScreen>,
+    camera_settings: ResMut<'w, CameraSettingsScreen>,
+    preferences: ResMut<'w, Preferences>,
 }
 
 struct TabViewerAndResources<'a> {
@@ -20,6 +59,13 @@ struct TabViewerAndResources<'a> {
     next_tool: &'a mut NextState<Tools>,
     editor_actions: &'a mut EditorActions,
     gizmos: Gizmos<'a, 'a>,
+    tool_options: ToolOptions<'a>,
+    inspector: &'a mut Inspector,
+    inspected: Option<InspectedEntity>,
+    selected_count: usize,
+    tabs: TabResources<'a>,
+    keymap: &'a Keymap,
+    selection: &'a SelectionState,
 }
 
 impl<'a> TabViewer for TabViewerAndResources<'a> {
@@ -28,8 +74,12 @@ impl<'a> TabViewer for TabViewerAndResources<'a> {
     fn title(&mut self, tab: &mut Self::Tab) -> WidgetText {
         match tab {
             TabKinds::Empty(name) => { name.as_str().into() }
-            TabKinds::Tools => { "Tools".into() }
+            TabKinds::Tools => { get!("tools.title").into() }
             TabKinds::Timeline => { "Timeline".into() }
+            TabKinds::Inspector => { get!("inspector.title").into() }
+            TabKinds::Outliner => { get!("outliner.title").into() }
+            TabKinds::Console => { get!("console.title").into() }
+            TabKinds::Problems => { get!("problems.title").into() }
         }
     }
 
@@ -39,12 +89,26 @@ impl<'a> TabViewer for TabViewerAndResources<'a> {
                 ui.label(format!("Empty: {}", name));
             }
             TabKinds::Tools => {
-                ui.label("Tools.");
+                ToolPlugin::toolbar(ui, self.current_tool, self.next_tool, self.keymap);
+                ui.separator();
+                self.tool_options.ui(ui, self.current_tool.get());
             }
             TabKinds::Timeline => {
                 ui.label("Timeline.");
                 EditorActions::ui(ui, self.editor_actions)
             }
+            TabKinds::Inspector => {
+                Inspector::ui(ui, self.inspector, &mut self.inspected, self.selected_count, self.editor_actions);
+            }
+            TabKinds::Outliner => {
+                Outliner::ui(ui, &mut self.tabs.outliner, self.selection, self.editor_actions);
+            }
+            TabKinds::Console => {
+                Console::ui(ui, &mut self.tabs.console);
+            }
+            TabKinds::Problems => {
+                Problems::ui(ui, &mut self.tabs.problems);
+            }
         }
     }
 }
@@ -54,8 +118,31 @@ impl Plugin for EditorPanelPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<EditorPanels>()
-            .add_systems(Startup, EditorPanels::set_multicam_size)
-            .add_systems(EguiContextPass, EditorPanels::ui)
+            .init_resource::<Inspector>()
+            .init_resource::<Outliner>()
+            .init_resource::<Problems>()
+            .add_systems(Update, Problems::refresh)
+            .add_systems(Startup, (EditorPanels::restore, EditorPanels::set_multicam_size).chain())
+            .add_systems(EguiContextPass, (
+                Outliner::refresh.before(EditorPanels::ui),
+                // After the status bar, so it takes the very bottom of the window.
+                EditorPanels::ui.after(StatusBarPlugin::ui),
+                Outliner::apply.after(EditorPanels::ui),
+                Problems::apply.after(EditorPanels::ui),
+            ))
+            .add_systems(Last, EditorPanels::save.run_if(on_event::<AppExit>))
+            .add_operation(Operation::new("palette.operations.layout_modeling", |world| {
+                *world.resource_mut::<EditorPanels>() = EditorPanels::from_preset(LayoutPreset::Modeling);
+            }))
+            .add_operation(Operation::new("palette.operations.layout_lighting", |world| {
+                *world.resource_mut::<EditorPanels>() = EditorPanels::from_preset(LayoutPreset::Lighting);
+            }))
+            .add_operation(Operation::new("palette.operations.layout_playtest", |world| {
+                *world.resource_mut::<EditorPanels>() = EditorPanels::from_preset(LayoutPreset::Playtest);
+            }))
+            .add_operation(Operation::new("palette.operations.layout_reset", |world| {
+                *world.resource_mut::<EditorPanels>() = EditorPanels::new();
+            }))
         ;
     }
 }
@@ -68,9 +155,28 @@ pub enum EditorPanelLocation {
     Top,
 }
 
-#[derive(Resource)]
+#[derive(EnumIter, Display, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutPreset {
+    Modeling,
+    Lighting,
+    Playtest,
+}
+
+impl LayoutPreset {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Modeling => get!("layout.modeling"),
+            Self::Lighting => get!("layout.lighting"),
+            Self::Playtest => get!("layout.playtest"),
+        }
+    }
+}
+
+/// The dock layout and panel sizes are saved to the user's config directory on exit.
+#[derive(Resource, Serialize, Deserialize)]
 pub struct EditorPanels {
     top_tabs: DockState<TabKinds>,
+    #[serde(skip)]
     toolbar_height: f32,
     top_height: f32,
     bottom_tabs: DockState<TabKinds>,
@@ -79,6 +185,12 @@ pub struct EditorPanels {
     left_width: f32,
     right_tabs: DockState<TabKinds>,
     right_width: f32,
+    /// Drawn by the status bar, below the bottom dock.
+    #[serde(skip)]
+    status_bar_height: f32,
+    /// Set when the sizes were changed from outside egui, so the panels get forced to them next frame.
+    #[serde(skip)]
+    resize_pending: bool,
 }
 
 pub enum PanelError {
@@ -96,20 +208,150 @@ impl Default for EditorPanels {
 impl EditorPanels {
     pub fn new() -> Self {
         let default_top_tabs = vec![TabKinds::Tools];
-        let default_left_tabs = vec![TabKinds::Timeline, TabKinds::Empty("Alpha".to_owned())];
-        let default_right_tabs = vec![TabKinds::Empty("Beta".to_owned()), TabKinds::Empty("Gamma".to_owned())];
-        let default_bottom_tabs = vec![TabKinds::Empty("Delta".to_owned()), TabKinds::Empty("Epsilon".to_owned())];
+        let default_left_tabs = vec![TabKinds::Outliner, TabKinds::Timeline];
+        let default_right_tabs = vec![TabKinds::Inspector, TabKinds::Problems];
+        let default_bottom_tabs = vec![TabKinds::Console, TabKinds::Empty("Epsilon".to_owned())];
         
         Self {
             top_tabs: DockState::new(default_top_tabs),
             toolbar_height: 20.0,
-            top_height: 40.0,
+            top_height: 72.0,
             bottom_tabs: DockState::new(default_bottom_tabs),
             bottom_height: 30.0,
             left_tabs: DockState::new(default_left_tabs),
             left_width: 40.0,
             right_tabs: DockState::new(default_right_tabs),
             right_width: 40.0,
+            status_bar_height: 0.0,
+            resize_pending: true,
+        }
+    }
+
+    pub fn from_preset(preset: LayoutPreset) -> Self {
+        let (left_tabs, right_tabs, bottom_tabs, left_width, right_width, bottom_height) = match preset {
+            LayoutPreset::Modeling => (
+                vec![TabKinds::Outliner],
+                vec![TabKinds::Inspector, TabKinds::Problems],
+                vec![TabKinds::Timeline, TabKinds::Console],
+                220.0, 260.0, 160.0,
+            ),
+            LayoutPreset::Lighting => (
+                vec![TabKinds::Outliner],
+                vec![TabKinds::Inspector],
+                vec![TabKinds::Console, TabKinds::Problems],
+                180.0, 340.0, 120.0,
+            ),
+            LayoutPreset::Playtest => (vec![], vec![], vec![TabKinds::Console], 0.0, 0.0, 120.0),
+        };
+        Self {
+            top_tabs: DockState::new(vec![TabKinds::Tools]),
+            toolbar_height: 20.0,
+            top_height: 72.0,
+            bottom_tabs: DockState::new(bottom_tabs),
+            bottom_height,
+            left_tabs: DockState::new(left_tabs),
+            left_width,
+            right_tabs: DockState::new(right_tabs),
+            right_width,
+            status_bar_height: 0.0,
+            resize_pending: true,
+        }
+    }
+
+    fn restore(mut panels: ResMut<Self>) {
+        let json = match config::read_config(LAYOUT_FILE) {
+            Ok(Some(json)) => json,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("{}", e);
+                return;
+            }
+        };
+        match serde_json::from_str::<Self>(&json) {
+            Ok(layout) => {
+                *panels = Self {
+                    toolbar_height: panels.toolbar_height,
+                    status_bar_height: panels.status_bar_height,
+                    resize_pending: true,
+                    ..layout
+                };
+            }
+            Err(e) => warn!("Ignoring saved layout \"{}\": {}", LAYOUT_FILE, e),
+        }
+    }
+
+    fn save(panels: Res<Self>) {
+        let result = serde_json::to_string_pretty(&*panels)
+            .map_err(|e| e.to_string())
+            .and_then(|json| config::write_config(LAYOUT_FILE, &json));
+        if let Err(e) = result {
+            warn!("Could not save layout: {}", e);
+        }
+    }
+
+    pub fn set_status_bar_height(&mut self, height: f32) {
+        self.status_bar_height = height;
+    }
+
+    fn is_empty(tabs: &DockState<TabKinds>) -> bool {
+        tabs.iter_all_tabs().next().is_none()
+    }
+
+    fn menu_bar(&mut self, ctx: &mut egui::Context, menus: &mut MenuResources) {
+        let mut chosen = None;
+        let mut reset = false;
+        self.toolbar_height = egui::TopBottomPanel::top("menu_bar")
+            .show(ctx, |ui| {
+                egui::menu::bar(ui, |ui| {
+                    ui.menu_button(get!("layout.title"), |ui| {
+                        for preset in LayoutPreset::iter() {
+                            if ui.button(preset.name()).clicked() {
+                                chosen = Some(preset);
+                                ui.close_menu();
+                            }
+                        }
+                        ui.separator();
+                        if ui.button(get!("layout.reset")).clicked() {
+                            reset = true;
+                            ui.close_menu();
+                        }
+                    });
+                    ui.menu_button(get!("viewport.title"), |ui| {
+                        for layout in MulticamLayout::iter() {
+                            let current = menus.preferences.viewports.layout == layout;
+                            if ui.radio(current, layout.name()).clicked() && !current {
+                                menus.preferences.viewports.layout = layout;
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.menu_button(get!("settings.title"), |ui| {
+                        if ui.button(get!("keymap.menu")).clicked() {
+                            menus.keymap_screen.open = true;
+                            ui.close_menu();
+                        }
+                        if ui.button(get!("camera_settings.menu")).clicked() {
+                            menus.camera_settings.open = true;
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        let mut highlight = lang::highlight_fallbacks();
+                        if ui.checkbox(&mut highlight, get!("settings.highlight_fallbacks"))
+                            .on_hover_text(get!("settings.highlight_fallbacks_tooltip"))
+                            .changed() {
+                            lang::set_highlight_fallbacks(highlight);
+                        }
+                    });
+                });
+            })
+            .response
+            .rect
+            .height();
+
+        if let Some(preset) = chosen {
+            *self = Self::from_preset(preset);
+        } else if reset {
+            *self = Self::new();
         }
     }
 
@@ -123,6 +365,14 @@ impl EditorPanels {
         mut gizmos: Gizmos,
         mut next_tool: ResMut<NextState<Tools>>,
         mut editor_actions: ResMut<EditorActions>,
+        mut inspector: ResMut<Inspector>,
+        mut history: ResMut<EditHistory>,
+        selection: Res<SelectionState>,
+        mut inspectables: Inspectables,
+        tabs: TabResources,
+        tool_options: ToolOptions,
+        keymap: Res<Keymap>,
+        mut menus: MenuResources,
     ) -> Result {
         let ctx = contexts.try_ctx_mut();
         if ctx.is_none() {
@@ -130,15 +380,32 @@ impl EditorPanels {
         }
         let ctx = ctx.unwrap();
         
+        let inspected = inspector.gather(&selection, &inspectables);
         let mut viewer = TabViewerAndResources  {
             current_tool: & *current_tool,
             gizmos,
+            tool_options,
             next_tool: &mut *next_tool,
             editor_actions: &mut *editor_actions,
+            inspector: &mut *inspector,
+            inspected,
+            selected_count: selection.selected.len(),
+            tabs,
+            keymap: &*keymap,
+            selection: &*selection,
         };
 
-        panels.top_height = egui::TopBottomPanel::top("top_panel")
+        panels.menu_bar(ctx, &mut menus);
+        let resize = panels.resize_pending;
+        panels.resize_pending = false;
+
+        let mut top = egui::TopBottomPanel::top("top_panel")
             .resizable(true)
+            .default_height(panels.top_height);
+        if resize {
+            top = top.exact_height(panels.top_height);
+        }
+        panels.top_height = top
             .show(ctx, |ui| {
                 DockArea::new(&mut panels.top_tabs)
                     .id(egui::Id::new("egui_dock::DockArea::top"))
@@ -148,39 +415,67 @@ impl EditorPanels {
             .response
             .rect
             .height();
-        panels.left_width = egui::SidePanel::left("left_panel")
+
+        // Empty dock areas are hidden entirely so that presets like Playtest can give the viewports the whole window.
+        let mut left = egui::SidePanel::left("left_panel")
             .resizable(true)
-            .show(ctx, |ui| {
-                DockArea::new(&mut panels.left_tabs)
-                    .id(egui::Id::new("egui_dock::DockArea::left"))
-                    .show_inside(ui, &mut viewer);
-                ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
-            })
-            .response
-            .rect
-            .width();
-        panels.right_width = egui::SidePanel::right("right_panel")
+            .default_width(panels.left_width);
+        if resize {
+            left = left.exact_width(panels.left_width);
+        }
+        panels.left_width = if Self::is_empty(&panels.left_tabs) { 0.0 } else {
+            left
+                .show(ctx, |ui| {
+                    DockArea::new(&mut panels.left_tabs)
+                        .id(egui::Id::new("egui_dock::DockArea::left"))
+                        .show_inside(ui, &mut viewer);
+                    ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
+                })
+                .response
+                .rect
+                .width()
+        };
+
+        let mut right = egui::SidePanel::right("right_panel")
             .resizable(true)
-            .show(ctx, |ui| {
-                DockArea::new(&mut panels.right_tabs)
-                    .id(egui::Id::new("egui_dock::DockArea::right"))
-                    .show_inside(ui, &mut viewer);
-                ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
-            })
-            .response
-            .rect
-            .width();
-        panels.bottom_height = egui::TopBottomPanel::bottom("bottom_panel")
+            .default_width(panels.right_width);
+        if resize {
+            right = right.exact_width(panels.right_width);
+        }
+        panels.right_width = if Self::is_empty(&panels.right_tabs) { 0.0 } else {
+            right
+                .show(ctx, |ui| {
+                    DockArea::new(&mut panels.right_tabs)
+                        .id(egui::Id::new("egui_dock::DockArea::right"))
+                        .show_inside(ui, &mut viewer);
+                    ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
+                })
+                .response
+                .rect
+                .width()
+        };
+
+        let mut bottom = egui::TopBottomPanel::bottom("bottom_panel")
             .resizable(true)
-            .show(ctx, |ui| {
-                DockArea::new(&mut panels.bottom_tabs)
-                    .id(egui::Id::new("egui_dock::DockArea::bottom"))
-                    .show_inside(ui, &mut viewer);
-                //ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
-            })
-            .response
-            .rect
-            .height();
+            .default_height(panels.bottom_height);
+        if resize {
+            bottom = bottom.exact_height(panels.bottom_height);
+        }
+        panels.bottom_height = if Self::is_empty(&panels.bottom_tabs) { 0.0 } else {
+            bottom
+                .show(ctx, |ui| {
+                    DockArea::new(&mut panels.bottom_tabs)
+                        .id(egui::Id::new("egui_dock::DockArea::bottom"))
+                        .show_inside(ui, &mut viewer);
+                    //ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
+                })
+                .response
+                .rect
+                .height()
+        };
+
+        let inspected = viewer.inspected.take();
+        viewer.inspector.apply(inspected, &mut inspectables, &mut history);
 
         Self::set_multicam_size(panels, multicam_state, windows)
     }
@@ -194,7 +489,7 @@ impl EditorPanels {
 
         let left_taken = panels.left_width / window.width();
         let right_taken = panels.right_width / window.width();
-        let bottom_taken = panels.bottom_height / window.height();
+        let bottom_taken = (panels.bottom_height + panels.status_bar_height) / window.height();
         let top_taken = (panels.toolbar_height + panels.top_height) / window.height();
         // info!("[{} {}] -> [{}, {}]", left_taken, top_taken, 1.0 - right_taken, 1.0 - bottom_taken);
 
@@ -0,0 +1,169 @@
+use std::ops::RangeInclusive;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::common::config;
+use crate::editor::multicam::{MulticamLayout, MIN_SPLIT};
+
+pub const PREFERENCES_FILE: &str = "preferences.toml";
+
+pub struct PreferencesPlugin;
+
+impl Plugin for PreferencesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Preferences>()
+            .add_systems(PreStartup, Preferences::load)
+            .add_systems(Last, Preferences::save.run_if(on_event::<AppExit>))
+        ;
+    }
+}
+
+/// Per-user editor settings that aren't part of a map, saved to the config directory.
+#[derive(Resource, Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Preferences {
+    pub viewports: ViewportPreferences,
+    pub camera: CameraPreferences,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct ViewportPreferences {
+    pub layout: MulticamLayout,
+    /// Where the dividers sit, as a fraction of the viewport area.
+    pub split: [f32; 2],
+}
+
+impl Default for ViewportPreferences {
+    fn default() -> Self {
+        Self {
+            layout: MulticamLayout::Grid,
+            split: [0.5, 0.5],
+        }
+    }
+}
+
+impl ViewportPreferences {
+    /// Keeps the dividers where they could have been dragged to. Numbers that aren't numbers go back to the default.
+    fn validate(&mut self) {
+        let default = Self::default().split;
+        for (split, default) in self.split.iter_mut().zip(default) {
+            *split = if split.is_finite() { split.clamp(MIN_SPLIT, 1.0 - MIN_SPLIT) } else { default };
+        }
+    }
+}
+
+/// How the perspective camera looks and flies.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct CameraPreferences {
+    /// Vertical field of view, in degrees.
+    pub fov_degrees: f32,
+    pub near: f32,
+    pub far: f32,
+    /// Top flying speed, in units per second.
+    pub fly_speed: f32,
+    /// How quickly flying gets up to speed, in units per second per second.
+    pub fly_acceleration: f32,
+    /// Multiplies how far moving the mouse turns the camera.
+    pub mouse_sensitivity: f32,
+    pub invert_y: bool,
+}
+
+impl Default for CameraPreferences {
+    fn default() -> Self {
+        Self {
+            fov_degrees: 75.0,
+            near: 0.05,
+            far: 1000.0,
+            fly_speed: 12.0,
+            fly_acceleration: 40.0,
+            mouse_sensitivity: 1.0,
+            invert_y: false,
+        }
+    }
+}
+
+impl CameraPreferences {
+    pub const NEAR_RANGE: RangeInclusive<f32> = 0.001..=10.0;
+    const MAX_FAR: f32 = 100000.0;
+
+    /// Where the far plane can go. It stays well past the near plane, or the projection falls apart.
+    pub fn far_range(&self) -> RangeInclusive<f32> {
+        (self.near * 2.0).max(10.0)..=Self::MAX_FAR
+    }
+
+    /// Keeps the clip planes in the ranges the camera settings allow. Numbers that aren't numbers go back to the default.
+    pub fn validate(&mut self) {
+        let default = Self::default();
+        self.near = if self.near.is_finite() { self.near.clamp(*Self::NEAR_RANGE.start(), *Self::NEAR_RANGE.end()) } else { default.near };
+        let far = if self.far.is_finite() { self.far } else { default.far };
+        let far_range = self.far_range();
+        self.far = far.clamp(*far_range.start(), *far_range.end());
+    }
+
+    /// Mouse movement with sensitivity and inversion applied.
+    pub fn look_delta(&self, delta: Vec2) -> Vec2 {
+        let y = if self.invert_y { -delta.y } else { delta.y };
+        Vec2::new(delta.x, y) * self.mouse_sensitivity
+    }
+}
+
+impl Preferences {
+    /// Reads a preferences file, fixing up values that were edited out of range.
+    pub fn from_toml(contents: &str) -> Result<Self, String> {
+        let mut preferences = toml::from_str::<Self>(contents).map_err(|e| e.to_string())?;
+        preferences.viewports.validate();
+        preferences.camera.validate();
+        Ok(preferences)
+    }
+
+    fn load(mut preferences: ResMut<Self>) {
+        let contents = match config::read_config(PREFERENCES_FILE) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("{}", e);
+                return;
+            }
+        };
+        match Self::from_toml(&contents) {
+            Ok(loaded) => *preferences = loaded,
+            Err(e) => warn!("Ignoring preferences \"{}\": {}", PREFERENCES_FILE, e),
+        }
+    }
+
+    fn save(preferences: Res<Self>) {
+        let result = toml::to_string_pretty(&*preferences)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| config::write_config(PREFERENCES_FILE, &contents));
+        if let Err(e) = result {
+            warn!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits_are_kept_inside_the_viewports() {
+        let preferences = Preferences::from_toml("[viewports]\nsplit = [0.0, 1.5]").unwrap();
+        assert_eq!(preferences.viewports.split, [MIN_SPLIT, 1.0 - MIN_SPLIT]);
+
+        let preferences = Preferences::from_toml("[viewports]\nsplit = [nan, 0.3]").unwrap();
+        assert_eq!(preferences.viewports.split, [0.5, 0.3]);
+    }
+
+    #[test]
+    fn test_far_plane_stays_past_near_plane() {
+        let preferences = Preferences::from_toml("[camera]\nnear = 10.0\nfar = 10.0").unwrap();
+        assert_eq!(preferences.camera.near, 10.0);
+        assert_eq!(preferences.camera.far, 20.0);
+
+        let preferences = Preferences::from_toml("[camera]\nnear = nan\nfar = 0.0").unwrap();
+        assert_eq!(preferences.camera.near, CameraPreferences::default().near);
+        assert_eq!(preferences.camera.far, 10.0);
+    }
+}
@@ -0,0 +1,209 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::{Color32, RichText, Ui};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use crate::editor::editable::{EditorAction, EditorActionId, EditorActions};
+use crate::get;
+use crate::tool::room::Room;
+use crate::tool::selection::{FrameBounds, FrameSelection, SelectionMode, SelectionState};
+
+#[derive(EnumIter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    fn name(&self) -> String {
+        match self {
+            Severity::Error => get!("problems.error"),
+            Severity::Warning => get!("problems.warning"),
+            Severity::Info => get!("problems.info"),
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Severity::Error => "⊗",
+            Severity::Warning => "⚠",
+            Severity::Info => "ℹ",
+        }
+    }
+
+    fn color(&self, ui: &Ui) -> Color32 {
+        match self {
+            Severity::Error => Color32::from_rgb(230, 80, 80),
+            Severity::Warning => Color32::from_rgb(230, 200, 60),
+            Severity::Info => ui.visuals().text_color(),
+        }
+    }
+}
+
+/// What a problem is about, so that clicking it can take you there.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProblemSource {
+    Entity(Entity),
+    Action(EditorActionId),
+    None,
+}
+
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+    pub source: ProblemSource,
+}
+
+impl Problem {
+    pub fn new(severity: Severity, message: String, source: ProblemSource) -> Self {
+        Self { severity, message, source }
+    }
+}
+
+/// Diagnostics about the map. Problems that can be worked out from the map as it is are rebuilt whenever it changes;
+/// bakes report theirs through `set_bake_problems`, and those stay until the next bake.
+#[derive(Resource)]
+pub struct Problems {
+    map: Vec<Problem>,
+    bake: Vec<Problem>,
+    /// Whether each severity is shown, in `Severity::iter()` order.
+    shown: [bool; 3],
+    clicked: Option<ProblemSource>,
+}
+
+impl Default for Problems {
+    fn default() -> Self {
+        Self {
+            map: vec![],
+            bake: vec![],
+            shown: [true; 3],
+            clicked: None,
+        }
+    }
+}
+
+impl Problems {
+    pub fn set_bake_problems(&mut self, problems: Vec<Problem>) {
+        self.bake = problems;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Problem> {
+        self.map.iter().chain(self.bake.iter())
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.iter().filter(|problem| problem.severity == severity).count()
+    }
+
+    pub fn refresh(
+        mut problems: ResMut<Self>,
+        rooms: Query<(Entity, Ref<Room>)>,
+        mut removed_rooms: RemovedComponents<Room>,
+        actions: Res<EditorActions>,
+    ) {
+        let removed = removed_rooms.read().count() > 0;
+        if !removed && !actions.is_changed() && !rooms.iter().any(|(_, room)| room.is_changed()) {
+            return;
+        }
+
+        let mut map = Vec::new();
+        for (entity, room) in &rooms {
+            map.extend(room.problems(entity));
+        }
+        for (id, error) in actions.diagnose() {
+            let name = actions.get_action(&id).map(|action| action.type_name_with_id()).unwrap_or_default();
+            map.push(Problem::new(
+                Severity::Error,
+                get!("problems.action", "action", name, "error", error),
+                ProblemSource::Action(id),
+            ));
+        }
+        map.sort_by_key(|problem| problem.severity as u8);
+        problems.map = map;
+    }
+
+    pub fn ui(ui: &mut Ui, problems: &mut Self) {
+        ui.horizontal(|ui| {
+            for (index, severity) in Severity::iter().enumerate() {
+                let label = RichText::new(format!("{} {} ({})", severity.icon(), severity.name(), problems.count(severity)))
+                    .color(severity.color(ui));
+                if ui.selectable_label(problems.shown[index], label).clicked() {
+                    problems.shown[index] = !problems.shown[index];
+                }
+            }
+        });
+        ui.separator();
+
+        let mut clicked = None;
+        egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            let mut any = false;
+            for (index, severity) in Severity::iter().enumerate() {
+                if !problems.shown[index] {
+                    continue;
+                }
+                for problem in problems.iter().filter(|problem| problem.severity == severity) {
+                    any = true;
+                    let text = RichText::new(format!("{} {}", severity.icon(), problem.message)).color(severity.color(ui));
+                    let response = ui.selectable_label(false, text);
+                    if response.clicked() && problem.source != ProblemSource::None {
+                        clicked = Some(problem.source);
+                    }
+                }
+            }
+            if !any {
+                ui.weak(get!("problems.none"));
+            }
+        });
+        if clicked.is_some() {
+            problems.clicked = clicked;
+        }
+    }
+
+    /// Selects and frames whatever was clicked in the panel.
+    pub fn apply(
+        mut problems: ResMut<Self>,
+        mut selection: ResMut<SelectionState>,
+        mut actions: ResMut<EditorActions>,
+        mut frame: EventWriter<FrameSelection>,
+        mut frame_bounds: EventWriter<FrameBounds>,
+    ) {
+        match problems.clicked.take() {
+            Some(ProblemSource::Entity(entity)) => {
+                selection.apply(SelectionMode::Replace, [entity]);
+                frame.write(FrameSelection);
+            }
+            Some(ProblemSource::Action(id)) => {
+                actions.select(Some(id));
+                if let Some((min, max)) = actions.get_action(&id).and_then(EditorAction::bounds) {
+                    frame_bounds.write(FrameBounds { min, max });
+                }
+            }
+            Some(ProblemSource::None) | None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use super::*;
+
+    #[test]
+    fn test_clicked_actions_are_selected_and_framed() {
+        let mut world = World::new();
+        world.init_resource::<Problems>();
+        world.init_resource::<SelectionState>();
+        world.init_resource::<EditorActions>();
+        world.init_resource::<Events<FrameSelection>>();
+        world.init_resource::<Events<FrameBounds>>();
+        let point = world.resource::<EditorActions>().iter().nth(2).unwrap().id();
+        world.resource_mut::<Problems>().clicked = Some(ProblemSource::Action(point));
+
+        world.run_system_once(Problems::apply).unwrap();
+        assert_eq!(world.resource::<EditorActions>().selected(), Some(point));
+        let framed: Vec<FrameBounds> = world.resource_mut::<Events<FrameBounds>>().drain().collect();
+        assert_eq!(framed.len(), 1);
+        assert_eq!((framed[0].min, framed[0].max), (Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, 5.0, 0.0)));
+    }
+}
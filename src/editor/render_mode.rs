@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::mesh::{PrimitiveTopology, VertexAttributeValues};
+use bevy::render::view::RenderLayers;
+use strum_macros::EnumIter;
+use crate::editor::grid::ViewportGrid;
+use crate::editor::layers::LayerTint;
+use crate::editor::multicam::{CameraAxis, Multicam};
+use crate::get;
+use crate::tool::selection::EditorSelectable;
+
+// Each look of the map gets its own render layer, and cameras see the layers their render mode calls for.
+// Everything else in the editor stays on layer 0, which every camera sees.
+const SHADED_LAYER: usize = 24;
+const UNLIT_LAYER: usize = 25;
+const WIREFRAME_LAYER: usize = 26;
+const NORMALS_LAYER: usize = 27;
+
+const WIREFRAME_COLOR: Color = Color::srgb(0.85, 0.85, 0.85);
+/// Normals pointing the other way from the face's winding, which is what culling and lighting go by.
+const FLIPPED_COLOR: Color = Color::srgb_u8(255, 40, 40);
+/// Normal arrows are this fraction of the size of their face, up to `MAX_NORMAL_LENGTH`.
+const NORMAL_LENGTH: f32 = 0.3;
+const MAX_NORMAL_LENGTH: f32 = 1.0;
+
+pub struct RenderModePlugin;
+
+impl Plugin for RenderModePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_gizmo_group::<WireframeGizmos>()
+            .init_gizmo_group::<NormalGizmos>()
+            .init_resource::<OutlineCache>()
+            .add_systems(Startup, Self::configure_gizmos)
+            .add_systems(Update, (
+                Self::camera_layers,
+                Self::mesh_layers,
+                Self::light_layers,
+                OutlineCache::forget_changed,
+                Self::draw_wireframes.after(OutlineCache::forget_changed),
+                Self::draw_normals,
+            ))
+        ;
+    }
+}
+
+/// How a viewport draws the map.
+#[derive(EnumIter, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RenderMode {
+    #[default]
+    Shaded,
+    Unlit,
+    Wireframe,
+    WireframeOnShaded,
+    Normals,
+}
+
+impl RenderMode {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Shaded => get!("viewport.render_modes.shaded"),
+            Self::Unlit => get!("viewport.render_modes.unlit"),
+            Self::Wireframe => get!("viewport.render_modes.wireframe"),
+            Self::WireframeOnShaded => get!("viewport.render_modes.wireframe_on_shaded"),
+            Self::Normals => get!("viewport.render_modes.normals"),
+        }
+    }
+
+    /// Flat views start out as wireframes, like in classic map editors.
+    pub fn default_for(axis: CameraAxis) -> Self {
+        match axis {
+            CameraAxis::None => Self::Shaded,
+            _ => Self::Wireframe,
+        }
+    }
+
+    fn layers(&self) -> &'static [usize] {
+        match self {
+            Self::Shaded => &[SHADED_LAYER],
+            Self::Unlit => &[UNLIT_LAYER],
+            Self::Wireframe => &[WIREFRAME_LAYER],
+            Self::WireframeOnShaded => &[SHADED_LAYER, WIREFRAME_LAYER],
+            Self::Normals => &[UNLIT_LAYER, NORMALS_LAYER],
+        }
+    }
+
+    fn shows(&self, layer: usize) -> bool {
+        self.layers().contains(&layer)
+    }
+}
+
+/// Map meshes that are drawn differently depending on the render mode. They get an unlit copy as a child.
+#[derive(Component)]
+pub struct RenderModeMesh;
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct WireframeGizmos;
+
+/// The outline edges of each map mesh, in the mesh's own space, so wireframes don't have to be worked out every frame.
+#[derive(Resource, Default)]
+struct OutlineCache {
+    edges: HashMap<AssetId<Mesh>, Vec<(Vec3, Vec3)>>,
+}
+
+impl OutlineCache {
+    /// The outline of a mesh, worked out the first time it's asked for. `None` until the mesh has loaded.
+    fn edges(&mut self, mesh: AssetId<Mesh>, meshes: &Assets<Mesh>) -> Option<&[(Vec3, Vec3)]> {
+        if !self.edges.contains_key(&mesh) {
+            let edges = triangles(meshes.get(mesh)?).map_or_else(Vec::new, |triangles| outline_edges(&triangles));
+            self.edges.insert(mesh, edges);
+        }
+        self.edges.get(&mesh).map(Vec::as_slice)
+    }
+
+    /// Drops the outlines of meshes that were edited or removed, so they're worked out again when next drawn.
+    fn forget_changed(mut cache: ResMut<Self>, mut mesh_events: EventReader<AssetEvent<Mesh>>) {
+        for event in mesh_events.read() {
+            match event {
+                AssetEvent::Modified { id } | AssetEvent::Removed { id } | AssetEvent::Unused { id } => {
+                    cache.edges.remove(id);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct NormalGizmos;
+
+impl RenderModePlugin {
+    fn configure_gizmos(mut store: ResMut<GizmoConfigStore>) {
+        let (config, _) = store.config_mut::<WireframeGizmos>();
+        config.render_layers = RenderLayers::layer(WIREFRAME_LAYER);
+        // Pulled towards the camera a little, so wireframes over shaded faces don't flicker.
+        config.depth_bias = -0.01;
+        let (config, _) = store.config_mut::<NormalGizmos>();
+        config.render_layers = RenderLayers::layer(NORMALS_LAYER);
+    }
+
+    fn camera_layers(mut commands: Commands, cameras: Query<(Entity, &Multicam), Changed<Multicam>>) {
+        for (entity, multicam) in &cameras {
+            let mut layers = vec![0, ViewportGrid::layer(multicam)];
+            layers.extend_from_slice(multicam.render_mode.layers());
+            commands.entity(entity).insert(RenderLayers::from_layers(&layers));
+        }
+    }
+
+    /// Moves new map meshes onto the shaded layer and gives them an unlit copy.
+    fn mesh_layers(
+        mut commands: Commands,
+        meshes: Query<(Entity, &Mesh3d, &MeshMaterial3d<StandardMaterial>), (With<EditorSelectable>, Without<RenderModeMesh>)>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+    ) {
+        for (entity, mesh, material) in &meshes {
+            let base_color = materials.get(&material.0).map_or(Color::WHITE, |material| material.base_color);
+            let unlit = materials.add(StandardMaterial {
+                base_color,
+                unlit: true,
+                ..default()
+            });
+            commands
+                .entity(entity)
+                .insert((RenderModeMesh, RenderLayers::layer(SHADED_LAYER)))
+                .with_child((
+                    Mesh3d(mesh.0.clone()),
+                    MeshMaterial3d(unlit),
+                    RenderLayers::layer(UNLIT_LAYER),
+                    NotShadowCaster,
+                ));
+        }
+    }
+
+    /// Lights only light meshes on their own layers, so they need the shaded layer as well as layer 0.
+    fn light_layers(
+        mut commands: Commands,
+        lights: Query<Entity, (Or<(With<PointLight>, With<SpotLight>, With<DirectionalLight>)>, Without<RenderLayers>)>,
+    ) {
+        for light in &lights {
+            commands.entity(light).insert(RenderLayers::from_layers(&[0, SHADED_LAYER]));
+        }
+    }
+
+    fn draw_wireframes(
+        cameras: Query<(&Camera, &Multicam)>,
+        map: Query<(&Mesh3d, &GlobalTransform, &InheritedVisibility, Option<&LayerTint>), With<RenderModeMesh>>,
+        meshes: Res<Assets<Mesh>>,
+        mut cache: ResMut<OutlineCache>,
+        mut gizmos: Gizmos<WireframeGizmos>,
+    ) {
+        if !cameras.iter().any(|(camera, multicam)| camera.is_active && multicam.render_mode.shows(WIREFRAME_LAYER)) {
+            return;
+        }
+        for (mesh, tfm, visibility, tint) in &map {
+            if !visibility.get() {
+                continue;
+            }
+            let Some(edges) = cache.edges(mesh.0.id(), &meshes) else {
+                continue;
+            };
+            // Wireframes take on their layer's tint outright, so layers can be told apart in flat views.
+            let color = tint.filter(|tint| tint.is_tinted()).map_or(WIREFRAME_COLOR, |tint| tint.0);
+            for (a, b) in edges {
+                gizmos.line(tfm.transform_point(*a), tfm.transform_point(*b), color);
+            }
+        }
+    }
+
+    fn draw_normals(
+        cameras: Query<(&Camera, &Multicam)>,
+        map: Query<(&Mesh3d, &GlobalTransform, &InheritedVisibility), With<RenderModeMesh>>,
+        meshes: Res<Assets<Mesh>>,
+        mut gizmos: Gizmos<NormalGizmos>,
+    ) {
+        if !cameras.iter().any(|(camera, multicam)| camera.is_active && multicam.render_mode.shows(NORMALS_LAYER)) {
+            return;
+        }
+        for (mesh, tfm, visibility) in &map {
+            if !visibility.get() {
+                continue;
+            }
+            let Some(triangles) = meshes.get(&mesh.0).and_then(triangles) else {
+                continue;
+            };
+            for triangle in triangles {
+                let normal = triangle.normal();
+                if normal == Vec3::ZERO {
+                    continue;
+                }
+                let color = if triangle.flipped() {
+                    FLIPPED_COLOR
+                } else {
+                    let rgb = normal * 0.5 + 0.5;
+                    Color::srgb(rgb.x, rgb.y, rgb.z)
+                };
+                let length = (triangle.area().sqrt() * NORMAL_LENGTH).min(MAX_NORMAL_LENGTH);
+                let start = tfm.transform_point(triangle.center());
+                let end = tfm.transform_point(triangle.center() + normal * length);
+                gizmos.arrow(start, end, color);
+            }
+        }
+    }
+}
+
+/// One triangle of a mesh, in the mesh's own space.
+#[derive(Clone, Copy, Debug)]
+struct MeshTriangle {
+    points: [Vec3; 3],
+    /// The average of the normals stored on its vertices, if the mesh has any.
+    vertex_normal: Option<Vec3>,
+}
+
+impl MeshTriangle {
+    /// The normal its winding gives it, counter-clockwise being the front.
+    fn normal(&self) -> Vec3 {
+        let [a, b, c] = self.points;
+        (b - a).cross(c - a).normalize_or_zero()
+    }
+
+    fn area(&self) -> f32 {
+        let [a, b, c] = self.points;
+        (b - a).cross(c - a).length() / 2.0
+    }
+
+    fn center(&self) -> Vec3 {
+        self.points.iter().sum::<Vec3>() / 3.0
+    }
+
+    /// Whether the stored normals point away from the side the winding says is the front.
+    fn flipped(&self) -> bool {
+        self.vertex_normal.is_some_and(|normal| normal.dot(self.normal()) < 0.0)
+    }
+}
+
+/// The triangles of a triangle list mesh, or `None` for any other kind of mesh.
+fn triangles(mesh: &Mesh) -> Option<Vec<MeshTriangle>> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return None;
+    };
+    let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals)) => Some(normals),
+        _ => None,
+    };
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+    let triangles = indices
+        .chunks_exact(3)
+        .filter(|corners| corners.iter().all(|&index| index < positions.len()))
+        .map(|corners| MeshTriangle {
+            points: [0, 1, 2].map(|i| Vec3::from(positions[corners[i]])),
+            vertex_normal: normals.map(|normals| {
+                corners.iter().filter_map(|&index| normals.get(index)).map(|normal| Vec3::from(*normal)).sum::<Vec3>()
+            }),
+        })
+        .collect();
+    Some(triangles)
+}
+
+/// The edges a wireframe draws. Edges between two triangles in the same plane, like the diagonal across
+/// a quad, are left out so that boxes look like boxes.
+fn outline_edges(triangles: &[MeshTriangle]) -> Vec<(Vec3, Vec3)> {
+    // Vertices are often repeated for each face, so edges are matched up by position.
+    let key = |point: Vec3| (point * 1e4).round().as_ivec3().to_array();
+    let mut edges: HashMap<([i32; 3], [i32; 3]), (Vec3, Vec3, Vec<Vec3>)> = HashMap::new();
+    let mut order = Vec::new();
+    for triangle in triangles {
+        let [a, b, c] = triangle.points;
+        for (from, to) in [(a, b), (b, c), (c, a)] {
+            let (from_key, to_key) = (key(from), key(to));
+            let edge_key = if from_key <= to_key { (from_key, to_key) } else { (to_key, from_key) };
+            edges
+                .entry(edge_key)
+                .or_insert_with(|| {
+                    order.push(edge_key);
+                    (from, to, Vec::new())
+                })
+                .2
+                .push(triangle.normal());
+        }
+    }
+    order
+        .into_iter()
+        .filter_map(|edge_key| {
+            let (from, to, normals) = &edges[&edge_key];
+            let flat = normals.len() == 2 && normals[0].dot(normals[1]) > 0.999;
+            (!flat).then_some((*from, *to))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boxes_have_twelve_edges() {
+        let triangles = triangles(&Mesh::from(Cuboid::new(1.0, 2.0, 3.0))).unwrap();
+        assert_eq!(triangles.len(), 12);
+        assert_eq!(outline_edges(&triangles).len(), 12);
+        assert!(triangles.iter().all(|triangle| !triangle.flipped()));
+    }
+
+    #[test]
+    fn test_flipped_normals() {
+        let triangle = MeshTriangle {
+            points: [Vec3::ZERO, Vec3::X, Vec3::Y],
+            vertex_normal: Some(Vec3::NEG_Z),
+        };
+        assert_eq!(triangle.normal(), Vec3::Z);
+        assert!(triangle.flipped());
+        assert!(!MeshTriangle { vertex_normal: None, ..triangle }.flipped());
+    }
+
+    #[test]
+    fn test_edited_meshes_get_new_outlines() {
+        let mut app = App::new();
+        app
+            .add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_resource::<OutlineCache>()
+            .add_systems(Update, OutlineCache::forget_changed)
+        ;
+        let handle = app.world_mut().resource_mut::<Assets<Mesh>>().add(Cuboid::new(1.0, 1.0, 1.0));
+        app.update();
+
+        let outline = |app: &mut App| {
+            let world = app.world_mut();
+            world.resource_scope(|world, mut cache: Mut<OutlineCache>| {
+                cache.edges(handle.id(), world.resource::<Assets<Mesh>>()).unwrap().to_vec()
+            })
+        };
+        let before = outline(&mut app);
+        assert!(before.iter().all(|(a, b)| a.abs().max_element() <= 0.5 && b.abs().max_element() <= 0.5));
+
+        *app.world_mut().resource_mut::<Assets<Mesh>>().get_mut(&handle).unwrap() = Mesh::from(Cuboid::new(4.0, 4.0, 4.0));
+        // Asset events go out at the end of the frame, so they're read the frame after.
+        app.update();
+        app.update();
+        let after = outline(&mut app);
+        assert_eq!(after.len(), 12);
+        assert!(after.iter().any(|(a, _)| a.abs().max_element() == 2.0));
+    }
+}
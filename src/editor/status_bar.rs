@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContextPass, EguiContexts};
+use crate::editor::grid::grid_spacing;
+use crate::editor::input::CurrentMouseInput;
+use crate::editor::multicam::{CameraAxis, Multicam};
+use crate::editor::panels::EditorPanels;
+use crate::get;
+use crate::tool::selection::{EditorSelectable, SelectionState};
+use crate::tool::snap::SnapSettings;
+
+pub struct StatusBarPlugin;
+
+impl Plugin for StatusBarPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(EguiContextPass, Self::ui)
+        ;
+    }
+}
+
+impl StatusBarPlugin {
+    pub fn ui(
+        mut contexts: EguiContexts,
+        mut panels: ResMut<EditorPanels>,
+        mouse_input: Res<CurrentMouseInput>,
+        cameras: Query<(&Multicam, &Projection)>,
+        snap: Res<SnapSettings>,
+        selection: Res<SelectionState>,
+        selectables: Query<(Entity, &Transform, &EditorSelectable)>,
+    ) {
+        let Some(ctx) = contexts.try_ctx_mut() else {
+            return;
+        };
+        let hovered = mouse_input.in_camera.and_then(|camera| cameras.get(camera).ok());
+
+        let cursor = match (hovered, mouse_input.world_pos) {
+            (Some((multicam, Projection::Orthographic(_))), Some(ray)) if multicam.axis != CameraAxis::None =>
+                get!("status_bar.cursor", "view", multicam.axis.name(), "coordinates", coordinates(multicam.axis, ray.origin)),
+            _ => get!("status_bar.no_cursor"),
+        };
+        let grid = match hovered {
+            Some((_, Projection::Orthographic(ortho))) => get!("status_bar.grid_view",
+                "size", snap.granularity,
+                "spacing", grid_spacing(snap.granularity, ortho.scale)),
+            _ => get!("status_bar.grid", "size", snap.granularity),
+        };
+        let size = selection.bounds(&selectables).map(|(min, max)| max - min);
+        let selected = match size {
+            Some(size) => get!("status_bar.selection",
+                "x", format!("{:.3}", size.x),
+                "y", format!("{:.3}", size.y),
+                "z", format!("{:.3}", size.z)),
+            None => get!("status_bar.nothing_selected"),
+        };
+
+        let height = egui::TopBottomPanel::bottom("status_bar")
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.monospace(cursor);
+                    ui.separator();
+                    ui.label(grid);
+                    ui.separator();
+                    ui.label(selected);
+                });
+            })
+            .response
+            .rect
+            .height();
+        panels.set_status_bar_height(height);
+    }
+}
+
+/// The cursor's position along the two world axes a flat view shows. Its depth isn't known, so it's left out.
+fn coordinates(axis: CameraAxis, point: Vec3) -> String {
+    ["X", "Y", "Z"]
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| Some(*index) != axis.index())
+        .map(|(index, name)| format!("{} {:>8.3}", name, point[index]))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coordinates_leave_out_depth() {
+        let point = Vec3::new(1.0, -2.5, 3.25);
+        assert_eq!(coordinates(CameraAxis::Y, point), "X    1.000  Z    3.250");
+        assert_eq!(coordinates(CameraAxis::NegX, point), "Y   -2.500  Z    3.250");
+    }
+}
@@ -3,16 +3,27 @@ mod startup;
 mod editor;
 mod tool;
 
+use bevy::log::LogPlugin;
 use bevy::prelude::*;
 use bevy::window::{ExitCondition, PresentMode};
 use bevy_egui::{EguiPlugin};
 use bevy_vector_shapes::prelude::*;
 use crate::common::lang::change_lang;
 use crate::common::perf::PerfPlugin;
+use crate::editor::console::{self, ConsolePlugin};
+use crate::editor::bookmarks::CameraBookmarksPlugin;
 use crate::editor::editable::EditorStepsPlugin;
+use crate::editor::grid::GridPlugin;
 use crate::editor::input::EditorInputPlugin;
+use crate::editor::keymap::KeymapPlugin;
+use crate::editor::layers::LayersPlugin;
+use crate::editor::map::MapPlugin;
 use crate::editor::multicam::MulticamPlugin;
+use crate::editor::palette::CommandPalettePlugin;
 use crate::editor::panels::EditorPanelPlugin;
+use crate::editor::preferences::PreferencesPlugin;
+use crate::editor::render_mode::RenderModePlugin;
+use crate::editor::status_bar::StatusBarPlugin;
 use crate::tool::ToolPlugin;
 
 
@@ -41,6 +52,10 @@ fn main() {
                }),
                 exit_condition: ExitCondition::OnPrimaryClosed,
                 close_when_requested: true,
+            })
+            .set(LogPlugin {
+                custom_layer: console::log_layer,
+                ..default()
             }),
         )
         .add_plugins((
@@ -49,11 +64,24 @@ fn main() {
         ))
         .add_plugins((
             EditorInputPlugin,
+            KeymapPlugin,
+            PreferencesPlugin,
+            GridPlugin,
+            RenderModePlugin,
             MulticamPlugin {
                 test_scene: true,
             },
             EditorPanelPlugin,
+            StatusBarPlugin,
+            ConsolePlugin,
+            CommandPalettePlugin,
             EditorStepsPlugin,
+        ))
+        // Bevy only takes so many plugins at once.
+        .add_plugins((
+            MapPlugin,
+            LayersPlugin,
+            CameraBookmarksPlugin,
             ToolPlugin,
             PerfPlugin,
             ))
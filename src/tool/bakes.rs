@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContextPass, EguiContexts};
+use crate::editor::console::{ConsoleCommand, ConsoleCommandsExt};
+use crate::editor::palette::{Operation, OperationsExt};
 use crate::get;
 use crate::tool::room::CalculateRoomGeometry;
 
@@ -10,11 +12,18 @@ impl Plugin for BakePlugin {
         app
             .add_systems(EguiContextPass, Self::bake_ui)
             .add_event::<CalculateRoomGeometry>()
+            .add_console_command("bake", ConsoleCommand::new("console.commands.bake", Self::bake_command))
+            .add_operation(Operation::command("palette.operations.bake", "bake"))
         ;
     }
 }
 
 impl BakePlugin {
+    fn bake_command(world: &mut World, _args: &[&str]) -> Result<String, String> {
+        world.send_event(CalculateRoomGeometry);
+        Ok(get!("console.baking"))
+    }
+
     fn bake_ui(
         mut contexts: EguiContexts,
         mut room_events: EventWriter<CalculateRoomGeometry>
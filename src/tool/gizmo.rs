@@ -0,0 +1,592 @@
+use bevy::prelude::*;
+use bevy_egui::egui::Ui;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+use crate::common::painter;
+use crate::editor::input::CurrentMouseInput;
+use crate::editor::multicam::{CameraAxis, Multicam};
+use crate::get;
+use crate::tool::history::{Change, Edit, EditHistory};
+use crate::tool::playtest::Playtest;
+use crate::tool::selection::{EditorSelectable, Locked, SelectionState};
+use crate::tool::snap::{SnapSettings, Snapper};
+use crate::tool::Tools;
+
+/// Handles are drawn this many pixels long in the viewport the cursor is in.
+const HANDLE_PIXELS: f32 = 80.0;
+/// How close (in pixels) the cursor has to be to a handle to grab it.
+const PICK_PIXELS: f32 = 8.0;
+const CENTER_PICK_PIXELS: f32 = 12.0;
+const CIRCLE_SEGMENTS: usize = 48;
+const MIN_SCALE: f32 = 0.01;
+
+pub struct MovePlugin;
+
+impl Plugin for MovePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<MoveTool>()
+            .add_systems(Update, (
+                MoveTool::interface,
+                MoveTool::draw_handles.after(MoveTool::interface),
+            ).run_if(in_state(Tools::Move)).run_if(Playtest::inactive))
+            .add_systems(OnExit(Tools::Move), MoveTool::cancel_drag)
+        ;
+    }
+}
+
+#[derive(EnumIter, Display, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Translate => get!("move.translate"),
+            Self::Rotate => get!("move.rotate"),
+            Self::Scale => get!("move.scale"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    pub fn direction(&self) -> Vec3 {
+        match self {
+            GizmoAxis::X => Vec3::X,
+            GizmoAxis::Y => Vec3::Y,
+            GizmoAxis::Z => Vec3::Z,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            GizmoAxis::X => Color::srgb_u8(230, 60, 60),
+            GizmoAxis::Y => Color::srgb_u8(60, 230, 60),
+            GizmoAxis::Z => Color::srgb_u8(60, 60, 230),
+        }
+    }
+
+    fn from_camera_axis(axis: CameraAxis) -> Option<Self> {
+        match axis.index()? {
+            0 => Some(GizmoAxis::X),
+            1 => Some(GizmoAxis::Y),
+            _ => Some(GizmoAxis::Z),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoHandle {
+    Axis(GizmoAxis),
+    Center,
+}
+
+/// What the cursor is allowed to move along while dragging a handle.
+#[derive(Debug, Clone, Copy)]
+enum Constraint {
+    Axis(Vec3),
+    Plane(Vec3),
+}
+
+struct GizmoDrag {
+    mode: GizmoMode,
+    handle: GizmoHandle,
+    camera: Entity,
+    constraint: Constraint,
+    /// Perspective drags on the center handle scale uniformly instead of in the view plane.
+    uniform: bool,
+    pivot: Vec3,
+    extent: Vec3,
+    start: Vec3,
+    originals: Vec<(Entity, Transform)>,
+}
+
+#[derive(Resource)]
+pub struct MoveTool {
+    pub mode: GizmoMode,
+    hovered: Option<GizmoHandle>,
+    drag: Option<GizmoDrag>,
+    pivot: Option<Vec3>,
+    handle_length: f32,
+}
+
+impl Default for MoveTool {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            hovered: None,
+            drag: None,
+            pivot: None,
+            handle_length: 1.0,
+        }
+    }
+}
+
+impl MoveTool {
+    fn interface(
+        mut tool: ResMut<Self>,
+        mut history: ResMut<EditHistory>,
+        mut snapper: Snapper,
+        selection: Res<SelectionState>,
+        mouse_input: Res<CurrentMouseInput>,
+        cameras: Query<(&Camera, &GlobalTransform, &Multicam)>,
+        mut selectables: Query<(Entity, &mut Transform, &EditorSelectable), Without<Locked>>,
+    ) {
+        if tool.drag.is_some() {
+            if mouse_input.pressed == Some(MouseButton::Left) {
+                tool.update_drag(&mut snapper, &mouse_input, &cameras, &mut selectables);
+            } else {
+                tool.finish_drag(&mut history, &selectables);
+            }
+            return;
+        }
+
+        // Work out where the gizmo goes from the bounds of everything selected.
+        let bounds = selection.bounds(&selectables);
+        let Some((min, max)) = bounds else {
+            tool.pivot = None;
+            tool.hovered = None;
+            return;
+        };
+        let pivot = (min + max) / 2.0;
+        tool.pivot = Some(pivot);
+
+        let Some(camera_entity) = mouse_input.in_camera else {
+            tool.hovered = None;
+            return;
+        };
+        let Ok((camera, camera_tfm, multicam)) = cameras.get(camera_entity) else {
+            tool.hovered = None;
+            return;
+        };
+
+        if let Some(world_per_pixel) = world_per_pixel(camera, camera_tfm, multicam, pivot) {
+            tool.handle_length = HANDLE_PIXELS * world_per_pixel;
+        }
+
+        tool.hovered = match mouse_input.global_pos {
+            Some(cursor) => tool.pick(camera, camera_tfm, multicam, pivot, cursor),
+            None => None,
+        };
+
+        if mouse_input.pressed == Some(MouseButton::Left) && mouse_input.just_pressed {
+            if let (Some(handle), Some(ray)) = (tool.hovered, mouse_input.world_pos) {
+                let flat = multicam.axis != CameraAxis::None;
+                let (constraint, uniform) = Self::constraint(tool.mode, handle, camera_tfm.forward().as_vec3(), flat);
+                if let Some(start) = constrained_point(ray, pivot, constraint) {
+                    let originals = selectables
+                        .iter()
+                        .filter(|(entity, _, _)| selection.is_selected(*entity))
+                        .map(|(entity, transform, _)| (entity, *transform))
+                        .collect();
+                    tool.drag = Some(GizmoDrag {
+                        mode: tool.mode,
+                        handle,
+                        camera: camera_entity,
+                        constraint,
+                        uniform,
+                        pivot,
+                        extent: max - min,
+                        start,
+                        originals,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Axis handles drag along their axis, and the center handle drags in the view plane.
+    /// In `flat` (orthographic) views, rotation is always around the view direction,
+    /// and axes pointing into the screen fall back to the view plane.
+    fn constraint(mode: GizmoMode, handle: GizmoHandle, view_normal: Vec3, flat: bool) -> (Constraint, bool) {
+        match (mode, handle) {
+            (GizmoMode::Rotate, _) if flat => (Constraint::Plane(view_normal), false),
+            (GizmoMode::Rotate, GizmoHandle::Axis(axis)) => (Constraint::Plane(axis.direction()), false),
+            (_, GizmoHandle::Axis(axis)) if !flat || axis.direction().dot(view_normal).abs() < 0.5 => {
+                (Constraint::Axis(axis.direction()), false)
+            }
+            (GizmoMode::Scale, GizmoHandle::Center) if !flat => (Constraint::Plane(view_normal), true),
+            _ => (Constraint::Plane(view_normal), false),
+        }
+    }
+
+    fn pick(&self, camera: &Camera, camera_tfm: &GlobalTransform, multicam: &Multicam, pivot: Vec3, cursor: Vec2) -> Option<GizmoHandle> {
+        let center = project(camera, camera_tfm, multicam, pivot)?;
+        if self.mode != GizmoMode::Rotate && center.distance(cursor) <= CENTER_PICK_PIXELS {
+            return Some(GizmoHandle::Center);
+        }
+
+        let mut best: Option<(f32, GizmoHandle)> = None;
+        for axis in GizmoAxis::ALL {
+            let distance = match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    let Some(end) = project(camera, camera_tfm, multicam, pivot + axis.direction() * self.handle_length) else { continue; };
+                    // Skip axes that point straight into the screen.
+                    if center.distance(end) < PICK_PIXELS * 2.0 {
+                        continue;
+                    }
+                    distance_to_segment(cursor, center, end)
+                }
+                GizmoMode::Rotate => {
+                    // The flat views can only rotate around the axis they look down.
+                    if let Some(view_axis) = GizmoAxis::from_camera_axis(multicam.axis) {
+                        if view_axis != axis {
+                            continue;
+                        }
+                    }
+                    let points: Vec<Vec2> = circle_points(pivot, axis.direction(), self.handle_length)
+                        .into_iter()
+                        .filter_map(|point| project(camera, camera_tfm, multicam, point))
+                        .collect();
+                    points
+                        .windows(2)
+                        .map(|pair| distance_to_segment(cursor, pair[0], pair[1]))
+                        .fold(f32::INFINITY, f32::min)
+                }
+            };
+            if distance <= PICK_PIXELS && best.is_none_or(|(best_distance, _)| distance < best_distance) {
+                best = Some((distance, GizmoHandle::Axis(axis)));
+            }
+        }
+        best.map(|(_, handle)| handle)
+    }
+
+    fn update_drag(
+        &mut self,
+        snapper: &mut Snapper,
+        mouse_input: &CurrentMouseInput,
+        cameras: &Query<(&Camera, &GlobalTransform, &Multicam)>,
+        selectables: &mut Query<(Entity, &mut Transform, &EditorSelectable), Without<Locked>>,
+    ) {
+        let Some(drag) = &self.drag else { return; };
+        if mouse_input.in_camera != Some(drag.camera) {
+            return;
+        }
+        let Some(ray) = mouse_input.world_pos else { return; };
+        let Some(current) = constrained_point(ray, drag.pivot, drag.constraint) else { return; };
+
+        let pivot = drag.pivot;
+        let apply: Box<dyn Fn(&Transform) -> Transform> = match drag.mode {
+            GizmoMode::Translate => {
+                // Snap where the pivot lands, without snapping to the things being moved.
+                let depth = cameras.get(drag.camera).ok().and_then(|(_, _, multicam)| multicam.axis.index());
+                let tolerance = self.handle_length / HANDLE_PIXELS * snapper.settings().radius_pixels;
+                let moving: Vec<Entity> = drag.originals.iter().map(|(entity, _)| *entity).collect();
+                let snapped = snapper.snap(pivot + current - drag.start, tolerance, depth, &moving) - pivot;
+                let delta = match drag.constraint {
+                    Constraint::Axis(axis) => axis * snapped.dot(axis),
+                    Constraint::Plane(normal) => snapped - normal * snapped.dot(normal),
+                };
+                Box::new(move |original| original.with_translation(original.translation + delta))
+            }
+            GizmoMode::Rotate => {
+                let axis = match drag.constraint {
+                    Constraint::Axis(axis) | Constraint::Plane(axis) => axis,
+                };
+                let from = drag.start - pivot;
+                let to = current - pivot;
+                let angle = snapper.snap_angle(f32::atan2(axis.dot(from.cross(to)), from.dot(to)));
+                let rotation = Quat::from_axis_angle(axis, angle);
+                Box::new(move |original| Transform {
+                    translation: pivot + rotation * (original.translation - pivot),
+                    rotation: rotation * original.rotation,
+                    scale: original.scale,
+                })
+            }
+            GizmoMode::Scale => {
+                let scale = Self::scale_factors(drag, snapper.settings(), current);
+                Box::new(move |original| Transform {
+                    translation: pivot + (original.translation - pivot) * scale,
+                    rotation: original.rotation,
+                    scale: original.scale * world_scale_to_local(original.rotation, scale),
+                })
+            }
+        };
+
+        for (entity, original) in &drag.originals {
+            if let Ok((_, mut transform, _)) = selectables.get_mut(*entity) {
+                *transform = apply(original);
+            }
+        }
+    }
+
+    fn scale_factors(drag: &GizmoDrag, snap: &SnapSettings, current: Vec3) -> Vec3 {
+        let from = drag.start - drag.pivot;
+        let to = current - drag.pivot;
+        match drag.constraint {
+            Constraint::Axis(axis) => {
+                let from_length = from.dot(axis);
+                if from_length.abs() < f32::EPSILON {
+                    return Vec3::ONE;
+                }
+                let extent = drag.extent.dot(axis).abs();
+                let factor = snap_factor(snap, extent, to.dot(axis) / from_length);
+                Vec3::ONE + axis.abs() * (factor - 1.0)
+            }
+            Constraint::Plane(normal) => {
+                let from_length = from.length();
+                if from_length < f32::EPSILON {
+                    return Vec3::ONE;
+                }
+                let in_plane = drag.extent - normal.abs() * drag.extent;
+                let factor = snap_factor(snap, in_plane.max_element(), to.length() / from_length);
+                if drag.uniform {
+                    Vec3::splat(factor)
+                } else {
+                    Vec3::ONE + (Vec3::ONE - normal.abs()) * (factor - 1.0)
+                }
+            }
+        }
+    }
+
+    fn finish_drag(
+        &mut self,
+        history: &mut EditHistory,
+        selectables: &Query<(Entity, &mut Transform, &EditorSelectable), Without<Locked>>,
+    ) {
+        let Some(drag) = self.drag.take() else { return; };
+        let changes = drag.originals
+            .iter()
+            .filter_map(|(entity, before)| {
+                let (_, after, _) = selectables.get(*entity).ok()?;
+                (*after != *before).then(|| Change::Transform {
+                    entity: *entity,
+                    before: *before,
+                    after: *after,
+                })
+            })
+            .collect();
+        history.record(Edit {
+            label: drag.mode.name(),
+            changes,
+        });
+    }
+
+    fn cancel_drag(
+        mut tool: ResMut<Self>,
+        mut selectables: Query<(Entity, &mut Transform, &EditorSelectable), Without<Locked>>,
+    ) {
+        if let Some(drag) = tool.drag.take() {
+            for (entity, original) in drag.originals {
+                if let Ok((_, mut transform, _)) = selectables.get_mut(entity) {
+                    *transform = original;
+                }
+            }
+        }
+        tool.hovered = None;
+    }
+
+    fn draw_handles(
+        tool: Res<Self>,
+        mut gizmos: Gizmos,
+    ) {
+        let Some(pivot) = tool.pivot else { return; };
+        let active = tool.drag.as_ref().map(|drag| drag.handle).or(tool.hovered);
+        let highlight = Color::srgb_u8(255, 230, 0);
+        let length = tool.handle_length;
+
+        for axis in GizmoAxis::ALL {
+            let color = if active == Some(GizmoHandle::Axis(axis)) { highlight } else { axis.color() };
+            let end = pivot + axis.direction() * length;
+            match tool.mode {
+                GizmoMode::Translate => {
+                    gizmos.arrow(pivot, end, color);
+                }
+                GizmoMode::Rotate => {
+                    let rotation = Quat::from_rotation_arc(Vec3::Z, axis.direction());
+                    gizmos.circle(Isometry3d::new(pivot, rotation), length, color);
+                }
+                GizmoMode::Scale => {
+                    gizmos.line(pivot, end, color);
+                    gizmos.cuboid(Transform::from_translation(end).with_scale(Vec3::splat(length * 0.1)), color);
+                }
+            }
+        }
+
+        if tool.mode != GizmoMode::Rotate {
+            let color = if active == Some(GizmoHandle::Center) { highlight } else { Color::WHITE };
+            gizmos.sphere(Isometry3d::from_translation(pivot), length * 0.08, color);
+        }
+    }
+
+    pub fn options_ui(&mut self, ui: &mut Ui, snap: &mut SnapSettings) {
+        for mode in GizmoMode::iter() {
+            ui.radio_value(&mut self.mode, mode, mode.name());
+        }
+        ui.separator();
+        snap.grid_ui(ui);
+        ui.separator();
+        snap.angle_ui(ui);
+    }
+}
+
+fn project(camera: &Camera, camera_tfm: &GlobalTransform, multicam: &Multicam, point: Vec3) -> Option<Vec2> {
+    if multicam.axis == CameraAxis::None {
+        camera.world_to_viewport(camera_tfm, point).ok()
+    } else {
+        painter::world_to_viewport_ignoring_depth(camera, camera_tfm, point)
+    }
+}
+
+fn world_per_pixel(camera: &Camera, camera_tfm: &GlobalTransform, multicam: &Multicam, point: Vec3) -> Option<f32> {
+    let a = project(camera, camera_tfm, multicam, point)?;
+    let b = project(camera, camera_tfm, multicam, point + camera_tfm.right().as_vec3())?;
+    let pixels = a.distance(b);
+    if pixels < f32::EPSILON {
+        return None;
+    }
+    Some(1.0 / pixels)
+}
+
+fn constrained_point(ray: Ray3d, pivot: Vec3, constraint: Constraint) -> Option<Vec3> {
+    match constraint {
+        Constraint::Axis(axis) => closest_point_on_line(ray, pivot, axis),
+        Constraint::Plane(normal) => ray_plane_intersection(ray, pivot, normal),
+    }
+}
+
+/// Unlike `Ray3d::intersect_plane`, this accepts planes behind the ray origin,
+/// which happens in the orthographic views.
+fn ray_plane_intersection(ray: Ray3d, plane_origin: Vec3, normal: Vec3) -> Option<Vec3> {
+    let denominator = normal.dot(*ray.direction);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let distance = (plane_origin - ray.origin).dot(normal) / denominator;
+    Some(ray.get_point(distance))
+}
+
+fn closest_point_on_line(ray: Ray3d, line_origin: Vec3, line_direction: Vec3) -> Option<Vec3> {
+    let ray_direction = *ray.direction;
+    let w = line_origin - ray.origin;
+    let b = line_direction.dot(ray_direction);
+    let d = line_direction.dot(w);
+    let e = ray_direction.dot(w);
+    let denominator = 1.0 - b * b;
+    if denominator.abs() < 1e-5 {
+        return None;
+    }
+    let t = (b * e - d) / denominator;
+    Some(line_origin + line_direction * t)
+}
+
+fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    if length_squared < f32::EPSILON {
+        return point.distance(a);
+    }
+    let t = ((point - a).dot(ab) / length_squared).clamp(0.0, 1.0);
+    point.distance(a + ab * t)
+}
+
+fn circle_points(center: Vec3, normal: Vec3, radius: f32) -> Vec<Vec3> {
+    let rotation = Quat::from_rotation_arc(Vec3::Z, normal);
+    (0..=CIRCLE_SEGMENTS)
+        .map(|i| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + rotation * Vec3::new(angle.cos(), angle.sin(), 0.0) * radius
+        })
+        .collect()
+}
+
+/// Snaps a scale factor so that the scaled size lands on the grid.
+fn snap_factor(snap: &SnapSettings, extent: f32, factor: f32) -> f32 {
+    let factor = factor.max(MIN_SCALE);
+    if extent < f32::EPSILON {
+        return factor;
+    }
+    let snapped = snap.snap_scalar(extent * factor);
+    if snapped < f32::EPSILON {
+        return factor;
+    }
+    snapped / extent
+}
+
+/// Scale handles are world aligned, so each local axis takes the factor of the world axis it lines up with best.
+fn world_scale_to_local(rotation: Quat, world_scale: Vec3) -> Vec3 {
+    let pick = |local: Vec3| {
+        let world = (rotation * local).abs();
+        if world.x >= world.y && world.x >= world.z {
+            world_scale.x
+        } else if world.y >= world.z {
+            world_scale.y
+        } else {
+            world_scale.z
+        }
+    };
+    Vec3::new(pick(Vec3::X), pick(Vec3::Y), pick(Vec3::Z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closest_point_on_line() {
+        let ray = Ray3d::new(Vec3::new(2.0, 5.0, 0.0), Dir3::NEG_Y);
+        let point = closest_point_on_line(ray, Vec3::ZERO, Vec3::X).unwrap();
+        assert!(point.distance(Vec3::new(2.0, 0.0, 0.0)) < 1e-5);
+
+        let parallel = Ray3d::new(Vec3::Y, Dir3::X);
+        assert!(closest_point_on_line(parallel, Vec3::ZERO, Vec3::X).is_none());
+    }
+
+    #[test]
+    fn test_ray_plane_behind_origin() {
+        let ray = Ray3d::new(Vec3::new(1.0, 2.0, 0.0), Dir3::Z);
+        let point = ray_plane_intersection(ray, Vec3::new(0.0, 0.0, -3.0), Vec3::Z).unwrap();
+        assert!(point.distance(Vec3::new(1.0, 2.0, -3.0)) < 1e-5);
+    }
+
+    #[test]
+    fn test_snap_factor_lands_on_grid() {
+        let snap = SnapSettings::default();
+        let factor = snap_factor(&snap, 1.0, 1.234);
+        assert!((factor - 1.2).abs() < 1e-5);
+        assert_eq!(snap_factor(&snap, 1.0, -2.0), MIN_SCALE);
+    }
+
+    #[test]
+    fn test_flat_views_scale_one_axis() {
+        // The top view looks down -Y; grab the X handle and drag it out to twice its length, off to the side.
+        let (constraint, uniform) = MoveTool::constraint(GizmoMode::Scale, GizmoHandle::Axis(GizmoAxis::X), Vec3::NEG_Y, true);
+        let drag = GizmoDrag {
+            mode: GizmoMode::Scale,
+            handle: GizmoHandle::Axis(GizmoAxis::X),
+            camera: Entity::from_raw(1),
+            constraint,
+            uniform,
+            pivot: Vec3::ZERO,
+            extent: Vec3::ONE,
+            start: Vec3::X,
+            originals: vec![],
+        };
+        let scale = MoveTool::scale_factors(&drag, &SnapSettings::default(), Vec3::new(2.0, 0.0, 0.7));
+        assert!(scale.distance(Vec3::new(2.0, 1.0, 1.0)) < 1e-5);
+
+        let (center, _) = MoveTool::constraint(GizmoMode::Scale, GizmoHandle::Center, Vec3::NEG_Y, true);
+        assert!(matches!(center, Constraint::Plane(normal) if normal == Vec3::NEG_Y));
+    }
+
+    #[test]
+    fn test_world_scale_to_local() {
+        let rotation = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        let local = world_scale_to_local(rotation, Vec3::new(2.0, 1.0, 1.0));
+        assert!((local.z - 2.0).abs() < 1e-5);
+        assert!((local.x - 1.0).abs() < 1e-5);
+    }
+}
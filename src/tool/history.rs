@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use crate::editor::input::CurrentKeyboardInput;
+use crate::tool::room::Room;
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<EditHistory>()
+            .add_systems(Update, Self::undo_redo)
+        ;
+    }
+}
+
+/// One undoable step. A single drag of several selected objects is one edit with several changes.
+pub struct Edit {
+    pub label: String,
+    pub changes: Vec<Change>,
+}
+
+pub enum Change {
+    Transform {
+        entity: Entity,
+        before: Transform,
+        after: Transform,
+    },
+    /// A room's (min, max) corners.
+    Room {
+        entity: Entity,
+        before: (Vec3, Vec3),
+        after: (Vec3, Vec3),
+    },
+}
+
+impl Change {
+    fn revert(&self, transforms: &mut Query<&mut Transform>, rooms: &mut Query<&mut Room>) {
+        match self {
+            Change::Transform { entity, before, .. } => {
+                if let Ok(mut transform) = transforms.get_mut(*entity) {
+                    *transform = *before;
+                }
+            }
+            Change::Room { entity, before, .. } => {
+                if let Ok(mut room) = rooms.get_mut(*entity) {
+                    room.set_bounds(before.0, before.1);
+                }
+            }
+        }
+    }
+
+    fn reapply(&self, transforms: &mut Query<&mut Transform>, rooms: &mut Query<&mut Room>) {
+        match self {
+            Change::Transform { entity, after, .. } => {
+                if let Ok(mut transform) = transforms.get_mut(*entity) {
+                    *transform = *after;
+                }
+            }
+            Change::Room { entity, after, .. } => {
+                if let Ok(mut room) = rooms.get_mut(*entity) {
+                    room.set_bounds(after.0, after.1);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct EditHistory {
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+}
+
+impl EditHistory {
+    /// Records an edit that has already been applied to the world.
+    pub fn record(&mut self, edit: Edit) {
+        if edit.changes.is_empty() {
+            return;
+        }
+        self.undo_stack.push(edit);
+        self.redo_stack.clear();
+    }
+
+    /// Forgets every edit, for when the entities they refer to are gone.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo_stack.last().map(|edit| edit.label.as_str())
+    }
+
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo_stack.last().map(|edit| edit.label.as_str())
+    }
+}
+
+impl HistoryPlugin {
+    fn undo_redo(
+        keyboard_input: Res<CurrentKeyboardInput>,
+        mut history: ResMut<EditHistory>,
+        mut transforms: Query<&mut Transform>,
+        mut rooms: Query<&mut Room>,
+    ) {
+        if keyboard_input.undo {
+            if let Some(edit) = history.undo_stack.pop() {
+                for change in edit.changes.iter().rev() {
+                    change.revert(&mut transforms, &mut rooms);
+                }
+                history.redo_stack.push(edit);
+            }
+        } else if keyboard_input.redo {
+            if let Some(edit) = history.redo_stack.pop() {
+                for change in edit.changes.iter() {
+                    change.reapply(&mut transforms, &mut rooms);
+                }
+                history.undo_stack.push(edit);
+            }
+        }
+    }
+}
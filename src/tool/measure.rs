@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContextPass, EguiContexts};
+use bevy_egui::egui::Ui;
+use crate::editor::input::{CurrentKeyboardInput, CurrentMouseInput};
+use crate::editor::multicam::Multicam;
+use crate::get;
+use crate::tool::playtest::Playtest;
+use crate::tool::snap::{SnapSettings, Snapper};
+use crate::tool::Tools;
+
+pub struct MeasurePlugin;
+
+impl Plugin for MeasurePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<MeasureTool>()
+            .add_systems(Update, (
+                MeasureTool::pick,
+                MeasureTool::draw,
+            ).chain().run_if(in_state(Tools::Measure)).run_if(Playtest::inactive))
+            .add_systems(EguiContextPass, MeasureTool::window.run_if(in_state(Tools::Measure)))
+        ;
+    }
+}
+
+/// Picks points in the viewports and reports the distances and angles between them.
+/// The points make a path: each one is measured from the one before.
+#[derive(Resource)]
+pub struct MeasureTool {
+    points: Vec<Vec3>,
+    /// Where the next point would go, if the cursor is over a view.
+    hover: Option<Vec3>,
+    window: bool,
+}
+
+impl Default for MeasureTool {
+    fn default() -> Self {
+        Self {
+            points: Vec::new(),
+            hover: None,
+            window: true,
+        }
+    }
+}
+
+/// One leg of a measured path.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Segment {
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+impl Segment {
+    pub fn delta(&self) -> Vec3 {
+        self.to - self.from
+    }
+
+    pub fn length(&self) -> f32 {
+        self.delta().length()
+    }
+}
+
+/// The legs between consecutive points.
+pub fn segments(points: &[Vec3]) -> impl Iterator<Item = Segment> + '_ {
+    points.windows(2).map(|pair| Segment { from: pair[0], to: pair[1] })
+}
+
+/// The angle at `corner` between the lines out to `a` and `b`, in degrees.
+/// `None` if either line has no length, since then there's no angle.
+pub fn angle_at(a: Vec3, corner: Vec3, b: Vec3) -> Option<f32> {
+    let (to_a, to_b) = (a - corner, b - corner);
+    if to_a.length_squared() < f32::EPSILON || to_b.length_squared() < f32::EPSILON {
+        return None;
+    }
+    Some(to_a.angle_between(to_b).to_degrees())
+}
+
+impl MeasureTool {
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    fn pick(
+        mut tool: ResMut<Self>,
+        keyboard_input: Res<CurrentKeyboardInput>,
+        mouse_input: Res<CurrentMouseInput>,
+        cameras: Query<(&Multicam, &Projection, &Camera)>,
+        mut ray_cast: MeshRayCast,
+        mut snapper: Snapper,
+    ) {
+        if keyboard_input.cancel {
+            tool.clear();
+        }
+        tool.hover = None;
+        let (Some(camera), Some(ray)) = (mouse_input.in_camera, mouse_input.world_pos) else {
+            return;
+        };
+        let Ok((multicam, projection, cam)) = cameras.get(camera) else {
+            return;
+        };
+
+        let radius = snapper.settings().radius_pixels;
+        let (point, tolerance, depth) = match (multicam.axis.index(), projection) {
+            (Some(depth), Projection::Orthographic(ortho)) => {
+                // Flat views don't say how deep the point is, so it stays level with the last one.
+                let mut point = ray.origin;
+                point[depth] = tool.points.last().map_or(0.0, |last| last[depth]);
+                (point, ortho.scale * radius, Some(depth))
+            }
+            (_, Projection::Perspective(perspective)) => {
+                let Some((_, hit)) = ray_cast.cast_ray(ray, &MeshRayCastSettings::default()).first() else {
+                    return;
+                };
+                // How wide the snap radius is, out at the distance of the hit.
+                let pixel = cam.logical_viewport_size().map_or(0.0, |size| perspective.fov / size.y);
+                (hit.point, hit.distance * pixel * radius, None)
+            }
+            _ => return,
+        };
+        let point = snapper.snap(point, tolerance, depth, &[]);
+        tool.hover = Some(point);
+
+        if mouse_input.released == Some(MouseButton::Left) {
+            tool.points.push(point);
+        }
+    }
+
+    fn draw(tool: Res<Self>, mut gizmos: Gizmos) {
+        let color = Color::srgb_u8(255, 220, 0);
+        for point in &tool.points {
+            gizmos.sphere(Isometry3d::from_translation(*point), 0.05, color);
+        }
+        for segment in segments(&tool.points) {
+            gizmos.line(segment.from, segment.to, color);
+        }
+        if let (Some(last), Some(hover)) = (tool.points.last(), tool.hover) {
+            gizmos.line(*last, hover, color.with_alpha(0.4));
+        }
+    }
+
+    pub fn options_ui(&mut self, ui: &mut Ui, snap: &mut SnapSettings) {
+        snap.grid_ui(ui);
+        ui.separator();
+        if ui.add_enabled(!self.points.is_empty(), egui::Button::new(get!("measure.clear"))).clicked() {
+            self.clear();
+        }
+        ui.checkbox(&mut self.window, get!("measure.window"));
+    }
+
+    fn window(mut contexts: EguiContexts, mut tool: ResMut<Self>) {
+        let Some(ctx) = contexts.try_ctx_mut() else {
+            return;
+        };
+        if !tool.window {
+            return;
+        }
+        let mut open = tool.window;
+        egui::Window::new(get!("measure.title"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| tool.readout(ui));
+        tool.window = open;
+    }
+
+    fn readout(&self, ui: &mut Ui) {
+        if self.points.is_empty() {
+            ui.weak(get!("measure.hint"));
+            return;
+        }
+        for (index, segment) in segments(&self.points).enumerate() {
+            let delta = segment.delta();
+            ui.label(get!("measure.segment", "from", index + 1, "to", index + 2, "length", format!("{:.3}", segment.length())));
+            ui.weak(get!("measure.delta",
+                "x", format!("{:.3}", delta.x),
+                "y", format!("{:.3}", delta.y),
+                "z", format!("{:.3}", delta.z)));
+        }
+        for (index, corner) in self.points.windows(3).enumerate() {
+            if let Some(degrees) = angle_at(corner[0], corner[1], corner[2]) {
+                ui.label(get!("measure.angle", "point", index + 2, "degrees", format!("{:.1}", degrees)));
+            }
+        }
+        if self.points.len() > 2 {
+            let total: f32 = segments(&self.points).map(|segment| segment.length()).sum();
+            ui.label(get!("measure.total", "length", format!("{:.3}", total)));
+        }
+        if let (Some(last), Some(hover)) = (self.points.last(), self.hover) {
+            ui.separator();
+            ui.weak(get!("measure.cursor", "length", format!("{:.3}", last.distance(hover))));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments_measure_each_leg() {
+        let points = [Vec3::ZERO, Vec3::new(3.0, 4.0, 0.0), Vec3::new(3.0, 4.0, 2.0)];
+        let legs: Vec<Segment> = segments(&points).collect();
+        assert_eq!(legs.len(), 2);
+        assert_eq!(legs[0].length(), 5.0);
+        assert_eq!(legs[1].delta(), Vec3::new(0.0, 0.0, 2.0));
+        assert_eq!(segments(&points[..1]).count(), 0);
+    }
+
+    #[test]
+    fn test_angles() {
+        let right = angle_at(Vec3::X, Vec3::ZERO, Vec3::Y).unwrap();
+        assert!((right - 90.0).abs() < 1e-4);
+        let straight = angle_at(Vec3::NEG_X, Vec3::ZERO, Vec3::X).unwrap();
+        assert!((straight - 180.0).abs() < 1e-3);
+        assert_eq!(angle_at(Vec3::ZERO, Vec3::ZERO, Vec3::X), None);
+    }
+}
@@ -1,18 +1,33 @@
 use bevy::app::App;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy_egui::{egui, EguiContextPass, EguiContexts};
+use bevy_egui::{egui, EguiContexts};
 use bevy_egui::egui::Ui;
 use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter};
+use crate::editor::console::{ConsoleCommand, ConsoleCommands, ConsoleCommandsExt};
+use crate::editor::keymap::{ActionState, InputAction, Keymap};
+use crate::editor::palette::{Operation, OperationsExt};
 use crate::get;
 use crate::tool::bakes::BakePlugin;
+use crate::tool::gizmo::{MovePlugin, MoveTool};
+use crate::tool::history::HistoryPlugin;
+use crate::tool::measure::{MeasurePlugin, MeasureTool};
 use crate::tool::movement::MovementPlugin;
+use crate::tool::playtest::{Playtest, PlaytestPlugin};
+use crate::tool::room::{RoomPlugin, RoomTool};
 use crate::tool::selection::SelectionPlugin;
 use crate::tool::show::ShowPlugin;
+use crate::tool::snap::{SnapPlugin, SnapSettings};
 
 pub mod selection;
 pub mod room;
 pub mod movement;
+pub mod gizmo;
+pub mod history;
+pub mod snap;
+pub mod playtest;
+pub mod measure;
 mod bakes;
 mod show;
 
@@ -23,12 +38,23 @@ impl Plugin for ToolPlugin {
         app
             .init_resource::<ToolData>()
             .init_state::<Tools>()
+            .add_plugins(SnapPlugin)
             .add_plugins(ShowPlugin)
             .add_plugins(BakePlugin)
             .add_plugins(MovementPlugin)
             .add_plugins(SelectionPlugin)
-            // .add_plugins(RoomPlugin)
-            // .add_systems(EguiContextPass, Self::toolbar)
+            .add_plugins(HistoryPlugin)
+            .add_plugins(MovePlugin)
+            .add_plugins(RoomPlugin)
+            .add_plugins(MeasurePlugin)
+            .add_plugins(PlaytestPlugin)
+            .add_systems(Update, Self::hotkeys.run_if(Playtest::inactive))
+            .add_console_command("tool", ConsoleCommand::new("console.commands.tool", Self::tool_command)
+                .with_completions(|_| Tools::iter().map(|tool| tool.to_string().to_lowercase()).collect()))
+            .add_operation(Operation::command("palette.operations.tool_select", "tool select").with_action(InputAction::ToolSelect))
+            .add_operation(Operation::command("palette.operations.tool_move", "tool move").with_action(InputAction::ToolMove))
+            .add_operation(Operation::command("palette.operations.tool_room", "tool room").with_action(InputAction::ToolRoom))
+            .add_operation(Operation::command("palette.operations.tool_measure", "tool measure").with_action(InputAction::ToolMeasure))
         ;
     }
 }
@@ -49,43 +75,111 @@ impl Default for ToolData {
 pub enum Tools {
     #[default]
     Select,
+    Move,
     Room,
+    Measure,
 }
 
 impl Tools {
     fn name(&self) -> String {
         match self {
             Self::Select => get!("tools.select"),
+            Self::Move => get!("tools.move"),
             Self::Room => get!("tools.room"),
+            Self::Measure => get!("tools.measure"),
         }
     }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Self::Select => "🖱",
+            Self::Move => "↔",
+            Self::Room => "🏠",
+            Self::Measure => "📏",
+        }
+    }
+
+    /// Number keys, because WASDQE are taken by the camera while flying.
+    fn action(&self) -> InputAction {
+        match self {
+            Self::Select => InputAction::ToolSelect,
+            Self::Move => InputAction::ToolMove,
+            Self::Room => InputAction::ToolRoom,
+            Self::Measure => InputAction::ToolMeasure,
+        }
+    }
+}
+
+/// The resources each tool's options strip edits, bundled so the panels don't run out of system parameters.
+#[derive(SystemParam)]
+pub struct ToolOptions<'w> {
+    snap: ResMut<'w, SnapSettings>,
+    move_tool: ResMut<'w, MoveTool>,
+    room_tool: ResMut<'w, RoomTool>,
+    measure_tool: ResMut<'w, MeasureTool>,
+}
+
+impl ToolOptions<'_> {
+    pub fn ui(&mut self, ui: &mut Ui, tool: &Tools) {
+        ui.horizontal(|ui| {
+            match tool {
+                Tools::Select => {}
+                Tools::Move => self.move_tool.options_ui(ui, &mut self.snap),
+                Tools::Room => self.room_tool.options_ui(ui, &mut self.snap),
+                Tools::Measure => self.measure_tool.options_ui(ui, &mut self.snap),
+            }
+        });
+    }
 }
 
 impl ToolPlugin {
-    fn toolbar(
+    pub fn toolbar(
+        ui: &mut Ui,
+        current_tool: &State<Tools>,
+        next_tool: &mut NextState<Tools>,
+        keymap: &Keymap,
+    ) {
+        ui.horizontal(|ui| {
+            for item in Tools::iter() {
+                let active = current_tool.get() == &item;
+                let button = ui.selectable_label(active, egui::RichText::new(item.icon()).size(18.0))
+                    .on_hover_text(get!("tools.tooltip", "tool", item.name(), "key", keymap.describe(item.action())));
+                if button.clicked() && !active {
+                    next_tool.set(item);
+                }
+            }
+        });
+    }
+
+    fn tool_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+        let [name] = args else {
+            return Err(world.resource::<ConsoleCommands>().get("tool").map(ConsoleCommand::usage).unwrap_or_default());
+        };
+        let tool = Tools::iter()
+            .find(|tool| tool.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| get!("console.no_such_tool", "tool", name))?;
+        world.resource_mut::<NextState<Tools>>().set(tool);
+        Ok(String::new())
+    }
+
+    fn hotkeys(
         mut contexts: EguiContexts,
+        actions: Res<ActionState>,
+        mouse_buttons: Res<ButtonInput<MouseButton>>,
         current_tool: Res<State<Tools>>,
         mut next_tool: ResMut<NextState<Tools>>,
     ) {
-        let ctx = contexts.try_ctx_mut();
-        if ctx.is_none() { return; }
-        let ctx = ctx.unwrap();
-
-        egui::Window::new(get!("tools.title")).show(ctx, |ui| {
-           egui::Grid::new("tools").show(ui, |ui| {
-               for item in Tools::iter() {
-                   if current_tool.eq(&item) {
-                       ui.scope(|ui| {
-                           ui.disable();
-                           let _ = ui.button(item.name());
-                       });
-                   } else {
-                       if ui.button(item.name()).clicked() {
-                           next_tool.set(item);
-                       }
-                   }
-               }
-           })
-        });
+        if let Some(ctx) = contexts.try_ctx_mut() && ctx.wants_keyboard_input() {
+            return;
+        }
+        // Holding a mouse button means the camera is flying, or a drag is in progress.
+        if mouse_buttons.get_pressed().next().is_some() {
+            return;
+        }
+        for item in Tools::iter() {
+            if actions.just_pressed(item.action()) && current_tool.get() != &item {
+                next_tool.set(item);
+            }
+        }
     }
 }
@@ -3,9 +3,31 @@
  use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, PrimaryWindow};
 use bevy_egui::{egui, EguiContextPass, EguiContexts};
+use bevy_egui::egui::Ui;
+use crate::editor::console::{ConsoleCommand, ConsoleCommands, ConsoleCommandsExt};
 use crate::editor::input::{CurrentKeyboardInput, CurrentMouseInput};
-use crate::editor::multicam::Multicam;
+use crate::editor::keymap::{ActionState, InputAction};
+use crate::editor::multicam::{CameraAxis, Multicam};
+use crate::editor::palette::{Operation, OperationsExt};
+use crate::editor::preferences::{CameraPreferences, Preferences};
 use crate::get;
+use crate::tool::playtest::Playtest;
+use crate::tool::selection::{EditorSelectable, FrameBounds, FrameSelection, SelectionState};
+
+const MIN_ORTHO_SCALE: f32 = 0.001;
+const MAX_ORTHO_SCALE: f32 = 0.1;
+/// How much empty space to leave around framed objects.
+const FRAME_MARGIN: f32 = 1.25;
+/// When there's nothing selected or under the cursor, orbit around a point this far in front of the camera.
+const ORBIT_FALLBACK_DISTANCE: f32 = 5.0;
+/// Orbiting stops short of looking straight up or down, where yaw stops making sense.
+const ORBIT_MAX_PITCH: f32 = 0.99;
+/// How far one pixel of mouse movement turns the camera, at a sensitivity of 1.
+const LOOK_RADIANS_PER_PIXEL: f32 = 0.01;
+const MIN_FLY_SPEED: f32 = 0.5;
+const MAX_FLY_SPEED: f32 = 200.0;
+/// Each notch of Ctrl+scroll while flying changes the fly speed by this factor.
+const FLY_SPEED_STEP: f32 = 1.25;
 
 pub struct MovementPlugin;
 
@@ -13,21 +35,86 @@ impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<MovementSettings>()
+            .init_resource::<Orbit>()
+            .init_resource::<CameraSettingsScreen>()
             .add_systems(Update, (
-                Self::handle,
+                Self::apply_camera_preferences,
+                Self::frame_hotkey.run_if(Playtest::inactive),
+                Orbit::pick_pivot.before(Self::handle),
+                Self::handle.run_if(Playtest::inactive),
+                Self::frame_selection.after(Self::frame_hotkey),
                 )
             )
-            .add_systems(EguiContextPass, Self::debug_window)
+            .add_systems(EguiContextPass, (Self::debug_window, CameraSettingsScreen::window))
+            .add_console_command("camera", ConsoleCommand::new("console.commands.camera", Self::camera_command)
+                .with_completions(|_| vec!["goto".to_owned()]))
+            .add_operation(Operation::new("palette.operations.frame_selection", |world| {
+                world.send_event(FrameSelection);
+            }).with_action(InputAction::FrameSelection))
+            .add_operation(Operation::new("palette.operations.camera_settings", |world| {
+                world.resource_mut::<CameraSettingsScreen>().open = true;
+            }))
+            .add_operation(Operation::new("palette.operations.debug_movement", |world| {
+                let mut settings = world.resource_mut::<MovementSettings>();
+                settings.debug_window = !settings.debug_window;
+            }))
         ;
     }
 }
 
 impl MovementPlugin {
+    fn camera_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+        let usage = || world.resource::<ConsoleCommands>().get("camera").map(ConsoleCommand::usage).unwrap_or_default();
+        let ["goto", x, y, z] = args else {
+            return Err(usage());
+        };
+        let (Ok(x), Ok(y), Ok(z)) = (x.parse::<f32>(), y.parse::<f32>(), z.parse::<f32>()) else {
+            return Err(usage());
+        };
+        let position = Vec3::new(x, y, z);
+        let mut cameras = world.query::<(&mut Transform, &Multicam)>();
+        let mut transform = cameras
+            .iter_mut(world)
+            .find(|(_, multicam)| multicam.axis == CameraAxis::None)
+            .map(|(transform, _)| transform)
+            .ok_or_else(|| get!("console.no_camera"))?;
+        transform.translation = position;
+        Ok(get!("console.camera_moved", "position", position))
+    }
+
+    fn frame_hotkey(actions: Res<ActionState>, mut frame: EventWriter<FrameSelection>) {
+        if actions.just_pressed(InputAction::FrameSelection) {
+            frame.write(FrameSelection);
+        }
+    }
+
+    /// Keeps the perspective cameras' field of view and clip planes in line with the preferences.
+    fn apply_camera_preferences(
+        preferences: Res<Preferences>,
+        mut cameras: Query<&mut Projection, With<Multicam>>,
+    ) {
+        let camera = &preferences.camera;
+        let fov = camera.fov_degrees.to_radians();
+        for mut projection in &mut cameras {
+            if let Projection::Perspective(perspective) = projection.as_ref()
+                && (perspective.fov != fov || perspective.near != camera.near || perspective.far != camera.far)
+                && let Projection::Perspective(perspective) = projection.as_mut() {
+                perspective.fov = fov;
+                perspective.near = camera.near;
+                perspective.far = camera.far;
+            }
+        }
+    }
+
     fn handle(
         mut window: Query<&mut Window, With<PrimaryWindow>>,
-        settings: Res<MovementSettings>,
+        mut settings: ResMut<MovementSettings>,
+        mut preferences: ResMut<Preferences>,
+        time: Res<Time>,
         mouse_input: Res<CurrentMouseInput>,
         keyboard_input: Res<CurrentKeyboardInput>,
+        actions: Res<ActionState>,
+        orbit: Res<Orbit>,
         mut cameras: Query<(Entity, &mut Transform, &GlobalTransform, &Multicam, &mut Projection, &Camera)>,
         mut evr_scroll: EventReader<MouseWheel>,
     ) {
@@ -36,6 +123,14 @@ impl MovementPlugin {
             return;
         }
         let mut window = window.unwrap();
+
+        // Flying speeds up while the movement keys are held, and starts from a standstill again after.
+        let moving = keyboard_input.forward() != 0.0 || keyboard_input.right() != 0.0 || keyboard_input.up() != 0.0;
+        settings.fly_speed = if moving && mouse_input.pressed == Some(MouseButton::Middle) {
+            (settings.fly_speed + preferences.camera.fly_acceleration * time.delta_secs()).min(preferences.camera.fly_speed)
+        } else {
+            0.0
+        };
         
         if mouse_input.started_in_camera.is_none() {
             window.cursor_options.grab_mode = CursorGrabMode::None;
@@ -54,12 +149,30 @@ impl MovementPlugin {
                     for (entity, mut transform, global_transform, multicam, mut projection, camera) in &mut cameras {
                         if cam_id == entity {
                             let delta = mouse_input.delta_pos;
+                            let look = preferences.camera.look_delta(delta) * LOOK_RADIANS_PER_PIXEL;
                             let forward = transform.forward();
                             match projection.into_inner() {
                                 Projection::Perspective(projection) => {
-                                    Self::perspective_move(&mut transform, global_transform, delta, &settings, &keyboard_input);
+                                    // Holding right on top of middle pans, like holding Shift.
+                                    let pan = keyboard_input.modify || mouse_input.chord.contains(MouseButton::Right);
+                                    match orbit.pivot {
+                                        Some(pivot) if actions.pressed(InputAction::Orbit) && !pan => {
+                                            Orbit::rotate(&mut transform, pivot, look);
+                                        }
+                                        _ => {
+                                            let step = settings.fly_speed * time.delta_secs();
+                                            Self::perspective_move(&mut transform, global_transform, delta, look, pan, step, &settings, &keyboard_input);
+                                        }
+                                    }
 
                                     for ev in evr_scroll.read() {
+                                        // Ctrl+scroll while flying changes how fast you fly.
+                                        if keyboard_input.toggle {
+                                            let camera = &mut preferences.camera;
+                                            camera.fly_speed = (camera.fly_speed * FLY_SPEED_STEP.powf(ev.y.signum()))
+                                                .clamp(MIN_FLY_SPEED, MAX_FLY_SPEED);
+                                            continue;
+                                        }
                                         match ev.unit {
                                             MouseScrollUnit::Line => {
                                                 transform.translation += forward * settings.perspective_scroll * ev.y;
@@ -82,11 +195,11 @@ impl MovementPlugin {
                                                 projection.scale -= settings.orthographic_scroll * ev.y;
                                             }
                                         }
-                                        if projection.scale < 0.001 {
-                                            projection.scale = 0.001;
+                                        if projection.scale < MIN_ORTHO_SCALE {
+                                            projection.scale = MIN_ORTHO_SCALE;
                                         }
-                                        if projection.scale > 0.1 {
-                                            projection.scale = 0.1;
+                                        if projection.scale > MAX_ORTHO_SCALE {
+                                            projection.scale = MAX_ORTHO_SCALE;
                                         }
                                     }
                                 }
@@ -99,8 +212,45 @@ impl MovementPlugin {
         }
     }
 
-    fn perspective_move(transform: &mut Mut<Transform>, global_transform: &GlobalTransform, delta: Vec2, movement_settings: &Res<MovementSettings>, keyboard_input: &Res<CurrentKeyboardInput>) {
-        if keyboard_input.modify {
+    /// Perspective cameras back off along their view direction until the selection fits.
+    /// Orthographic cameras keep their depth, centre on the selection and zoom to fit it.
+    /// A box asked for with `FrameBounds` is framed the same way, instead of the selection.
+    fn frame_selection(
+        mut events: EventReader<FrameSelection>,
+        mut bounds_events: EventReader<FrameBounds>,
+        selection: Res<SelectionState>,
+        selectables: Query<(Entity, &Transform, &EditorSelectable), Without<Multicam>>,
+        mut cameras: Query<(&mut Transform, &mut Projection, &Camera), With<Multicam>>,
+    ) {
+        let requested = bounds_events.read().last().map(|bounds| (bounds.min, bounds.max));
+        let selected = if events.read().count() > 0 { selection.bounds(&selectables) } else { None };
+        let Some((min, max)) = requested.or(selected) else { return; };
+        let center = (min + max) / 2.0;
+        let radius = ((max - min).length() / 2.0).max(0.5);
+
+        for (mut transform, mut projection, camera) in &mut cameras {
+            let forward = transform.forward().as_vec3();
+            match projection.as_mut() {
+                Projection::Perspective(projection) => {
+                    let distance = radius * FRAME_MARGIN / (projection.fov / 2.0).sin().abs().max(0.1);
+                    transform.translation = center - forward * distance;
+                }
+                Projection::Orthographic(projection) => {
+                    let depth = (transform.translation - center).dot(forward);
+                    transform.translation = center + forward * depth;
+                    if let Some(size) = camera.logical_viewport_size() {
+                        projection.scale = (radius * 2.0 * FRAME_MARGIN / size.min_element())
+                            .clamp(MIN_ORTHO_SCALE, MAX_ORTHO_SCALE);
+                    }
+                }
+                Projection::Custom(_) => {}
+            }
+        }
+    }
+
+    /// `look` is how far to turn, in radians, and `step` how far to fly this frame.
+    fn perspective_move(transform: &mut Mut<Transform>, global_transform: &GlobalTransform, delta: Vec2, look: Vec2, pan: bool, step: f32, movement_settings: &MovementSettings, keyboard_input: &Res<CurrentKeyboardInput>) {
+        if pan {
             let pan_scaled_x = delta.x * movement_settings.perspective_pan;
             let pan_scaled_y = delta.y * movement_settings.perspective_pan;
 
@@ -114,23 +264,23 @@ impl MovementPlugin {
             let max = pi_fourths * 0.95;
             let min = -pi_fourths * 0.95;
             
-            transform.rotate_y(-delta.x * movement_settings.perspective_rotate);
-            transform.rotate_local_x(-delta.y * movement_settings.perspective_rotate);
+            transform.rotate_y(-look.x);
+            transform.rotate_local_x(-look.y);
 
 
             let (ry, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
-            let local_dx = keyboard_input.backward() * movement_settings.perspective_speed;
-            let local_dz = keyboard_input.right() * movement_settings.perspective_speed;
+            let local_dx = keyboard_input.backward() * step;
+            let local_dz = keyboard_input.right() * step;
             let dx = local_dx * f32::sin(ry) + local_dz * f32::sin(ry + pi_halves);
             let dz = local_dx * f32::cos(ry) + local_dz * f32::cos(ry + pi_halves);
             
-            let dy = keyboard_input.up() * movement_settings.perspective_speed;
+            let dy = keyboard_input.up() * step;
 
             transform.translation += Vec3::new(dx, dy, dz);
         }
     }
 
-    fn ortho_move(transform: &mut Mut<Transform>, delta: Vec2, projection: &OrthographicProjection, movement_settings: &Res<MovementSettings>, keyboard_input: &Res<CurrentKeyboardInput>) {
+    fn ortho_move(transform: &mut Mut<Transform>, delta: Vec2, projection: &OrthographicProjection, movement_settings: &MovementSettings, keyboard_input: &Res<CurrentKeyboardInput>) {
         let pi_halves = std::f32::consts::FRAC_PI_2;
 
         let pan_scaled_x = delta.x * projection.scale;
@@ -167,8 +317,6 @@ impl MovementPlugin {
         egui::Window::new(get!("debug.movement.title")).show(ctx, |ui| {
             ui.heading(get!("debug.movement.perspective.title"));
             ui.add(egui::Slider::new(&mut settings.perspective_pan, 0.0..=1.0).text(get!("debug.movement.perspective.pan")));
-            ui.add(egui::Slider::new(&mut settings.perspective_rotate, 0.0..=0.1).text(get!("debug.movement.perspective.rotate")));
-            ui.add(egui::Slider::new(&mut settings.perspective_speed, 0.0..=10.0).text(get!("debug.movement.perspective.speed")));
             ui.add(egui::Slider::new(&mut settings.perspective_scroll, 0.0..=1.0).text(get!("debug.movement.perspective.scroll")));
 
             ui.heading(get!("debug.movement.orthographic.title"));
@@ -177,12 +325,131 @@ impl MovementPlugin {
     }
 }
 
+/// The camera settings window, opened from the Settings menu.
+#[derive(Resource, Default)]
+pub struct CameraSettingsScreen {
+    pub open: bool,
+}
+
+impl CameraSettingsScreen {
+    fn window(
+        mut contexts: EguiContexts,
+        mut screen: ResMut<Self>,
+        mut preferences: ResMut<Preferences>,
+    ) {
+        if !screen.open {
+            return;
+        }
+        let Some(ctx) = contexts.try_ctx_mut() else {
+            return;
+        };
+        let mut open = screen.open;
+        egui::Window::new(get!("camera_settings.title"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| Self::ui(ui, &mut preferences.camera));
+        screen.open = open;
+    }
+
+    fn ui(ui: &mut Ui, camera: &mut CameraPreferences) {
+        egui::Grid::new("camera_settings").num_columns(2).show(ui, |ui| {
+            ui.label(get!("camera_settings.fov"));
+            ui.add(egui::Slider::new(&mut camera.fov_degrees, 20.0..=120.0).suffix("°"));
+            ui.end_row();
+
+            ui.label(get!("camera_settings.near"));
+            ui.add(egui::DragValue::new(&mut camera.near).speed(0.01).range(CameraPreferences::NEAR_RANGE));
+            ui.end_row();
+
+            ui.label(get!("camera_settings.far"));
+            let far_range = camera.far_range();
+            ui.add(egui::DragValue::new(&mut camera.far).speed(10.0).range(far_range));
+            ui.end_row();
+
+            ui.label(get!("camera_settings.fly_speed"));
+            ui.add(egui::Slider::new(&mut camera.fly_speed, MIN_FLY_SPEED..=MAX_FLY_SPEED).logarithmic(true));
+            ui.end_row();
+
+            ui.label(get!("camera_settings.fly_acceleration"));
+            ui.add(egui::Slider::new(&mut camera.fly_acceleration, 1.0..=500.0).logarithmic(true));
+            ui.end_row();
+
+            ui.label(get!("camera_settings.mouse_sensitivity"));
+            ui.add(egui::Slider::new(&mut camera.mouse_sensitivity, 0.1..=5.0));
+            ui.end_row();
+
+            ui.label(get!("camera_settings.invert_y"));
+            ui.checkbox(&mut camera.invert_y, "");
+            ui.end_row();
+        });
+        ui.weak(get!("camera_settings.speed_hint"));
+        if ui.button(get!("camera_settings.reset")).clicked() {
+            *camera = CameraPreferences::default();
+        }
+        // Raising the near plane can leave the far plane too close behind it.
+        camera.validate();
+    }
+}
+
+/// The point the perspective camera orbits around while the orbit key is held during a middle drag.
+#[derive(Resource, Default)]
+pub struct Orbit {
+    pub pivot: Option<Vec3>,
+}
+
+impl Orbit {
+    /// Picks the pivot when an orbit starts: the selection if there is one, otherwise whatever is under the cursor.
+    fn pick_pivot(
+        mut orbit: ResMut<Self>,
+        mouse_input: Res<CurrentMouseInput>,
+        actions: Res<ActionState>,
+        selection: Res<SelectionState>,
+        selectables: Query<(Entity, &Transform, &EditorSelectable), Without<Multicam>>,
+        cameras: Query<(&GlobalTransform, &Multicam)>,
+        mut ray_cast: MeshRayCast,
+    ) {
+        let orbiting = mouse_input.pressed == Some(MouseButton::Middle) && actions.pressed(InputAction::Orbit);
+        if !orbiting {
+            orbit.pivot = None;
+            return;
+        }
+        if orbit.pivot.is_some() {
+            return;
+        }
+        let Some(Ok((camera_tfm, multicam))) = mouse_input.started_in_camera.map(|camera| cameras.get(camera)) else {
+            return;
+        };
+        if multicam.axis != CameraAxis::None {
+            return;
+        }
+
+        let under_cursor = || {
+            let ray = mouse_input.world_pos?;
+            ray_cast.cast_ray(ray, &MeshRayCastSettings::default()).first().map(|(_, hit)| hit.point)
+        };
+        orbit.pivot = selection.bounds(&selectables)
+            .map(|(min, max)| (min + max) / 2.0)
+            .or_else(under_cursor)
+            .or(Some(camera_tfm.translation() + camera_tfm.forward() * ORBIT_FALLBACK_DISTANCE));
+    }
+
+    /// Turns the camera around `pivot`, keeping its distance and keeping it looking the same way relative to the pivot.
+    fn rotate(transform: &mut Transform, pivot: Vec3, delta: Vec2) {
+        transform.rotate_around(pivot, Quat::from_rotation_y(-delta.x));
+        let pitch = Quat::from_axis_angle(transform.local_x().as_vec3(), -delta.y);
+        let pitched = (pitch * transform.forward().as_vec3()).normalize();
+        if pitched.y.abs() < ORBIT_MAX_PITCH {
+            transform.rotate_around(pivot, pitch);
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct MovementSettings {
     debug_window: bool,
     perspective_pan: f32,
-    perspective_rotate: f32,
-    perspective_speed: f32,
+    /// How fast the perspective camera is flying right now, working up to the preferred speed.
+    fly_speed: f32,
     perspective_scroll: f32,
     orthographic_speed: f32,
     orthographic_scroll: f32,
@@ -193,11 +460,38 @@ impl Default for MovementSettings {
         Self {
             debug_window: false,
             perspective_pan: 0.1,
-            perspective_rotate: 0.01,
-            perspective_speed: 0.2,
+            fly_speed: 0.0,
             perspective_scroll: 0.5,
             orthographic_speed: 0.2,
             orthographic_scroll: 0.01,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orbit_keeps_distance_and_aim() {
+        let pivot = Vec3::new(1.0, 0.0, -2.0);
+        let mut transform = Transform::from_xyz(1.0, 2.0, 4.0).looking_at(pivot, Vec3::Y);
+        let distance = transform.translation.distance(pivot);
+        let aim = transform.forward().dot((pivot - transform.translation).normalize());
+
+        Orbit::rotate(&mut transform, pivot, Vec2::new(0.7, -0.3));
+        assert!((transform.translation.distance(pivot) - distance).abs() < 1e-4);
+        assert!((transform.forward().dot((pivot - transform.translation).normalize()) - aim).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_orbit_stops_at_the_poles() {
+        let pivot = Vec3::ZERO;
+        let mut transform = Transform::from_xyz(0.0, 0.0, 5.0).looking_at(pivot, Vec3::Y);
+        for _ in 0..100 {
+            Orbit::rotate(&mut transform, pivot, Vec2::new(0.0, 0.1));
+        }
+        assert!(transform.forward().y.abs() < ORBIT_MAX_PITCH);
+        assert!((transform.translation.length() - 5.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,345 @@
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::editor::input::{CurrentKeyboardInput, CurrentMouseInput};
+use crate::editor::keymap::{ActionState, InputAction};
+use crate::editor::multicam::{CameraAxis, Multicam, MulticamState};
+use crate::editor::palette::{Operation, OperationsExt};
+use crate::editor::preferences::Preferences;
+use crate::get;
+use crate::tool::room::Room;
+
+/// Half the size of the player's box, around its centre. 0.6 wide and 1.8 tall.
+const HULL_HALF_EXTENTS: Vec3 = Vec3::new(0.3, 0.9, 0.3);
+/// Where the eyes are, above the centre of the hull.
+const EYE_OFFSET: f32 = 0.7;
+const WALK_SPEED: f32 = 5.0;
+const JUMP_SPEED: f32 = 6.0;
+const GRAVITY: f32 = 20.0;
+/// Ledges up to this high are walked up, like stairs.
+const STEP_HEIGHT: f32 = 0.35;
+/// Radians per pixel of mouse movement, at a sensitivity of 1.
+const LOOK_SENSITIVITY: f32 = 0.003;
+const MAX_PITCH: f32 = 1.54;
+/// How many times a blocked move is halved looking for how far it can get.
+const SWEEP_STEPS: usize = 10;
+
+pub struct PlaytestPlugin;
+
+impl Plugin for PlaytestPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Playtest>()
+            .add_systems(Update, (
+                Playtest::toggle,
+                Playtest::simulate.run_if(Playtest::active),
+            ).chain())
+            .add_operation(Operation::new("palette.operations.playtest", |world| {
+                world.resource_mut::<Playtest>().requested = true;
+            }).with_action(InputAction::Playtest))
+        ;
+    }
+}
+
+/// The player while playtesting.
+#[derive(Clone, Copy, Debug)]
+pub struct Player {
+    /// The centre of the hull.
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub grounded: bool,
+}
+
+impl Player {
+    fn eye(&self) -> Transform {
+        Transform::from_translation(self.position + Vec3::Y * EYE_OFFSET)
+            .with_rotation(Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.0))
+    }
+}
+
+/// Walking around the map as a player, in the perspective viewport.
+#[derive(Resource, Default)]
+pub struct Playtest {
+    player: Option<Player>,
+    /// The camera being played through, and where it was before.
+    camera: Option<(Entity, Transform)>,
+    maximized_before: Option<u32>,
+    /// Set by the command palette to start or stop at the next update.
+    requested: bool,
+}
+
+impl Playtest {
+    /// Run condition for systems that only run while playtesting.
+    pub fn active(playtest: Res<Self>) -> bool {
+        playtest.player.is_some()
+    }
+
+    /// Run condition for editor systems that would fight the player for the camera or cursor.
+    pub fn inactive(playtest: Res<Self>) -> bool {
+        playtest.player.is_none()
+    }
+
+    fn toggle(
+        mut playtest: ResMut<Self>,
+        actions: Res<ActionState>,
+        keyboard_input: Res<CurrentKeyboardInput>,
+        mouse_input: Res<CurrentMouseInput>,
+        mut state: ResMut<MulticamState>,
+        mut window: Query<&mut Window, With<PrimaryWindow>>,
+        mut cameras: Query<(Entity, &Multicam, &mut Transform)>,
+        rooms: Query<(&Room, &GlobalTransform)>,
+    ) {
+        let requested = std::mem::take(&mut playtest.requested) || actions.just_pressed(InputAction::Playtest);
+        let leaving = playtest.player.is_some() && (requested || keyboard_input.cancel);
+        let entering = playtest.player.is_none() && requested;
+        let Ok(mut window) = window.single_mut() else { return; };
+
+        if leaving {
+            if let Some((camera, transform)) = playtest.camera.take()
+                && let Ok((_, _, mut camera_tfm)) = cameras.get_mut(camera) {
+                *camera_tfm = transform;
+            }
+            playtest.player = None;
+            state.maximized = playtest.maximized_before.take();
+            window.cursor_options.grab_mode = CursorGrabMode::None;
+            window.cursor_options.visible = true;
+            info!("{}", get!("playtest.stopped"));
+        } else if entering {
+            // Prefer the perspective view under the cursor, then any perspective view.
+            let camera = mouse_input.in_camera
+                .and_then(|camera| cameras.get(camera).ok())
+                .filter(|(_, multicam, _)| multicam.axis == CameraAxis::None)
+                .or_else(|| cameras.iter().find(|(_, multicam, _)| multicam.axis == CameraAxis::None));
+            let Some((camera, multicam, transform)) = camera else {
+                warn!("{}", get!("playtest.no_camera"));
+                return;
+            };
+
+            let solids = room_volumes(&rooms);
+            let position = transform.translation - Vec3::Y * EYE_OFFSET;
+            if !is_free(&solids, position, HULL_HALF_EXTENTS) {
+                warn!("{}", get!("playtest.outside"));
+                return;
+            }
+            let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+            playtest.player = Some(Player {
+                position,
+                velocity: Vec3::ZERO,
+                yaw,
+                pitch: pitch.clamp(-MAX_PITCH, MAX_PITCH),
+                grounded: false,
+            });
+            playtest.camera = Some((camera, *transform));
+            playtest.maximized_before = state.maximized;
+            state.maximized = Some(multicam.id);
+            window.cursor_options.grab_mode = CursorGrabMode::Locked;
+            window.cursor_options.visible = false;
+            info!("{}", get!("playtest.started"));
+        }
+    }
+
+    fn simulate(
+        mut playtest: ResMut<Self>,
+        time: Res<Time>,
+        keyboard_input: Res<CurrentKeyboardInput>,
+        mouse_input: Res<CurrentMouseInput>,
+        preferences: Res<Preferences>,
+        rooms: Query<(&Room, &GlobalTransform)>,
+        mut cameras: Query<&mut Transform, With<Multicam>>,
+    ) {
+        let Some((camera, _)) = playtest.camera else { return; };
+        let Some(player) = playtest.player.as_mut() else { return; };
+        let dt = time.delta_secs().min(0.1);
+
+        let look = preferences.camera.look_delta(mouse_input.delta_pos) * LOOK_SENSITIVITY;
+        player.yaw -= look.x;
+        player.pitch = (player.pitch - look.y).clamp(-MAX_PITCH, MAX_PITCH);
+
+        let facing = Quat::from_rotation_y(player.yaw);
+        let wish = (facing * Vec3::NEG_Z * keyboard_input.forward() + facing * Vec3::X * keyboard_input.right())
+            .normalize_or_zero() * WALK_SPEED;
+        player.velocity.x = wish.x;
+        player.velocity.z = wish.z;
+        if player.grounded && keyboard_input.jump {
+            player.velocity.y = JUMP_SPEED;
+        }
+        player.velocity.y -= GRAVITY * dt;
+
+        let solids = room_volumes(&rooms);
+        step(&solids, player, dt);
+
+        if let Ok(mut transform) = cameras.get_mut(camera) {
+            *transform = player.eye();
+        }
+    }
+}
+
+/// The space inside every room, which is where the player can be. Everything else is solid.
+/// Rotated rooms are treated as their axis-aligned bounds.
+fn room_volumes(rooms: &Query<(&Room, &GlobalTransform)>) -> Vec<(Vec3, Vec3)> {
+    rooms
+        .iter()
+        .map(|(room, tfm)| {
+            let (min, max) = room.bounds();
+            let (a, b) = (tfm.transform_point(min), tfm.transform_point(max));
+            (a.min(b), a.max(b))
+        })
+        .collect()
+}
+
+/// Whether a box fits in the rooms, however thin the walls between them.
+/// The faces of the rooms it touches cut the box into cells, and it fits if every cell is inside one of those rooms.
+fn is_free(rooms: &[(Vec3, Vec3)], center: Vec3, half: Vec3) -> bool {
+    let (min, max) = (center - half, center + half);
+    let touching: Vec<&(Vec3, Vec3)> = rooms
+        .iter()
+        .filter(|(room_min, room_max)| room_min.cmple(max).all() && room_max.cmpge(min).all())
+        .collect();
+    let cuts = |axis: usize| {
+        let mut cuts = vec![min[axis], max[axis]];
+        for (room_min, room_max) in &touching {
+            cuts.extend([room_min[axis], room_max[axis]].into_iter().filter(|cut| *cut > min[axis] && *cut < max[axis]));
+        }
+        cuts.sort_by(f32::total_cmp);
+        cuts.dedup();
+        cuts
+    };
+    let (xs, ys, zs) = (cuts(0), cuts(1), cuts(2));
+    xs.windows(2).all(|x| ys.windows(2).all(|y| zs.windows(2).all(|z| {
+        let cell = Vec3::new(x[0] + x[1], y[0] + y[1], z[0] + z[1]) / 2.0;
+        touching.iter().any(|(room_min, room_max)| cell.cmpge(*room_min).all() && cell.cmple(*room_max).all())
+    })))
+}
+
+/// Moves along one axis as far as the box fits. Returns where it ended up and whether it was stopped short.
+fn move_axis(rooms: &[(Vec3, Vec3)], center: Vec3, half: Vec3, axis: usize, delta: f32) -> (Vec3, bool) {
+    let moved = |fraction: f32| {
+        let mut point = center;
+        point[axis] += delta * fraction;
+        point
+    };
+    if is_free(rooms, moved(1.0), half) {
+        return (moved(1.0), false);
+    }
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..SWEEP_STEPS {
+        let middle = (low + high) / 2.0;
+        if is_free(rooms, moved(middle), half) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (moved(low), true)
+}
+
+/// Moves across the floor, sliding along walls.
+fn walk(rooms: &[(Vec3, Vec3)], center: Vec3, half: Vec3, delta: Vec3) -> (Vec3, bool) {
+    let (center, blocked_x) = move_axis(rooms, center, half, 0, delta.x);
+    let (center, blocked_z) = move_axis(rooms, center, half, 2, delta.z);
+    (center, blocked_x || blocked_z)
+}
+
+/// Advances the player by `dt`: walking (and stepping up ledges), then falling or jumping.
+fn step(rooms: &[(Vec3, Vec3)], player: &mut Player, dt: f32) {
+    let half = HULL_HALF_EXTENTS;
+    let horizontal = Vec3::new(player.velocity.x, 0.0, player.velocity.z) * dt;
+    let (mut position, blocked) = walk(rooms, player.position, half, horizontal);
+
+    // Quake-style stair stepping: try the same move from a step higher, then drop back down onto the step.
+    if blocked && player.grounded {
+        let (raised, _) = move_axis(rooms, player.position, half, 1, STEP_HEIGHT);
+        let (stepped, _) = walk(rooms, raised, half, horizontal);
+        let (stepped, _) = move_axis(rooms, stepped, half, 1, -(raised.y - player.position.y));
+        let progress = |point: Vec3| (point - player.position).with_y(0.0).length();
+        if progress(stepped) > progress(position) {
+            position = stepped;
+        }
+    }
+
+    let (position, blocked) = move_axis(rooms, position, half, 1, player.velocity.y * dt);
+    player.grounded = blocked && player.velocity.y <= 0.0;
+    if blocked {
+        player.velocity.y = 0.0;
+    }
+    player.position = position;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(position: Vec3) -> Player {
+        Player { position, velocity: Vec3::ZERO, yaw: 0.0, pitch: 0.0, grounded: false }
+    }
+
+    fn run(rooms: &[(Vec3, Vec3)], player: &mut Player, velocity: Vec3, seconds: f32) {
+        for _ in 0..(seconds * 60.0) as usize {
+            player.velocity.x = velocity.x;
+            player.velocity.z = velocity.z;
+            player.velocity.y -= GRAVITY / 60.0;
+            step(rooms, player, 1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn test_falls_onto_floor() {
+        let rooms = [(Vec3::new(-5.0, 0.0, -5.0), Vec3::new(5.0, 3.0, 5.0))];
+        let mut player = player(Vec3::new(0.0, 2.0, 0.0));
+        run(&rooms, &mut player, Vec3::ZERO, 1.0);
+        assert!(player.grounded);
+        assert!((player.position.y - HULL_HALF_EXTENTS.y).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_walls_stop_the_player() {
+        let rooms = [(Vec3::new(-5.0, 0.0, -5.0), Vec3::new(5.0, 3.0, 5.0))];
+        let mut player = player(Vec3::new(0.0, HULL_HALF_EXTENTS.y, 0.0));
+        run(&rooms, &mut player, Vec3::new(WALK_SPEED, 0.0, 0.0), 3.0);
+        assert!(player.position.x <= 5.0 - HULL_HALF_EXTENTS.x + 1e-3);
+        assert!(player.position.x > 4.5);
+    }
+
+    #[test]
+    fn test_steps_up_stairs_but_not_walls() {
+        let floor = (Vec3::new(-5.0, 0.0, -1.0), Vec3::new(0.0, 3.0, 1.0));
+        let step_up = (Vec3::new(0.0, 0.25, -1.0), Vec3::new(5.0, 3.0, 1.0));
+        let mut climber = player(Vec3::new(-2.0, HULL_HALF_EXTENTS.y, 0.0));
+        run(&[floor, step_up], &mut climber, Vec3::new(WALK_SPEED, 0.0, 0.0), 1.0);
+        assert!(climber.position.x > 1.0);
+        assert!((climber.position.y - (0.25 + HULL_HALF_EXTENTS.y)).abs() < 0.01);
+
+        let ledge = (Vec3::new(0.0, 1.0, -1.0), Vec3::new(5.0, 3.0, 1.0));
+        let mut blocked = player(Vec3::new(-2.0, HULL_HALF_EXTENTS.y, 0.0));
+        run(&[floor, ledge], &mut blocked, Vec3::new(WALK_SPEED, 0.0, 0.0), 1.0);
+        assert!(blocked.position.x < 0.0);
+    }
+
+    #[test]
+    fn test_thin_walls_block_the_hull() {
+        let center = Vec3::new(0.0, HULL_HALF_EXTENTS.y, 0.0);
+        // The rooms are 0.1 apart, far less than the hull is wide, but that sliver is still solid wall.
+        let split = [
+            (Vec3::new(-5.0, 0.0, -5.0), Vec3::new(0.1, 3.0, 5.0)),
+            (Vec3::new(0.2, 0.0, -5.0), Vec3::new(5.0, 3.0, 5.0)),
+        ];
+        assert!(!is_free(&split, center, HULL_HALF_EXTENTS));
+
+        // Once the rooms touch, the hull fits across the seam.
+        let joined = [
+            (Vec3::new(-5.0, 0.0, -5.0), Vec3::new(0.1, 3.0, 5.0)),
+            (Vec3::new(0.1, 0.0, -5.0), Vec3::new(5.0, 3.0, 5.0)),
+        ];
+        assert!(is_free(&joined, center, HULL_HALF_EXTENTS));
+    }
+
+    #[test]
+    fn test_ceiling_stops_jump() {
+        let rooms = [(Vec3::new(-5.0, 0.0, -5.0), Vec3::new(5.0, 2.0, 5.0))];
+        let mut player = player(Vec3::new(0.0, HULL_HALF_EXTENTS.y, 0.0));
+        player.velocity.y = JUMP_SPEED;
+        run(&rooms, &mut player, Vec3::ZERO, 0.3);
+        assert!(player.position.y + HULL_HALF_EXTENTS.y <= 2.0 + 1e-3);
+    }
+}
@@ -3,9 +3,18 @@ use bevy::app::App;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 use bevy_egui::{egui, EguiContextPass, EguiContexts};
+use bevy_egui::egui::Ui;
+use strum::IntoEnumIterator;
+use crate::common::cuboid::CuboidPoint;
+use crate::editor::palette::{Operation, OperationsExt};
+use crate::editor::problems::{Problem, ProblemSource, Problems, Severity};
 use crate::get;
 use crate::editor::input::{CurrentKeyboardInput, CurrentMouseInput};
+use crate::editor::layers::LayerTint;
 use crate::editor::multicam::{CameraAxis, Multicam};
+use crate::tool::playtest::Playtest;
+use crate::tool::selection::EditorSelectable;
+use crate::tool::snap::{SnapSettings, SnapTarget, Snapper};
 use crate::tool::Tools;
 
 pub struct RoomPlugin;
@@ -20,6 +29,10 @@ impl Plugin for RoomPlugin {
                 RoomTool::confirm_window,
             ).run_if(in_state(Tools::Room)))
             .add_systems(Startup, RoomTool::init)
+            .add_operation(Operation::new("palette.operations.debug_room", |world| {
+                let mut tool = world.resource_mut::<RoomTool>();
+                tool.debug_window = !tool.debug_window;
+            }))
             .add_systems(Update, (
                 RoomTool::interface,
                 RoomTool::draw_active,
@@ -28,14 +41,15 @@ impl Plugin for RoomPlugin {
                 RoomTool::handle_dragging,
                 RoomTool::cancel,
                 RoomTool::create_active_room,
-                ).run_if(in_state(Tools::Room)))
+                ).run_if(in_state(Tools::Room)).run_if(Playtest::inactive))
+            .add_systems(Update, (Room::rebuild_meshes, Room::bake_geometry))
             .add_systems(OnExit(Tools::Room), RoomTool::despawn_handles)
         ;
     }
 }
 
 #[derive(Resource)]
-struct RoomTool {
+pub struct RoomTool {
     debug_window: bool,
     debug_show_points: bool,
     debug_show_cursor: bool,
@@ -47,8 +61,6 @@ struct RoomTool {
     handle_mesh: Option<Handle<Mesh>>,
     handle_idle_material: Option<Handle<StandardMaterial>>,
     handle_highlight_material: Option<Handle<StandardMaterial>>,
-    snap: bool,
-    snap_granularity: f32,
     drag_start: Option<Vec3>,
     drag_handle_entity: Option<Entity>,
     drag_handle_start: Option<Vec3>,
@@ -68,8 +80,6 @@ impl Default for RoomTool {
             handle_mesh: None,
             handle_idle_material: None,
             handle_highlight_material: None,
-            snap: true,
-            snap_granularity: 0.1,
             drag_start: None,
             drag_handle_entity: None,
             drag_handle_start: None,
@@ -165,6 +175,7 @@ impl RoomTool {
         mut gizmos: Gizmos,
         cameras: Query<(Entity, &Transform, &GlobalTransform, &Multicam, &Projection, &Camera)>,
         mouse_input: Res<CurrentMouseInput>,
+        mut snapper: Snapper,
     ) {
         if tool.debug_show_points {
             let last_color = Color::srgb_u8(0, 255, 0);
@@ -186,7 +197,7 @@ impl RoomTool {
         };
         if let Some(camera_entity) = mouse_input.in_camera {
             if let Some(world_pos) = mouse_input.world_pos {
-                for (entity, tfm, g_tfm, multicam, _, cam) in cameras {
+                for (entity, tfm, g_tfm, multicam, projection, cam) in cameras {
                     if camera_entity == entity && multicam.axis != CameraAxis::None {
                         let world_pos = world_pos.origin;
                         if tool.debug_show_cursor {
@@ -194,22 +205,17 @@ impl RoomTool {
                             gizmos.sphere(world_pos, 0.1, color);
                         }
                         
-                        let cursor = match multicam.axis {
-                            CameraAxis::None => panic!("{}", get!("debug.room.invalid_cursor")),
-                            CameraAxis::X => Vec3::new(suggestion.x, world_pos.y, world_pos.z),
-                            CameraAxis::Y => Vec3::new(world_pos.x, suggestion.y, world_pos.z),
-                            CameraAxis::Z => Vec3::new(world_pos.x, world_pos.y, suggestion.z),
+                        // The view only says where the cursor is across the screen; its depth comes from the suggestion.
+                        let Some(depth) = multicam.axis.index() else {
+                            panic!("{}", get!("debug.room.invalid_cursor"));
                         };
-                        let cursor = if tool.snap {
-                            let g = tool.snap_granularity;
-                            Vec3::new(
-                                f32::ceil(cursor.x / g) * g,
-                                f32::ceil(cursor.y / g) * g,
-                                f32::ceil(cursor.z / g) * g
-                            )
-                        } else {
-                            cursor
+                        let mut cursor = world_pos;
+                        cursor[depth] = suggestion[depth];
+                        let tolerance = match projection {
+                            Projection::Orthographic(ortho) => ortho.scale * snapper.settings().radius_pixels,
+                            _ => 0.0,
                         };
+                        let cursor = snapper.snap(cursor, tolerance, Some(depth), &[]);
                         if let Some(min) = tool.active_min {
                             if tool.active_max.is_none() {
                                 let color = Color::srgb_u8(40, 40, 200);
@@ -255,10 +261,14 @@ impl RoomTool {
     
     fn draw_room_bounds(
         mut gizmos: Gizmos,
-        rooms: Query<(Entity, &Room)>,
+        rooms: Query<(&Room, &InheritedVisibility, Option<&LayerTint>)>,
     ) {
         let color = Color::srgb_u8(100, 100, 100);
-        for (_, room) in rooms {
+        for (room, visibility, tint) in rooms {
+            if !visibility.get() {
+                continue;
+            }
+            let color = tint.filter(|tint| tint.is_tinted()).map_or(color, |tint| tint.0);
             Self::bounds_gizmo(&mut gizmos, room.min, room.max, color);
         }
     }
@@ -390,6 +400,7 @@ impl RoomTool {
         mouse_input: Res<CurrentMouseInput>,
         mut commands: Commands,
         mut tool: ResMut<Self>,
+        snap: Res<SnapSettings>,
     ) {
         let window = window.single();
         if window.is_err() {
@@ -470,10 +481,10 @@ impl RoomTool {
                         info!("{}", diff);
                         let active_min = tool.active_min.clone().unwrap();
                         let active_max = tool.active_max.clone().unwrap();
-                        let g = tool.snap_granularity;
+                        let g = snap.granularity;
                         match &handle.axis {
                             HandleAxis::MinX => {
-                                let new_x = f32::min(f32::ceil((drag_handle_start.x - diff.x) / g) * g, active_max.x - g);
+                                let new_x = f32::min(snap.snap_scalar(drag_handle_start.x - diff.x), active_max.x - g);
                                 info!("{}", new_x);
                                 tool.active_min = Some(Vec3::new(
                                     new_x,
@@ -483,7 +494,7 @@ impl RoomTool {
                                 tfm.translation.x = new_x;
                             },
                             HandleAxis::MaxX => {
-                                let new_x = f32::max(f32::ceil((drag_handle_start.x - diff.x) / g) * g, active_min.x + g);
+                                let new_x = f32::max(snap.snap_scalar(drag_handle_start.x - diff.x), active_min.x + g);
                                 info!("{}", new_x);
                                 tool.active_max = Some(Vec3::new(
                                     new_x,
@@ -493,7 +504,7 @@ impl RoomTool {
                                 tfm.translation.x = new_x;
                             },
                             HandleAxis::MinY => {
-                                let new_y = f32::min(f32::ceil((drag_handle_start.y - diff.y) / g) * g, active_max.y - g);
+                                let new_y = f32::min(snap.snap_scalar(drag_handle_start.y - diff.y), active_max.y - g);
                                 info!("{}", new_y);
                                 tool.active_min = Some(Vec3::new(
                                     active_min.x,
@@ -503,7 +514,7 @@ impl RoomTool {
                                 tfm.translation.y = new_y;
                             },
                             HandleAxis::MaxY => {
-                                let new_y = f32::max(f32::ceil((drag_handle_start.y - diff.y) / g) * g, active_min.y + g);
+                                let new_y = f32::max(snap.snap_scalar(drag_handle_start.y - diff.y), active_min.y + g);
                                 info!("{}", new_y);
                                 tool.active_max = Some(Vec3::new(
                                     active_max.x,
@@ -513,7 +524,7 @@ impl RoomTool {
                                 tfm.translation.y = new_y;
                             },
                             HandleAxis::MinZ => {
-                                let new_z = f32::min(f32::ceil((drag_handle_start.z - diff.z) / g) * g, active_max.z - g);
+                                let new_z = f32::min(snap.snap_scalar(drag_handle_start.z - diff.z), active_max.z - g);
                                 info!("{}", new_z);
                                 tool.active_min = Some(Vec3::new(
                                     active_min.x,
@@ -523,7 +534,7 @@ impl RoomTool {
                                 tfm.translation.z = new_z;
                             },
                             HandleAxis::MaxZ => {
-                                let new_z = f32::max(f32::ceil((drag_handle_start.z - diff.z) / g) * g, active_min.z + g);
+                                let new_z = f32::max(snap.snap_scalar(drag_handle_start.z - diff.z), active_min.z + g);
                                 info!("{}", new_z);
                                 tool.active_max = Some(Vec3::new(
                                     active_max.x,
@@ -572,6 +583,12 @@ impl RoomTool {
         tool.handles_active = false;
     }
     
+    pub fn options_ui(&mut self, ui: &mut Ui, snap: &mut SnapSettings) {
+        snap.grid_ui(ui);
+        ui.separator();
+        ui.checkbox(&mut self.debug_window, get!("room.options.debug"));
+    }
+
     fn debug_window(
         mut contexts: EguiContexts,
         mut tool: ResMut<Self>,
@@ -597,10 +614,6 @@ impl RoomTool {
             ui.heading(get!("debug.room.gizmos"));
             ui.checkbox(&mut tool.debug_show_points, get!("debug.room.show_points"));
             ui.checkbox(&mut tool.debug_show_cursor, get!("debug.room.show_cursor"));
-            
-            ui.heading(get!("debug.room.snap.title"));
-            ui.checkbox(&mut tool.snap, get!("debug.room.snap.active"));
-            ui.add(egui::Slider::new(&mut tool.snap_granularity, 0.0..=0.2).text(get!("debug.room.snap.granularity")));
         });
     }
 }
@@ -642,11 +655,58 @@ impl Room {
         }
     }
     
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        (self.min, self.max)
+    }
+
+    /// The world-space box around the room, wherever its transform has put it.
+    pub fn world_bounds(&self, tfm: &GlobalTransform) -> (Vec3, Vec3) {
+        self.extremes()
+            .into_iter()
+            .map(|corner| tfm.transform_point(corner))
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), corner| (min.min(corner), max.max(corner)))
+    }
+
+    /// The room's corners and edge midpoints, in world space.
+    pub fn snap_points(&self, tfm: &GlobalTransform) -> Vec<(SnapTarget, Vec3)> {
+        CuboidPoint::iter()
+            .filter_map(|point| {
+                let target = if point.is_corner() {
+                    SnapTarget::Corner
+                } else if point.is_edge_center() {
+                    SnapTarget::EdgeMidpoint
+                } else {
+                    return None;
+                };
+                Some((target, tfm.transform_point(point.resolve_in_bounds(self.min, self.max))))
+            })
+            .collect()
+    }
+
+    /// Sets the corners of the room, swapping any components that are the wrong way round.
+    pub fn set_bounds(&mut self, a: Vec3, b: Vec3) {
+        self.min = a.min(b);
+        self.max = a.max(b);
+    }
+
+    /// Rooms bake their bounds into their mesh, so it has to be rebuilt whenever they change.
+    fn rebuild_meshes(
+        rooms: Query<(Ref<Room>, &Mesh3d)>,
+        mut meshes: ResMut<Assets<Mesh>>,
+    ) {
+        for (room, mesh) in &rooms {
+            if room.is_changed() && !room.is_added()
+                && let Some(mesh) = meshes.get_mut(&mesh.0) {
+                *mesh = room.mesh();
+            }
+        }
+    }
+
     pub fn spawn(
         self, commands: &mut Commands,
         mut meshes: ResMut<Assets<Mesh>>,
         mut materials: ResMut<Assets<StandardMaterial>>,
-    ) {
+    ) -> Entity {
         let mesh = meshes.add(self.mesh());
         let material = materials.add(StandardMaterial {
             base_color: Color::srgb_u8(255, 255, 255),
@@ -656,7 +716,8 @@ impl Room {
             self,
             Mesh3d(mesh),
             MeshMaterial3d(material),
-            ));
+            EditorSelectable::new("Room"),
+            )).id()
     }
     
     pub fn mesh(&self) -> Mesh {
@@ -746,6 +807,85 @@ impl Room {
         messages
     }
     
+    pub fn problems(&self, my_entity: Entity) -> Vec<Problem> {
+        let mut problems: Vec<Problem> = self.messages(my_entity)
+            .into_iter()
+            .map(|message| Problem::new(Severity::Warning, message, ProblemSource::Entity(my_entity)))
+            .collect();
+        if (self.max - self.min).min_element() <= 0.0 {
+            problems.push(Problem::new(
+                Severity::Error,
+                get!("room.messages.empty", "me", my_entity),
+                ProblemSource::Entity(my_entity),
+            ));
+        }
+        problems
+    }
+
+    /// Works out which rooms are hidden inside others, and reports rooms that partly overlap.
+    fn bake_geometry(
+        mut events: EventReader<CalculateRoomGeometry>,
+        mut rooms: Query<(Entity, &mut Room, &GlobalTransform)>,
+        mut problems: ResMut<Problems>,
+    ) {
+        if events.read().count() == 0 {
+            return;
+        }
+        let (entities, snapshot): (Vec<Entity>, Vec<Room>) = rooms
+            .iter()
+            .map(|(entity, room, tfm)| {
+                let (min, max) = room.world_bounds(tfm);
+                (entity, Room::new(min, max))
+            })
+            .unzip();
+
+        let (ghosts, overlaps) = Self::intersections(&snapshot);
+        for (entity, ghost) in entities.iter().zip(ghosts) {
+            let ghost = ghost.map(|index| entities[index]);
+            if let Ok((_, mut room, _)) = rooms.get_mut(*entity)
+                && room.ghost != ghost {
+                room.ghost = ghost;
+            }
+        }
+        let overlaps = overlaps
+            .into_iter()
+            .map(|(left, right)| Problem::new(
+                Severity::Info,
+                get!("room.messages.overlap", "me", entities[left], "other", entities[right]),
+                ProblemSource::Entity(entities[left]),
+            ))
+            .collect();
+        problems.set_bake_problems(overlaps);
+    }
+
+    /// For each room, the room it's hidden inside, if any, and every pair of rooms that partly overlap.
+    /// Rooms are referred to by their index in `rooms`.
+    fn intersections(rooms: &[Room]) -> (Vec<Option<usize>>, Vec<(usize, usize)>) {
+        let mut ghosts = vec![None; rooms.len()];
+        let mut overlaps = Vec::new();
+        for (i, left) in rooms.iter().enumerate() {
+            for (j, right) in rooms.iter().enumerate().skip(i + 1) {
+                match Room::test_intersection(left, right) {
+                    IntersectionResult::LeftEngulfsRight | IntersectionResult::Identical => {
+                        ghosts[j].get_or_insert(i);
+                    }
+                    IntersectionResult::RightEngulfsLeft => {
+                        ghosts[i].get_or_insert(j);
+                    }
+                    IntersectionResult::Intersection => {
+                        // Rooms that only share a face count as intersecting, but that's how rooms are meant to join up.
+                        let shared = left.max.min(right.max) - left.min.max(right.min);
+                        if shared.min_element() > 0.0 {
+                            overlaps.push((i, j));
+                        }
+                    }
+                    IntersectionResult::None => {}
+                }
+            }
+        }
+        (ghosts, overlaps)
+    }
+
     pub fn point_inside(&self, point: Vec3) -> bool {
         point.x >= self.min.x && point.x <= self.max.x
         && point.y >= self.min.y && point.y <= self.max.y
@@ -772,21 +912,19 @@ impl Room {
     }
     
     pub fn test_intersection(left: &Self, right: &Self) -> IntersectionResult {
-        let engulfed_right_points = left.count_points_inside(&right.extremes());
-        let engulfed_left_points = right.count_points_inside(&left.extremes());
-        if engulfed_right_points == 0 || engulfed_left_points == 0 {
+        // Counting corners inside each other misses rooms that cross without either having a corner in the other,
+        // and a room inside another has none of the outer room's corners, so compare the boxes directly.
+        if left.max.cmplt(right.min).any() || right.max.cmplt(left.min).any() {
             return IntersectionResult::None
         }
-        if engulfed_right_points == 8 && engulfed_left_points == 8 {
-            return IntersectionResult::Identical
-        }
-        if engulfed_right_points == 8 {
-            return IntersectionResult::LeftEngulfsRight
+        let left_engulfs_right = left.min.cmple(right.min).all() && left.max.cmpge(right.max).all();
+        let right_engulfs_left = right.min.cmple(left.min).all() && right.max.cmpge(left.max).all();
+        match (left_engulfs_right, right_engulfs_left) {
+            (true, true) => IntersectionResult::Identical,
+            (true, false) => IntersectionResult::LeftEngulfsRight,
+            (false, true) => IntersectionResult::RightEngulfsLeft,
+            (false, false) => IntersectionResult::Intersection,
         }
-        if engulfed_left_points == 8 {
-            return IntersectionResult::RightEngulfsLeft
-        }
-        IntersectionResult::Intersection
     }
 }
 
@@ -808,6 +946,7 @@ pub struct CreateRoom;
 mod tests {
     use bevy::ecs::relationship::RelationshipSourceCollection;
     use bevy::prelude::*;
+    use crate::tool::selection::SelectionPlugin;
     use super::*;
     
     #[test]
@@ -848,4 +987,60 @@ mod tests {
         assert!(!room.point_inside(Vec3::new(-1.1, 0.5, 0.5)));
         assert!(!room.point_inside(Vec3::new(0.5, 0.5, -1.1)));
     }
+
+    #[test]
+    fn test_edited_rooms_update_their_bounds() {
+        let mut app = App::new();
+        app
+            .add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .add_systems(Update, (Room::rebuild_meshes, SelectionPlugin::update_mesh_bounds).chain())
+        ;
+        let room = Room::new(Vec3::ZERO, Vec3::ONE);
+        let mesh = app.world_mut().resource_mut::<Assets<Mesh>>().add(room.mesh());
+        let entity = app.world_mut().spawn((room, Mesh3d(mesh), EditorSelectable::new("Room"))).id();
+        app.update();
+        let select = app.world().get::<EditorSelectable>(entity).unwrap();
+        assert_eq!(select.bounds_center, Vec3::splat(0.5));
+
+        app.world_mut().get_mut::<Room>(entity).unwrap().set_bounds(Vec3::ZERO, Vec3::new(4.0, 2.0, 2.0));
+        // The mesh is rebuilt this frame, and its asset event is seen the next.
+        app.update();
+        app.update();
+        let select = app.world().get::<EditorSelectable>(entity).unwrap();
+        assert_eq!(select.bounds_center, Vec3::new(2.0, 1.0, 1.0));
+        assert_eq!(select.bounding_box.half_size, Vec3::new(2.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_moved_rooms_bake_where_they_are() {
+        let room = Room::new(Vec3::ZERO, Vec3::ONE);
+        let moved = GlobalTransform::from(Transform::from_xyz(10.0, 0.0, 0.0).with_scale(Vec3::splat(2.0)));
+        assert_eq!(room.world_bounds(&moved), (Vec3::new(10.0, 0.0, 0.0), Vec3::new(12.0, 2.0, 2.0)));
+
+        // Both rooms start out the same, but one has been moved away: no ghosts and no overlaps.
+        let big = || Room::new(Vec3::ZERO, Vec3::splat(4.0));
+        let moved_away = Room::new(Vec3::ZERO, Vec3::ONE).world_bounds(&GlobalTransform::from_xyz(10.0, 0.0, 0.0));
+        let (ghosts, overlaps) = Room::intersections(&[big(), Room::new(moved_away.0, moved_away.1)]);
+        assert_eq!(ghosts, [None, None]);
+        assert!(overlaps.is_empty());
+
+        // A room far away has been moved into the big one, so it's a ghost.
+        let moved_in = Room::new(Vec3::splat(20.0), Vec3::splat(21.0)).world_bounds(&GlobalTransform::from_xyz(-19.0, -19.0, -19.0));
+        let (ghosts, _) = Room::intersections(&[big(), Room::new(moved_in.0, moved_in.1)]);
+        assert_eq!(ghosts, [None, Some(0)]);
+    }
+
+    #[test]
+    fn test_partial_overlaps_are_reported_but_shared_faces_are_not() {
+        let rooms = [
+            Room::new(Vec3::ZERO, Vec3::splat(2.0)),
+            Room::new(Vec3::ONE, Vec3::splat(3.0)),
+            Room::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(4.0, 2.0, 2.0)),
+        ];
+        let (ghosts, overlaps) = Room::intersections(&rooms);
+        assert_eq!(ghosts, [None, None, None]);
+        assert!(overlaps.contains(&(0, 1)));
+        assert!(!overlaps.contains(&(0, 2)));
+    }
 }
@@ -1,7 +1,16 @@
 use bevy::app::App;
+use bevy::platform::collections::HashSet;
 use bevy::prelude::*;
+use bevy::render::mesh::MeshAabb;
+use bevy::render::view::RenderLayers;
 use bevy::window::PrimaryWindow;
-use crate::editor::input::CurrentMouseInput;
+use bevy_vector_shapes::prelude::*;
+use crate::common::painter;
+use crate::editor::console::{ConsoleCommand, ConsoleCommands, ConsoleCommandsExt};
+use crate::get;
+use crate::editor::input::{CurrentKeyboardInput, CurrentMouseInput, PointerGesture, DRAG_THRESHOLD};
+use crate::editor::multicam::{CameraAxis, Multicam};
+use crate::tool::playtest::Playtest;
 use crate::tool::Tools;
 
 pub struct SelectionPlugin;
@@ -10,37 +19,88 @@ impl Plugin for SelectionPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<SelectionState>()
+            .add_event::<FrameSelection>()
+            .add_event::<FrameBounds>()
             .add_systems(Update, (
-                Self::select.run_if(in_state(Tools::Select)),
-                Self::draw_bounds,
+                Self::select.run_if(in_state(Tools::Select)).run_if(Playtest::inactive),
+                Self::update_mesh_bounds,
+                Self::draw_bounds.after(Self::update_mesh_bounds),
+                Self::draw_marquee.run_if(in_state(Tools::Select)).run_if(Playtest::inactive),
             ))
+            .add_systems(OnExit(Tools::Select), Self::cancel_marquee)
+            .add_console_command("select", ConsoleCommand::new("console.commands.select", Self::select_command)
+                .with_completions(Self::selectable_ids))
         ;
     }
 }
 
 impl SelectionPlugin {
+    /// Selects everything with the given id. Ids can contain spaces, so the arguments are joined back up.
+    /// Like in the viewports, locked and hidden objects can't be selected.
+    fn select_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+        if args.is_empty() {
+            return Err(world.resource::<ConsoleCommands>().get("select").map(ConsoleCommand::usage).unwrap_or_default());
+        }
+        let id = args.join(" ");
+        let mut selectables = world.query::<(Entity, &EditorSelectable, Has<Locked>, Option<&InheritedVisibility>)>();
+        let named: Vec<(Entity, bool)> = selectables
+            .iter(world)
+            .filter(|(_, select, _, _)| select.id.eq_ignore_ascii_case(&id))
+            .map(|(entity, _, locked, visibility)| (entity, !locked && visibility.is_none_or(|visibility| visibility.get())))
+            .collect();
+        if named.is_empty() {
+            return Err(get!("console.no_such_object", "id", id));
+        }
+        let found: Vec<Entity> = named.into_iter().filter(|(_, selectable)| *selectable).map(|(entity, _)| entity).collect();
+        if found.is_empty() {
+            return Err(get!("console.not_selectable", "id", id));
+        }
+        let count = found.len();
+        world.resource_mut::<SelectionState>().apply(SelectionMode::Replace, found);
+        Ok(get!("console.selected", "count", count))
+    }
+
+    fn selectable_ids(world: &mut World) -> Vec<String> {
+        let mut selectables = world.query_filtered::<(&EditorSelectable, Option<&InheritedVisibility>), Without<Locked>>();
+        let mut ids: Vec<String> = selectables
+            .iter(world)
+            .filter(|(_, visibility)| visibility.is_none_or(|visibility| visibility.get()))
+            .map(|(select, _)| select.id.clone())
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
     fn select(
         mut state: ResMut<SelectionState>,
         current_input: Res<CurrentMouseInput>,
-        selectables: Query<&EditorSelectable>,
+        keyboard_input: Res<CurrentKeyboardInput>,
+        selectables: Query<(Entity, &Transform, &EditorSelectable, Option<&InheritedVisibility>), Without<Locked>>,
+        cameras: Query<(&Camera, &GlobalTransform, &Multicam)>,
         mut ray_cast: MeshRayCast,
         mut gizmos: Gizmos,
         window: Query<&Window, With<PrimaryWindow>>,
+        mut frame: EventWriter<FrameSelection>,
     ) {
         let window = window.single();
         if window.is_err() {
             return;
         }
         let window = window.unwrap();
-        
+
         if !window.cursor_options.visible {
             state.hovered = None;
             return;
         }
-        
+
         let filter = |entity| selectables.get(entity).is_ok();
-        let settings = MeshRayCastSettings::default().with_filter(&filter);
-        
+        // Views in wireframe don't draw the meshes themselves, so they're picked whether or not any view shows them.
+        let settings = MeshRayCastSettings::default()
+            .with_filter(&filter)
+            .with_visibility(RayCastVisibility::Visible);
+
+        let mut hit = None;
         if let Some(ray) = current_input.world_pos {
             if let Some((hit_entity, hit_data)) = ray_cast
                 .cast_ray(ray, &settings)
@@ -49,22 +109,135 @@ impl SelectionPlugin {
                     gizmos.line(ray.origin, hit_data.point, Color::srgb_u8(0, 255, 0));
                     gizmos.sphere(Isometry3d::from_translation(hit_data.point), 0.2, Color::srgb_u8(0, 255, 0));
                 }
-                
-                state.hovered = Some(*hit_entity);
-                if current_input.released == Some(MouseButton::Left) {
-                    state.selected = Some(*hit_entity);
-                }
-            } else {
-                if state.debug_probe {
-                    gizmos.line(ray.origin, ray.origin + ray.direction * 100.0, Color::srgb_u8(255, 0, 0));
+                hit = Some(*hit_entity);
+            } else if state.debug_probe {
+                gizmos.line(ray.origin, ray.origin + ray.direction * 100.0, Color::srgb_u8(255, 0, 0));
+            }
+        }
+        state.hovered = hit;
+
+        // Marquees only make sense in the flat views, and only when the drag doesn't start on an object.
+        let left_pressed = current_input.pressed == Some(MouseButton::Left);
+        if left_pressed && current_input.just_pressed && hit.is_none() {
+            if let (Some(camera_entity), Some(cursor)) = (current_input.in_camera, current_input.global_pos) {
+                if let Ok((_, _, multicam)) = cameras.get(camera_entity) {
+                    if multicam.axis != CameraAxis::None {
+                        state.marquee = Some(Marquee {
+                            camera: camera_entity,
+                            start: cursor,
+                            end: cursor,
+                        });
+                    }
                 }
-                state.hovered = None;
-                if current_input.released == Some(MouseButton::Left) {
-                    state.selected = None;
+            }
+        }
+        if left_pressed {
+            if let (Some(marquee), Some(cursor)) = (&mut state.marquee, current_input.global_pos) {
+                if current_input.in_camera == Some(marquee.camera) {
+                    marquee.end = cursor;
                 }
             }
-        } else {
-            state.hovered = None;
+        }
+
+        if current_input.released == Some(MouseButton::Left) {
+            let mode = SelectionMode::from_keyboard(&keyboard_input);
+            let marquee = state.marquee.take();
+            if let Some(marquee) = marquee && marquee.is_drag() {
+                let enclosed = match cameras.get(marquee.camera) {
+                    Ok((camera, camera_tfm, _)) => Self::enclosed_by(&marquee, camera, camera_tfm, &selectables),
+                    Err(_) => vec![],
+                };
+                state.apply(mode, enclosed);
+            } else if let Some(hit) = hit {
+                state.apply(mode, [hit]);
+            } else if mode == SelectionMode::Replace {
+                state.selected.clear();
+            }
+        }
+
+        // Double-clicking an object frames it.
+        if let Some(PointerGesture::Click { chord, double: true }) = current_input.gesture
+            && chord.is(&[MouseButton::Left])
+            && hit.is_some() {
+            frame.write(FrameSelection);
+        }
+    }
+
+    fn enclosed_by(
+        marquee: &Marquee,
+        camera: &Camera,
+        camera_tfm: &GlobalTransform,
+        selectables: &Query<(Entity, &Transform, &EditorSelectable, Option<&InheritedVisibility>), Without<Locked>>,
+    ) -> Vec<Entity> {
+        let rect = marquee.rect();
+        selectables
+            .iter()
+            .filter(|(_, _, _, visibility)| visibility.is_none_or(|visibility| visibility.get()))
+            .filter(|(_, transform, select, _)| {
+                select.world_corners(transform)
+                    .iter()
+                    .all(|corner| match painter::world_to_viewport_ignoring_depth(camera, camera_tfm, *corner) {
+                        Some(point) => rect.contains(point),
+                        None => false,
+                    })
+            })
+            .map(|(entity, _, _, _)| entity)
+            .collect()
+    }
+
+    fn cancel_marquee(mut state: ResMut<SelectionState>) {
+        state.marquee = None;
+    }
+
+    fn draw_marquee(
+        state: Res<SelectionState>,
+        ui_cam: Query<&Camera, (With<Camera2d>, Without<Multicam>)>,
+        mut painter: ShapePainter,
+    ) {
+        let Some(marquee) = &state.marquee else { return; };
+        if !marquee.is_drag() {
+            return;
+        }
+        let Ok(ui_cam) = ui_cam.single() else { return; };
+
+        painter.reset();
+        painter.render_layers = Some(RenderLayers::layer(31));
+        painter.color = Color::srgb_u8(230, 230, 230);
+        painter.thickness = 1.0;
+
+        let rect = marquee.rect();
+        let min = painter::window_to_painter(ui_cam, rect.min);
+        let max = painter::window_to_painter(ui_cam, rect.max);
+        painter::draw_rect(&mut painter, min, max);
+    }
+
+    pub(crate) fn update_mesh_bounds(
+        meshes: Res<Assets<Mesh>>,
+        mut mesh_events: EventReader<AssetEvent<Mesh>>,
+        mut selectables: Query<(Ref<Mesh3d>, &mut EditorSelectable)>,
+    ) {
+        // Meshes edited in place, like rooms rebuilding theirs, don't mark `Mesh3d` as changed; only the asset says so.
+        let modified: HashSet<AssetId<Mesh>> = mesh_events
+            .read()
+            .filter_map(|event| match event {
+                AssetEvent::Modified { id } => Some(*id),
+                _ => None,
+            })
+            .collect();
+        for (mesh, mut select) in &mut selectables {
+            if select.bounds != SelectableBounds::Mesh {
+                continue;
+            }
+            if select.bounds_resolved && !mesh.is_changed() && !modified.contains(&mesh.0.id()) {
+                continue;
+            }
+            // The mesh might not be loaded yet, in which case we try again next frame.
+            let Some(aabb) = meshes.get(&mesh.0).and_then(|mesh| mesh.compute_aabb()) else {
+                continue;
+            };
+            select.bounding_box = Cuboid { half_size: aabb.half_extents.into() };
+            select.bounds_center = aabb.center.into();
+            select.bounds_resolved = true;
         }
     }
 
@@ -77,38 +250,18 @@ impl SelectionPlugin {
         let hovered_color = Color::srgb_u8(230, 230, 230);
         let same_color = Color::srgb_u8(230, 230, 0);
         for (entity, transform, select) in selectables {
-            let same = match (state.selected, state.hovered) {
-                (Some(a), Some(b)) => a == b,
-                _ => false,
-            };
-            if let Some(selected) = state.selected {
-                let color = if same { same_color } else { selected_color };
-                if selected == entity {
-                    Self::draw_bounding_box(&mut gizmos, color, transform, select);
-                }
-            }
-            if let Some(hovered) = state.hovered {
-                if hovered == entity {
-                    Self::draw_bounding_box(&mut gizmos, hovered_color, transform, select);
-                }
+            let hovered = state.hovered == Some(entity);
+            if state.is_selected(entity) {
+                let color = if hovered { same_color } else { selected_color };
+                Self::draw_bounding_box(&mut gizmos, color, transform, select);
+            } else if hovered {
+                Self::draw_bounding_box(&mut gizmos, hovered_color, transform, select);
             }
         }
     }
-    
-    fn local_to_world(transform: &Transform, point: &Vec3) -> Vec3 {
-        transform.transform_point(*point)
-    }
 
     fn draw_bounding_box(gizmos: &mut Gizmos, color: Color, transform: &Transform, select: &EditorSelectable) {
-        let a = transform.transform_point(Vec3::ZERO.with_x(select.bounding_box.half_size.x).with_y(select.bounding_box.half_size.y).with_z(select.bounding_box.half_size.z));
-        let b = transform.transform_point(Vec3::ZERO.with_x(-select.bounding_box.half_size.x).with_y(select.bounding_box.half_size.y).with_z(select.bounding_box.half_size.z));
-        let c = transform.transform_point(Vec3::ZERO.with_x(-select.bounding_box.half_size.x).with_y(-select.bounding_box.half_size.y).with_z(select.bounding_box.half_size.z));
-        let d = transform.transform_point(Vec3::ZERO.with_x(select.bounding_box.half_size.x).with_y(-select.bounding_box.half_size.y).with_z(select.bounding_box.half_size.z));
-
-        let e = transform.transform_point(Vec3::ZERO.with_x(select.bounding_box.half_size.x).with_y(select.bounding_box.half_size.y).with_z(-select.bounding_box.half_size.z));
-        let f = transform.transform_point(Vec3::ZERO.with_x(-select.bounding_box.half_size.x).with_y(select.bounding_box.half_size.y).with_z(-select.bounding_box.half_size.z));
-        let g = transform.transform_point(Vec3::ZERO.with_x(-select.bounding_box.half_size.x).with_y(-select.bounding_box.half_size.y).with_z(-select.bounding_box.half_size.z));
-        let h = transform.transform_point(Vec3::ZERO.with_x(select.bounding_box.half_size.x).with_y(-select.bounding_box.half_size.y).with_z(-select.bounding_box.half_size.z));
+        let [a, b, c, d, e, f, g, h] = select.world_corners(transform);
 
         gizmos.line(a, b, color);
         gizmos.line(b, c, color);
@@ -131,11 +284,228 @@ impl SelectionPlugin {
 pub struct EditorSelectable {
     pub id: String,
     pub bounding_box: Cuboid,
+    pub bounds_center: Vec3,
+    pub bounds: SelectableBounds,
+    bounds_resolved: bool,
+}
+
+/// Asks every viewport to move so that the current selection fills it.
+#[derive(Event)]
+pub struct FrameSelection;
+
+/// Asks every viewport to move so that this box fills it. For things that aren't entities, like editor actions.
+#[derive(Event, Clone, Copy)]
+pub struct FrameBounds {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+/// Locked objects still show up in the outliner, but can't be picked in the viewports or moved by tools.
+#[derive(Component)]
+pub struct Locked;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SelectableBounds {
+    /// Kept in sync with the AABB of the entity's `Mesh3d`.
+    Mesh,
+    /// Set by hand, for things without a mesh like spawn points.
+    Manual,
+}
+
+impl EditorSelectable {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_owned(),
+            bounding_box: Cuboid::from_length(0.0),
+            bounds_center: Vec3::ZERO,
+            bounds: SelectableBounds::Mesh,
+            bounds_resolved: false,
+        }
+    }
+
+    pub fn with_bounds(id: &str, bounding_box: Cuboid) -> Self {
+        Self {
+            id: id.to_owned(),
+            bounding_box,
+            bounds_center: Vec3::ZERO,
+            bounds: SelectableBounds::Manual,
+            bounds_resolved: true,
+        }
+    }
+
+    pub fn set_bounds(&mut self, bounding_box: Cuboid, center: Vec3) {
+        self.bounding_box = bounding_box;
+        self.bounds_center = center;
+        self.bounds = SelectableBounds::Manual;
+        self.bounds_resolved = true;
+    }
+
+    pub fn use_mesh_bounds(&mut self) {
+        self.bounds = SelectableBounds::Mesh;
+        self.bounds_resolved = false;
+    }
+
+    pub fn world_corners(&self, transform: &Transform) -> [Vec3; 8] {
+        let h = self.bounding_box.half_size;
+        [
+            Vec3::new(h.x, h.y, h.z),
+            Vec3::new(-h.x, h.y, h.z),
+            Vec3::new(-h.x, -h.y, h.z),
+            Vec3::new(h.x, -h.y, h.z),
+            Vec3::new(h.x, h.y, -h.z),
+            Vec3::new(-h.x, h.y, -h.z),
+            Vec3::new(-h.x, -h.y, -h.z),
+            Vec3::new(h.x, -h.y, -h.z),
+        ].map(|corner| transform.transform_point(self.bounds_center + corner))
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SelectionMode {
+    Replace,
+    Add,
+    Toggle,
+}
+
+impl SelectionMode {
+    pub fn from_keyboard(keyboard_input: &CurrentKeyboardInput) -> Self {
+        if keyboard_input.toggle {
+            Self::Toggle
+        } else if keyboard_input.modify {
+            Self::Add
+        } else {
+            Self::Replace
+        }
+    }
+}
+
+/// A rectangle being dragged out in one of the orthographic viewports, in window coordinates.
+#[derive(Clone, Copy)]
+pub struct Marquee {
+    pub camera: Entity,
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl Marquee {
+    pub fn rect(&self) -> Rect {
+        Rect::from_corners(self.start, self.end)
+    }
+
+    pub fn is_drag(&self) -> bool {
+        self.start.distance(self.end) >= DRAG_THRESHOLD
+    }
 }
 
 #[derive(Resource, Default)]
 pub struct SelectionState {
     pub hovered: Option<Entity>,
-    pub selected: Option<Entity>,
+    pub selected: HashSet<Entity>,
+    pub marquee: Option<Marquee>,
     debug_probe: bool,
 }
+
+impl SelectionState {
+    pub fn is_selected(&self, entity: Entity) -> bool {
+        self.selected.contains(&entity)
+    }
+
+    /// The world-space bounds around everything selected, out of the given selectables.
+    pub fn bounds<'a>(&self, selectables: impl IntoIterator<Item = (Entity, &'a Transform, &'a EditorSelectable)>) -> Option<(Vec3, Vec3)> {
+        let mut bounds: Option<(Vec3, Vec3)> = None;
+        for (entity, transform, select) in selectables {
+            if !self.is_selected(entity) {
+                continue;
+            }
+            for corner in select.world_corners(transform) {
+                bounds = Some(match bounds {
+                    Some((min, max)) => (min.min(corner), max.max(corner)),
+                    None => (corner, corner),
+                });
+            }
+        }
+        bounds
+    }
+
+    pub fn apply(&mut self, mode: SelectionMode, entities: impl IntoIterator<Item = Entity>) {
+        match mode {
+            SelectionMode::Replace => {
+                self.selected.clear();
+                self.selected.extend(entities);
+            }
+            SelectionMode::Add => {
+                self.selected.extend(entities);
+            }
+            SelectionMode::Toggle => {
+                for entity in entities {
+                    if !self.selected.remove(&entity) {
+                        self.selected.insert(entity);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_selection() {
+        let a = Entity::from_raw(1);
+        let b = Entity::from_raw(2);
+
+        let mut state = SelectionState::default();
+        state.apply(SelectionMode::Replace, [a]);
+        state.apply(SelectionMode::Replace, [b]);
+        assert!(!state.is_selected(a));
+        assert!(state.is_selected(b));
+    }
+
+    #[test]
+    fn test_add_and_toggle_selection() {
+        let a = Entity::from_raw(1);
+        let b = Entity::from_raw(2);
+
+        let mut state = SelectionState::default();
+        state.apply(SelectionMode::Add, [a]);
+        state.apply(SelectionMode::Add, [b]);
+        assert_eq!(state.selected.len(), 2);
+
+        state.apply(SelectionMode::Toggle, [a]);
+        assert!(!state.is_selected(a));
+        assert!(state.is_selected(b));
+
+        state.apply(SelectionMode::Toggle, [a]);
+        assert!(state.is_selected(a));
+    }
+
+    #[test]
+    fn test_marquee_threshold() {
+        let camera = Entity::from_raw(3);
+        let click = Marquee { camera, start: Vec2::new(10.0, 10.0), end: Vec2::new(11.0, 11.0) };
+        let drag = Marquee { camera, start: Vec2::new(10.0, 10.0), end: Vec2::new(40.0, 10.0) };
+        assert!(!click.is_drag());
+        assert!(drag.is_drag());
+        assert!(drag.rect().contains(Vec2::new(20.0, 10.0)));
+    }
+
+    #[test]
+    fn test_select_command_skips_locked_and_hidden() {
+        let mut world = World::new();
+        world.init_resource::<SelectionState>();
+        let open = world.spawn((EditorSelectable::new("Room"), InheritedVisibility::VISIBLE)).id();
+        world.spawn((EditorSelectable::new("Room"), InheritedVisibility::VISIBLE, Locked));
+        world.spawn((EditorSelectable::new("Room"), InheritedVisibility::HIDDEN));
+        world.spawn((EditorSelectable::new("Crate"), InheritedVisibility::VISIBLE, Locked));
+
+        assert!(SelectionPlugin::select_command(&mut world, &["room"]).is_ok());
+        let selection = world.resource::<SelectionState>();
+        assert_eq!(selection.selected.len(), 1);
+        assert!(selection.is_selected(open));
+
+        assert!(SelectionPlugin::select_command(&mut world, &["crate"]).is_err());
+        assert_eq!(SelectionPlugin::selectable_ids(&mut world), vec!["Room".to_owned()]);
+    }
+}
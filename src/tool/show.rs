@@ -2,6 +2,7 @@ use bevy::prelude::*;
 use crate::get;
 use bevy_egui::{egui, EguiContextPass, EguiContexts};
 use crate::editor::multicam::MulticamState;
+use crate::editor::palette::{Operation, OperationsExt};
 
 pub struct ShowPlugin;
 
@@ -9,6 +10,14 @@ impl Plugin for ShowPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_systems(EguiContextPass, Self::ui)
+            .add_operation(Operation::new("palette.operations.show_ortho_cameras", |world| {
+                let mut state = world.resource_mut::<MulticamState>();
+                state.draw_ortho_cameras = !state.draw_ortho_cameras;
+            }))
+            .add_operation(Operation::new("palette.operations.show_perspective_cameras", |world| {
+                let mut state = world.resource_mut::<MulticamState>();
+                state.draw_perspective_cameras = !state.draw_perspective_cameras;
+            }))
         ;
     }
 }
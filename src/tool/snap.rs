@@ -0,0 +1,329 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::Ui;
+use strum_macros::EnumIter;
+use crate::editor::editable::EditorActions;
+use crate::get;
+use crate::tool::room::Room;
+
+pub struct SnapPlugin;
+
+impl Plugin for SnapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SnapSettings>()
+            .init_resource::<SnapHover>()
+            .add_systems(PostUpdate, SnapHover::draw)
+        ;
+    }
+}
+
+/// The kinds of thing a point can snap to.
+#[derive(EnumIter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SnapTarget {
+    Corner,
+    EdgeMidpoint,
+    CuboidPoint,
+    Grid,
+}
+
+impl SnapTarget {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Corner => get!("snap.targets.corner"),
+            Self::EdgeMidpoint => get!("snap.targets.edge_midpoint"),
+            Self::CuboidPoint => get!("snap.targets.cuboid_point"),
+            Self::Grid => get!("snap.targets.grid"),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Self::Corner => Color::srgb_u8(255, 200, 0),
+            Self::EdgeMidpoint => Color::srgb_u8(0, 220, 255),
+            Self::CuboidPoint => Color::srgb_u8(255, 0, 255),
+            Self::Grid => Color::srgb_u8(200, 200, 200),
+        }
+    }
+}
+
+/// Snap settings shared by every tool, so that they all snap to the same things.
+#[derive(Resource)]
+pub struct SnapSettings {
+    pub grid: bool,
+    pub granularity: f32,
+    pub angle: bool,
+    pub angle_step_degrees: f32,
+    pub corners: bool,
+    pub edge_midpoints: bool,
+    pub cuboid_points: bool,
+    /// Targets earlier in the list win over later ones, however close the later ones are.
+    pub priority: [SnapTarget; 4],
+    /// How close (in pixels) the cursor has to be to a corner, midpoint or cuboid point to snap to it.
+    pub radius_pixels: f32,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            grid: true,
+            granularity: 0.1,
+            angle: true,
+            angle_step_degrees: 15.0,
+            corners: true,
+            edge_midpoints: true,
+            cuboid_points: true,
+            priority: [SnapTarget::Corner, SnapTarget::CuboidPoint, SnapTarget::EdgeMidpoint, SnapTarget::Grid],
+            radius_pixels: 10.0,
+        }
+    }
+}
+
+/// Where a point ended up, and what it snapped to, if anything.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Snapped {
+    pub point: Vec3,
+    pub target: Option<SnapTarget>,
+}
+
+impl SnapSettings {
+    pub fn snap_scalar(&self, value: f32) -> f32 {
+        if !self.grid || self.granularity <= 0.0 {
+            return value;
+        }
+        (value / self.granularity).round() * self.granularity
+    }
+
+    pub fn snap_point(&self, point: Vec3) -> Vec3 {
+        Vec3::new(
+            self.snap_scalar(point.x),
+            self.snap_scalar(point.y),
+            self.snap_scalar(point.z),
+        )
+    }
+
+    pub fn snap_angle(&self, radians: f32) -> f32 {
+        if !self.angle || self.angle_step_degrees <= 0.0 {
+            return radians;
+        }
+        let step = self.angle_step_degrees.to_radians();
+        (radians / step).round() * step
+    }
+
+    pub fn enabled(&self, target: SnapTarget) -> bool {
+        match target {
+            SnapTarget::Corner => self.corners,
+            SnapTarget::EdgeMidpoint => self.edge_midpoints,
+            SnapTarget::CuboidPoint => self.cuboid_points,
+            SnapTarget::Grid => self.grid,
+        }
+    }
+
+    fn enabled_mut(&mut self, target: SnapTarget) -> &mut bool {
+        match target {
+            SnapTarget::Corner => &mut self.corners,
+            SnapTarget::EdgeMidpoint => &mut self.edge_midpoints,
+            SnapTarget::CuboidPoint => &mut self.cuboid_points,
+            SnapTarget::Grid => &mut self.grid,
+        }
+    }
+
+    /// Picks what `point` snaps to out of `candidates`, going through the targets in priority order.
+    /// Candidates only count if they're within `tolerance` of the point.
+    /// `depth` is the world axis the view looks down, if it's flat: distances ignore it,
+    /// and the point keeps its own depth whatever it snaps to.
+    pub fn resolve(
+        &self,
+        point: Vec3,
+        tolerance: f32,
+        depth: Option<usize>,
+        candidates: impl IntoIterator<Item = (SnapTarget, Vec3)>,
+    ) -> Snapped {
+        let flatten = |mut v: Vec3| {
+            if let Some(depth) = depth {
+                v[depth] = 0.0;
+            }
+            v
+        };
+        let candidates: Vec<(SnapTarget, Vec3)> = candidates.into_iter().collect();
+        for target in self.priority {
+            if !self.enabled(target) {
+                continue;
+            }
+            let snapped = if target == SnapTarget::Grid {
+                Some(self.snap_point(point))
+            } else {
+                candidates
+                    .iter()
+                    .filter(|(kind, _)| *kind == target)
+                    .map(|(_, candidate)| (flatten(*candidate - point).length(), *candidate))
+                    .filter(|(distance, _)| *distance <= tolerance)
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .map(|(_, candidate)| candidate)
+            };
+            if let Some(mut snapped) = snapped {
+                if let Some(depth) = depth {
+                    snapped[depth] = point[depth];
+                }
+                return Snapped { point: snapped, target: Some(target) };
+            }
+        }
+        Snapped { point, target: None }
+    }
+
+    pub fn grid_ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.grid, get!("snap.grid"));
+        ui.add_enabled(self.grid, egui::DragValue::new(&mut self.granularity)
+            .speed(0.01)
+            .range(0.01..=10.0)
+            .prefix(format!("{}: ", get!("snap.granularity"))));
+        ui.menu_button(get!("snap.targets.title"), |ui| self.targets_ui(ui));
+    }
+
+    /// Turns each target on and off, and moves it up and down the priority list.
+    fn targets_ui(&mut self, ui: &mut Ui) {
+        let mut raise = None;
+        for (index, target) in self.priority.into_iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.add_enabled(index > 0, egui::Button::new("⏶").small()).clicked() {
+                    raise = Some(index);
+                }
+                ui.checkbox(self.enabled_mut(target), target.name());
+            });
+        }
+        if let Some(index) = raise {
+            self.priority.swap(index - 1, index);
+        }
+        ui.separator();
+        ui.add(egui::DragValue::new(&mut self.radius_pixels)
+            .speed(0.5)
+            .range(1.0..=50.0)
+            .prefix(format!("{}: ", get!("snap.targets.radius"))));
+    }
+
+    pub fn angle_ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.angle, get!("snap.angle"));
+        ui.add_enabled(self.angle, egui::DragValue::new(&mut self.angle_step_degrees)
+            .speed(0.5)
+            .range(1.0..=90.0)
+            .prefix(format!("{}: ", get!("snap.angle_step"))));
+    }
+}
+
+/// What the last snap this frame landed on, drawn as a marker so you can see which target won.
+#[derive(Resource, Default)]
+pub struct SnapHover {
+    hover: Option<(Snapped, f32)>,
+}
+
+impl SnapHover {
+    fn draw(mut hover: ResMut<Self>, mut gizmos: Gizmos) {
+        let Some((snapped, size)) = hover.hover.take() else {
+            return;
+        };
+        let Some(target) = snapped.target else {
+            return;
+        };
+        let color = target.color();
+        let position = Isometry3d::from_translation(snapped.point);
+        match target {
+            SnapTarget::Corner => {
+                gizmos.cuboid(Transform::from_translation(snapped.point).with_scale(Vec3::splat(size)), color);
+            }
+            SnapTarget::EdgeMidpoint => {
+                gizmos.sphere(position, size / 2.0, color);
+            }
+            SnapTarget::CuboidPoint => {
+                gizmos.cross(position, size, color);
+                gizmos.sphere(position, size / 2.0, color);
+            }
+            SnapTarget::Grid => {
+                gizmos.cross(position, size / 2.0, color);
+            }
+        }
+    }
+}
+
+/// Snapping for tools: gathers everything there is to snap to and marks what won.
+#[derive(SystemParam)]
+pub struct Snapper<'w, 's> {
+    settings: Res<'w, SnapSettings>,
+    hover: ResMut<'w, SnapHover>,
+    rooms: Query<'w, 's, (Entity, &'static Room, &'static GlobalTransform, &'static InheritedVisibility)>,
+    actions: Res<'w, EditorActions>,
+}
+
+impl Snapper<'_, '_> {
+    pub fn settings(&self) -> &SnapSettings {
+        &self.settings
+    }
+
+    /// Snaps `point`, ignoring anything on the `exclude`d entities, such as the ones being moved, and anything hidden.
+    /// `tolerance` is in world units; see `SnapSettings::resolve` for `depth`.
+    pub fn snap(&mut self, point: Vec3, tolerance: f32, depth: Option<usize>, exclude: &[Entity]) -> Vec3 {
+        let rooms = self.rooms
+            .iter()
+            .filter(|(entity, _, _, visibility)| !exclude.contains(entity) && visibility.get())
+            .flat_map(|(_, room, tfm, _)| room.snap_points(tfm));
+        let cuboids = self.actions
+            .iter()
+            .flat_map(|action| action.snap_points())
+            .map(|point| (SnapTarget::CuboidPoint, point));
+        let snapped = self.settings.resolve(point, tolerance, depth, rooms.chain(cuboids));
+        self.hover.hover = Some((snapped, tolerance));
+        snapped.point
+    }
+
+    pub fn snap_angle(&self, radians: f32) -> f32 {
+        self.settings.snap_angle(radians)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_rounds_to_nearest() {
+        let snap = SnapSettings::default();
+        let snapped = snap.resolve(Vec3::new(0.14, 0.16, -0.04), 0.0, None, []);
+        assert!(snapped.point.distance(Vec3::new(0.1, 0.2, 0.0)) < 1e-5);
+        assert_eq!(snapped.target, Some(SnapTarget::Grid));
+    }
+
+    #[test]
+    fn test_priority_beats_distance() {
+        let mut snap = SnapSettings::default();
+        let candidates = [
+            (SnapTarget::EdgeMidpoint, Vec3::new(1.0, 0.0, 0.0)),
+            (SnapTarget::Corner, Vec3::new(1.3, 0.0, 0.0)),
+        ];
+        let point = Vec3::new(1.05, 0.0, 0.0);
+        assert_eq!(snap.resolve(point, 0.5, None, candidates).target, Some(SnapTarget::Corner));
+
+        snap.priority.swap(0, 2);
+        assert_eq!(snap.resolve(point, 0.5, None, candidates).target, Some(SnapTarget::EdgeMidpoint));
+
+        snap.edge_midpoints = false;
+        assert_eq!(snap.resolve(point, 0.5, None, candidates).target, Some(SnapTarget::Corner));
+    }
+
+    #[test]
+    fn test_flat_views_keep_depth() {
+        let mut snap = SnapSettings::default();
+        snap.grid = false;
+        let corner = Vec3::new(1.0, 2.0, 5.0);
+        let snapped = snap.resolve(Vec3::new(1.05, 2.0, -3.0), 0.1, Some(2), [(SnapTarget::Corner, corner)]);
+        assert_eq!(snapped.point, Vec3::new(1.0, 2.0, -3.0));
+
+        let missed = snap.resolve(Vec3::new(1.5, 2.0, -3.0), 0.1, Some(2), [(SnapTarget::Corner, corner)]);
+        assert_eq!(missed.target, None);
+
+        snap.grid = true;
+        snap.granularity = 1.0;
+        let gridded = snap.resolve(Vec3::new(1.2, 2.7, -3.4), 0.1, Some(2), []);
+        assert_eq!(gridded.target, Some(SnapTarget::Grid));
+        assert_eq!(gridded.point, Vec3::new(1.0, 3.0, -3.4));
+    }
+}